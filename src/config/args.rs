@@ -1,26 +1,110 @@
-use std::path::PathBuf;
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use anyhow::bail;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use globset::{Glob, GlobSetBuilder};
 
-use super::theme;
+use super::{
+    file_config::{self, FileConfig},
+    theme,
+};
 use crate::data::persistent_data;
+use crate::error::TapError;
+use crate::fuzzy::{self, WalkOpts};
+use crate::player::{is_url, AudioOpts};
 
 type Color = cursive::theme::Color;
 
+#[cfg(not(test))]
 lazy_static::lazy_static! {
     static ref ARGS: Args = Args::parse();
 }
 
+lazy_static::lazy_static! {
+    // The directories to search, verified on startup.
+    static ref ROOTS: Vec<PathBuf> = parse_paths().unwrap_or_default();
+}
+
+// Unit tests use the default arguments, since the process arguments
+// belong to the test harness.
+#[cfg(test)]
+lazy_static::lazy_static! {
+    static ref ARGS: Args = Args::parse_from(["tap"]);
+}
+
 #[derive(PartialEq)]
 pub enum Opts {
+    Analyze,
+    Doctor,
     Automate,
     Print,
+    Dump,
     Set,
     Default,
     None,
 }
 
+// The extra information that can be shown next to each fuzzy-finder item.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Column {
+    // The path of the item, relative to the search root.
+    Path,
+    // The depth of the item below the search root.
+    Depth,
+    // Whether or not the item contains audio.
+    Audio,
+    // The number of subdirectories of the item.
+    Count,
+}
+
+// What the player does once the last track of the playlist has finished.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum EndOfPlaylist {
+    // Stop the player.
+    Stop,
+    // Play the playlist again from the first track.
+    Repeat,
+    // Play a random album from the fuzzy-finder.
+    RandomAlbum,
+    // Quit tap.
+    Quit,
+    // Run the `--end-command`.
+    Command,
+}
+
+// How the albums that are played at random are chosen.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Weighting {
+    // Every album is as likely to be chosen.
+    Uniform,
+    // Albums are chosen in proportion to their number of tracks.
+    TrackCount,
+    // Albums that haven't been played for longer are more likely to be chosen.
+    LeastRecent,
+}
+
+// The album that is played on startup, instead of opening the fuzzy-finder.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Autoplay {
+    // The first album, sorted alphabetically.
+    First,
+    // A random album, chosen with the `--random-weighting`.
+    Random,
+}
+
+// The built-in color schemes.
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Preset {
+    Default,
+    Solarized,
+    Gruvbox,
+    Nord,
+}
+
 #[derive(Parser)]
 #[command(
     author = "Tim Dubbins",
@@ -28,13 +112,34 @@ pub enum Opts {
     version = "0.4.11"
 )]
 pub struct Args {
-    /// The path to play or search on. Defaults to the current working directory
-    path: Option<PathBuf>,
+    /// The path to play or search on, or an http(s) URL to stream. More than
+    /// one directory can be given to search them all.
+    /// Defaults to the current working directory
+    #[arg(value_name = "PATH")]
+    paths: Vec<PathBuf>,
 
     /// Run an automated player without the TUI
     #[arg(short, long, default_value_t = false)]
     automate: bool,
 
+    /// Resume the automated player from the track it last stopped on in the path
+    #[arg(long = "continue", default_value_t = false)]
+    resume: bool,
+
+    /// Don't quit the automated player when Enter is pressed, such as when
+    /// input is piped to tap
+    #[arg(long, default_value_t = false)]
+    no_quit_on_enter: bool,
+
+    /// Analyze the loudness of each album in the path and store the track gains
+    #[arg(long, default_value_t = false)]
+    analyze: bool,
+
+    /// Check every audio file in the path and list those that can't be decoded,
+    /// have no title or artist tag, or have no duration
+    #[arg(long, default_value_t = false)]
+    doctor: bool,
+
     /// Set a default directory using the provided path
     #[arg(short, long, default_value_t = false)]
     set_default: bool,
@@ -49,16 +154,239 @@ pub struct Args {
     #[arg(short, long, default_value_t = false)]
     print_default: bool,
 
+    /// Read settings from the config file at <PATH>. Overrides $TAP_CONFIG and
+    /// the default location, ~/.config/tap/tap.toml
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Print the configuration that tap would run with, and where each value
+    /// comes from, as TOML
+    #[arg(long, default_value_t = false)]
+    dump_config: bool,
+
     /// Exclude directories without audio
-    #[arg(short, long, default_value_t = false)]
+    #[arg(short, long, visible_alias = "audio-only", default_value_t = false)]
     exclude: bool,
 
+    /// List album artists, read from the tags, for the artist search (Ctrl+a)
+    /// instead of directories with subdirectories
+    #[arg(long, default_value_t = false)]
+    album_artist_view: bool,
+
+    /// Sort names ignoring case, diacritics and a leading "The", "A" or "An",
+    /// with numbers sorted by value
+    #[arg(long, default_value_t = false)]
+    natural_sort: bool,
+
+    /// Play directories that contain audio on selection in the fuzzy-finder, even
+    /// if they have subdirectories. Use Ctrl+n to search the subdirectories instead
+    #[arg(long, default_value_t = false)]
+    select_prefers_audio: bool,
+
+    /// Play the discs of an album, in subdirectories such as CD1 and CD2, in
+    /// turn when the album is selected
+    #[arg(long, default_value_t = false)]
+    merge_discs: bool,
+
+    /// Select the only match in the fuzzy-finder once typing pauses, if it
+    /// would be played
+    #[arg(long, default_value_t = false)]
+    auto_select: bool,
+
+    /// Restart the album from the first track when the album that is playing
+    /// is selected in the fuzzy-finder again
+    #[arg(long, default_value_t = false)]
+    reselect_restarts: bool,
+
+    /// Print the directory selected in the fuzzy-finder and exit, rather than
+    /// playing it
+    #[arg(long, default_value_t = false)]
+    print_selection: bool,
+
+    /// Include hidden directories in the search
+    #[arg(long, default_value_t = false)]
+    hidden: bool,
+
+    /// Exclude directories matching the glob <PATTERN>, relative to the search root.
+    /// Can be passed multiple times
+    #[arg(long = "exclude-dir", value_name = "PATTERN", value_parser = parse_glob)]
+    exclude_dir: Vec<Glob>,
+
+    /// Only search directories up to <N> levels below the search root.
+    /// Unlimited by default
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    max_depth: Option<u64>,
+
+    /// Queue the next track when fewer than <SECS> seconds of the current track remain.
+    /// Defaults to queueing the next track as soon as the current track starts
+    #[arg(long, value_name = "SECS")]
+    prebuffer: Option<u64>,
+
+    /// Leave <SECS> seconds of silence between tracks. An album can set its
+    /// own gap in a .tap file
+    #[arg(long, value_name = "SECS")]
+    gap: Option<u64>,
+
+    /// Read the tracks before and after the current track into memory once it
+    /// has played for a moment, so that skipping to them is faster on slow disks
+    #[arg(long, default_value_t = false)]
+    prefetch: bool,
+
+    /// Leave tracks shorter than <SECS> seconds out of the playlist, unless
+    /// every track in the album is that short
+    #[arg(long = "min-length", value_name = "SECS", default_value_t = 0)]
+    min_track_seconds: usize,
+
+    /// Show the fuzzy match score of each item in the fuzzy-finder
+    #[arg(long, hide = true, default_value_t = false)]
+    show_match_score: bool,
+
+    /// Show a compact, single-line player
+    #[arg(long, default_value_t = false)]
+    mini: bool,
+
+    /// Draw the progress bar, borders and symbols with ASCII characters only
+    #[arg(long, default_value_t = false)]
+    ascii: bool,
+
+    /// Show extra information next to each item in the fuzzy-finder
+    #[arg(long, value_name = "COLUMNS", value_delimiter = ',')]
+    columns: Vec<Column>,
+
+    /// The header of the player. The {artist}, {album}, {year} and {title}
+    /// placeholders are replaced with the values of the current track
+    #[arg(long, value_name = "TEMPLATE", default_value = "{artist}  {album} ({year})")]
+    header_format: String,
+
+    /// Show the playback position as a percentage of the track in place of the
+    /// remaining time. Toggled with %
+    #[arg(long, default_value_t = false)]
+    show_percent: bool,
+
+    /// Show the title of the track that plays next in the header of the player
+    #[arg(long, default_value_t = false)]
+    show_next: bool,
+
+    /// Number the tracks 1..N in playlist order, instead of using their tagged track numbers
+    #[arg(long, default_value_t = false)]
+    sequential: bool,
+
+    /// The number of times per second the screen is redrawn while playing,
+    /// in range 1..=30. Lower values use less power
+    #[arg(long, value_name = "FPS", default_value_t = 15,
+        value_parser = clap::value_parser!(u32).range(1..=30))]
+    fps: u32,
+
+    /// The highest volume as a percentage, in range 100..=250. Volumes above
+    /// 100 amplify the audio, which can clip
+    #[arg(long, value_name = "PERCENT", default_value_t = 120,
+        value_parser = clap::value_parser!(u8).range(100..=250))]
+    max_volume: u8,
+
+    /// Prevent the system from sleeping while playing
+    #[arg(long, default_value_t = false)]
+    prevent_sleep: bool,
+
+    /// Run <CMD> in the shell when a new track starts playing. The {path},
+    /// {title}, {artist}, {album} and {cover} placeholders are replaced with the
+    /// quoted values of the track
+    #[arg(long, value_name = "CMD")]
+    on_track_change: Option<String>,
+
+    /// Run <CMD> in the shell when the player is paused. Takes the same
+    /// placeholders as --on-track-change
+    #[arg(long, value_name = "CMD")]
+    on_pause: Option<String>,
+
+    /// Run <CMD> in the shell when the player is stopped. Takes the same
+    /// placeholders as --on-track-change
+    #[arg(long, value_name = "CMD")]
+    on_stop: Option<String>,
+
+    /// What to do when the last track of the playlist has finished
+    #[arg(long, value_name = "ACTION", default_value = "stop")]
+    end_of_playlist: EndOfPlaylist,
+
+    /// How albums are chosen when playing at random
+    #[arg(long, value_name = "WEIGHTING", default_value = "uniform")]
+    random_weighting: Weighting,
+
+    /// Play the first or a random album on startup instead of opening the
+    /// fuzzy-finder, which can still be opened with Tab
+    #[arg(long, value_name = "ALBUM")]
+    autoplay: Option<Autoplay>,
+
+    /// Run <CMD> in the shell when the last track has finished, with
+    /// '--end-of-playlist command'. Takes the same placeholders as --on-track-change
+    #[arg(long, value_name = "CMD")]
+    end_command: Option<String>,
+
+    /// Quit after <MINS> minutes without input, unless playing
+    #[arg(long, value_name = "MINS")]
+    idle_timeout: Option<u64>,
+
+    /// Audiobook mode: step 30 seconds with . and , and continue each book from
+    /// the track and position it was left at
+    #[arg(long, default_value_t = false)]
+    audiobook: bool,
+
+    /// Ask before quitting with q while a track is playing or paused. Press q
+    /// twice to quit without asking
+    #[arg(long, default_value_t = false)]
+    confirm_quit: bool,
+
+    /// The command to run in the shell with Ctrl+e, in the directory of the
+    /// selected folder or current album. Defaults to $SHELL
+    #[arg(long, value_name = "CMD")]
+    shell_command: Option<String>,
+
+    /// The text copied with c. The {artist}, {title}, {album} and {elapsed}
+    /// placeholders are replaced with the values of the current track
+    #[arg(long, value_name = "TEMPLATE", default_value = "{artist} – {title} @ {elapsed}")]
+    timestamp_format: String,
+
+    /// Pause playback when the default audio output changes, such as when headphones
+    /// are unplugged. Requires PulseAudio or PipeWire on Linux
+    #[arg(long, default_value_t = false)]
+    pause_on_output_change: bool,
+
+    /// Don't use the alternate screen or erase the loading messages, for
+    /// terminals that don't support it
+    #[arg(long, default_value_t = false)]
+    no_clean_exit: bool,
+
+    /// Write the audio that is played to the WAV file at <FILE>, as well as
+    /// playing it
+    #[arg(long, value_name = "FILE")]
+    record: Option<PathBuf>,
+
+    /// Write the current track and status to <FILE> whenever they change, for
+    /// status bars and streaming software to read
+    #[arg(long, value_name = "FILE")]
+    status_file: Option<PathBuf>,
+
+    /// The line written to the --status-file. The {status}, {artist}, {album},
+    /// {year}, {title}, {track}, {tracks} and {path} placeholders are replaced
+    /// with the values of the current track. Defaults to "{artist} - {title}"
+    #[arg(long, value_name = "TEMPLATE")]
+    status_format: Option<String>,
+
+    /// Report the state of tap as JSON to each client that connects to the
+    /// Unix socket at <PATH>
+    #[arg(long, value_name = "PATH")]
+    socket: Option<PathBuf>,
+
+    /// The built-in color scheme to start with, which --color and the config
+    /// file change colors of. F5 moves to the next one
+    #[arg(long, value_name = "PRESET", default_value = "default")]
+    theme: Preset,
+
     /// Use the terminal background color
     #[arg(short = 'b', long, default_value_t = false)]
     term_bg: bool,
 
     /// Use the terminal foreground and background colors only
-    #[arg(short='c', long, default_value_t = false)]
+    #[arg(short='c', long, visible_alias = "no-color", default_value_t = false)]
     term_color: bool,
 
     /// Set the color scheme with <NAME>=<HEX>
@@ -74,36 +402,317 @@ pub struct Args {
 }
 
 pub fn parse() -> Result<(PathBuf, Opts), anyhow::Error> {
+    FileConfig::load(&mut std::io::stderr())?;
+    warn_unavailable(&mut std::io::stderr());
+    parse_paths()?;
     Ok((parse_path()?, parse_opts()?))
 }
 
+// Warns about the options that are accepted but can't take effect in this
+// build, so that they don't silently do nothing.
+fn warn_unavailable(out: &mut impl Write) {
+    if ARGS.pause_on_output_change && !cfg!(all(target_os = "linux", feature = "output-monitor")) {
+        let _ = writeln!(
+            out,
+            "[tap warning]: `--pause-on-output-change` has no effect\n\
+            - it's only available on Linux, in builds with the `output-monitor` feature"
+        );
+    }
+}
+
+pub fn config_path() -> Option<&'static Path> {
+    ARGS.config.as_deref()
+}
+
 pub fn audio_only() -> bool {
     ARGS.exclude
 }
 
+pub fn album_artist_view() -> bool {
+    ARGS.album_artist_view
+}
+
+pub fn natural_sort() -> bool {
+    ARGS.natural_sort
+}
+
+pub fn select_prefers_audio() -> bool {
+    ARGS.select_prefers_audio
+}
+
+pub fn auto_select() -> bool {
+    ARGS.auto_select
+}
+
+pub fn merge_discs() -> bool {
+    ARGS.merge_discs
+}
+
+pub fn reselect_restarts() -> bool {
+    ARGS.reselect_restarts
+}
+
+pub fn print_selection() -> bool {
+    ARGS.print_selection
+}
+
+pub fn walk_opts() -> WalkOpts {
+    let mut builder = GlobSetBuilder::new();
+    for glob in ARGS.exclude_dir.iter() {
+        builder.add(glob.to_owned());
+    }
+
+    WalkOpts {
+        hidden: ARGS.hidden,
+        exclude: builder.build().unwrap_or_default(),
+        root: search_root(),
+        natural_sort: ARGS.natural_sort,
+        max_depth: ARGS.max_depth.map(|depth| depth as usize),
+    }
+}
+
+// The `--exclude-dir` patterns, as given.
+pub fn exclude_dir() -> Vec<String> {
+    ARGS.exclude_dir.iter().map(|g| g.glob().to_string()).collect()
+}
+
+pub fn prebuffer() -> Option<Duration> {
+    ARGS.prebuffer.map(Duration::from_secs)
+}
+
+pub fn gap() -> Option<Duration> {
+    ARGS.gap.map(Duration::from_secs)
+}
+
+pub fn prefetch() -> bool {
+    ARGS.prefetch
+}
+
+pub fn audio_opts() -> AudioOpts {
+    AudioOpts {
+        merge_discs: ARGS.merge_discs,
+        min_track_seconds: ARGS.min_track_seconds,
+    }
+}
+
+pub fn resume() -> bool {
+    ARGS.resume
+}
+
+pub fn quit_on_enter() -> bool {
+    !ARGS.no_quit_on_enter
+}
+
+pub fn mini() -> bool {
+    ARGS.mini
+}
+
+pub fn ascii() -> bool {
+    ARGS.ascii
+}
+
+pub fn show_match_score() -> bool {
+    ARGS.show_match_score
+}
+
+pub fn columns() -> &'static [Column] {
+    &ARGS.columns
+}
+
+pub fn header_format() -> &'static str {
+    &ARGS.header_format
+}
+
+pub fn show_percent() -> bool {
+    ARGS.show_percent
+}
+
+pub fn show_next() -> bool {
+    ARGS.show_next
+}
+
+pub fn sequential() -> bool {
+    ARGS.sequential
+}
+
+pub fn fps() -> u32 {
+    ARGS.fps
+}
+
+pub fn max_volume() -> u8 {
+    ARGS.max_volume
+}
+
+pub fn prevent_sleep() -> bool {
+    ARGS.prevent_sleep
+}
+
+// The commands run on track change, pause and stop, if any.
+pub fn hooks() -> (Option<&'static str>, Option<&'static str>, Option<&'static str>) {
+    (
+        ARGS.on_track_change.as_deref(),
+        ARGS.on_pause.as_deref(),
+        ARGS.on_stop.as_deref(),
+    )
+}
+
+pub fn end_of_playlist() -> EndOfPlaylist {
+    ARGS.end_of_playlist
+}
+
+pub fn random_weighting() -> Weighting {
+    ARGS.random_weighting
+}
+
+pub fn autoplay() -> Option<Autoplay> {
+    ARGS.autoplay
+}
+
+pub fn end_command() -> Option<&'static str> {
+    ARGS.end_command.as_deref()
+}
+
+pub fn idle_timeout() -> Option<Duration> {
+    ARGS.idle_timeout.map(|mins| Duration::from_secs(mins.saturating_mul(60)))
+}
+
+pub fn audiobook() -> bool {
+    ARGS.audiobook
+}
+
+pub fn confirm_quit() -> bool {
+    ARGS.confirm_quit
+}
+
+pub fn shell_command() -> Option<&'static str> {
+    ARGS.shell_command.as_deref()
+}
+
+pub fn timestamp_format() -> &'static str {
+    &ARGS.timestamp_format
+}
+
+pub fn pause_on_output_change() -> bool {
+    ARGS.pause_on_output_change
+}
+
+// Whether tap draws in the alternate screen and erases its loading messages,
+// so that nothing is left in the scrollback on exit. On unless turned off with
+// `--no-clean-exit` or `clean_exit = false` in the config file.
+pub fn clean_exit() -> bool {
+    !ARGS.no_clean_exit && file_config::get().clean_exit.unwrap_or(true)
+}
+
+pub fn record() -> Option<&'static PathBuf> {
+    ARGS.record.as_ref()
+}
+
+// The file the current track is written to, from `--status-file` or
+// `status_file` in the config file.
+pub fn status_file() -> Option<PathBuf> {
+    ARGS.status_file.to_owned().or_else(|| file_config::get().status_file.to_owned())
+}
+
+// The line written to the status file, from `--status-format` or
+// `status_format` in the config file.
+pub fn status_format() -> &'static str {
+    ARGS.status_format
+        .as_deref()
+        .or(file_config::get().status_format.as_deref())
+        .unwrap_or("{artist} - {title}")
+}
+
+pub fn socket() -> Option<PathBuf> {
+    ARGS.socket.to_owned()
+}
+
+pub fn theme() -> Preset {
+    ARGS.theme
+}
+
 pub fn user_colors() -> (Vec<(String, Color)>, bool) {
     (ARGS.color.to_owned(), ARGS.term_bg)
 }
 
 pub fn term_color() -> bool {
-    ARGS.term_color
+    ARGS.term_color || no_color()
+}
+
+// What caused the terminal colors to be used, if they are.
+pub fn term_color_source() -> Option<&'static str> {
+    match (ARGS.term_color, no_color()) {
+        (true, _) => Some("--term-color"),
+        (false, true) => Some("NO_COLOR or TERM=dumb"),
+        _ => None,
+    }
+}
+
+// Whether the environment asks for monochrome output, either by setting
+// `NO_COLOR` (see https://no-color.org) or by using a dumb terminal.
+fn no_color() -> bool {
+    let no_color = std::env::var_os("NO_COLOR").map_or(false, |v| !v.is_empty());
+    let dumb_term = std::env::var("TERM").map_or(false, |t| t == "dumb");
+    no_color || dumb_term
 }
 
 pub fn search_root() -> PathBuf {
     parse_path().expect("should be verified on startup")
 }
 
+// The directories to search. The first is the `search_root`.
+pub fn search_roots() -> &'static [PathBuf] {
+    &ROOTS
+}
+
+// The search root that `path` is below.
+pub fn root_of(path: &Path) -> PathBuf {
+    fuzzy::root_of(path, search_roots())
+}
+
 fn parse_path() -> Result<PathBuf, anyhow::Error> {
-    let path = match &ARGS.path {
+    let path = match ARGS.paths.first() {
         Some(p) => p.to_owned(),
-        None => match ARGS.default > 0 {
-            true => persistent_data::cached_path()?,
-            false => std::env::current_dir()?,
+        None => match (ARGS.default > 0, &file_config::get().path) {
+            (true, _) => persistent_data::cached_path()?,
+            (false, Some(path)) => path.to_owned(),
+            (false, None) => std::env::current_dir()?,
         }
     };
 
+    checked_path(path)
+}
+
+fn parse_paths() -> Result<Vec<PathBuf>, anyhow::Error> {
+    if ARGS.paths.len() < 2 {
+        return Ok(vec![parse_path()?]);
+    }
+
+    // Roots that are given twice, or are below another root, would list their
+    // directories twice.
+    let mut roots: Vec<PathBuf> = vec![];
+    for path in ARGS.paths.iter() {
+        let path = checked_path(path.to_owned())?;
+        if roots.iter().any(|root| path.starts_with(root)) {
+            continue;
+        }
+        roots.retain(|root| !root.starts_with(&path));
+        roots.push(path);
+    }
+
+    Ok(roots)
+}
+
+fn checked_path(path: PathBuf) -> Result<PathBuf, anyhow::Error> {
+    // Network streams are played as given.
+    if is_url(&path) {
+        return Ok(path);
+    }
+
     if !path.exists() {
-        bail!("'{}' doesn't exist", path.display())
+        bail!(TapError::PathNotFound(format!(
+            "'{}' doesn't exist",
+            path.display()
+        )))
     }
 
     Ok(path.canonicalize()?)
@@ -142,17 +751,34 @@ fn parse_color(s: &str) -> Result<(String, Color), anyhow::Error> {
     }
 }
 
+fn parse_glob(s: &str) -> Result<Glob, anyhow::Error> {
+    match Glob::new(s) {
+        Ok(glob) => Ok(glob),
+        Err(e) => bail!(
+            "{}invalid pattern '{s}' for '--exclude-dir <PATTERN>'\n\n- `{e}`",
+            format_stderr(s),
+        ),
+    }
+}
+
 fn parse_opts() -> Result<Opts, anyhow::Error> {
-    exclude_multiple()?;
-    conflicts_path()?;
+    if let Err(e) = exclude_multiple().and_then(|_| conflicts_path()) {
+        bail!(TapError::InvalidArgument(e.to_string()))
+    }
     
-    if ARGS.automate {
+    if ARGS.dump_config {
+        Ok(Opts::Dump)
+    } else if ARGS.analyze {
+        Ok(Opts::Analyze)
+    } else if ARGS.doctor {
+        Ok(Opts::Doctor)
+    } else if ARGS.automate {
         Ok(Opts::Automate)
     } else if ARGS.set_default {
         Ok(Opts::Set)
     } else if ARGS.print_default {
         Ok(Opts::Print)
-    } else if ARGS.default > 0 && ARGS.path.is_none() {
+    } else if ARGS.default > 0 && ARGS.paths.is_empty() {
         Ok(Opts::Default)
     } else {
         Ok(Opts::None)
@@ -160,29 +786,188 @@ fn parse_opts() -> Result<Opts, anyhow::Error> {
 }
 
 fn exclude_multiple() -> Result<(), anyhow::Error> {
-    if ARGS.automate && ARGS.print_default {
+    if ARGS.analyze && ARGS.automate {
+        bail!("'--analyze' cannot be used with '--automate'")
+    } else if ARGS.doctor && (ARGS.analyze || ARGS.automate) {
+        bail!("'--doctor' cannot be used with '--analyze' or '--automate'")
+    } else if ARGS.doctor && (ARGS.print_default || ARGS.set_default) {
+        bail!("'--doctor' cannot be used with '--print-default' or '--set-default'")
+    } else if ARGS.analyze && ARGS.print_default {
+        bail!("'--analyze' cannot be used with '--print-default'")
+    } else if ARGS.analyze && ARGS.set_default {
+        bail!("'--analyze' cannot be used with '--set-default'")
+    } else if ARGS.automate && ARGS.print_default {
         bail!("'--automate' cannot be used with '--print-default'")
     } else if ARGS.automate && ARGS.set_default {
         bail!("'--automate' cannot be used with '--set-default'")
     } else if ARGS.print_default && ARGS.set_default {
         bail!("'--print-default' cannot be used with '--set-default'")
+    } else if ARGS.resume && !ARGS.automate {
+        bail!("'--continue' requires '--automate'")
+    } else if ARGS.no_quit_on_enter && !ARGS.automate {
+        bail!("'--no-quit-on-enter' requires '--automate'")
+    } else if ARGS.end_of_playlist == EndOfPlaylist::Command && ARGS.end_command.is_none() {
+        bail!("'--end-of-playlist command' requires '--end-command'")
+    } else if ARGS.autoplay.is_some() && ARGS.print_selection {
+        bail!("'--autoplay' cannot be used with '--print-selection'")
     }
 
     Ok(())
 }
 
 fn conflicts_path() -> Result<(), anyhow::Error> {
-    if ARGS.automate && ARGS.path.is_none() {
+    if ARGS.analyze && ARGS.paths.is_empty() {
+            bail!("'--analyze' requires a 'path' argument")
+    } else if ARGS.doctor && ARGS.paths.is_empty() {
+            bail!("'--doctor' requires a 'path' argument")
+    } else if ARGS.automate && ARGS.paths.is_empty() {
             bail!("'--automate' requires a 'path' argument")
-    } else if ARGS.set_default && ARGS.path.is_none() {
+    } else if ARGS.set_default && ARGS.paths.is_empty() {
             bail!("'--set-default' requires a 'path' argument")
-    } else if ARGS.print_default && ARGS.path.is_some() {
+    } else if ARGS.print_default && !ARGS.paths.is_empty() {
             bail!("'--print-default' cannot be used with a 'path' argument")
+    } else if (ARGS.analyze || ARGS.doctor || ARGS.set_default) && ARGS.paths.iter().any(is_url) {
+            bail!("'--analyze', '--doctor' and '--set-default' cannot be used with a URL")
+    } else if ARGS.paths.len() > 1 && (ARGS.analyze || ARGS.doctor || ARGS.automate || ARGS.set_default) {
+            bail!("'--analyze', '--doctor', '--automate' and '--set-default' can only be used with one 'path'")
+    } else if ARGS.paths.len() > 1 && ARGS.paths.iter().any(is_url) {
+            bail!("a URL cannot be used with other paths")
     }
 
     Ok(())
 }
 
+// The configuration that tap runs with, as TOML. Each color is commented with
+// where it was set, and each option that was set on the command line is marked.
+pub fn dump_config() -> String {
+    let mut out = match &file_config::get().source {
+        Some(path) => format!("# config file: {}\n", path.display()),
+        None => String::from("# no config file\n"),
+    };
+    out.push_str(&format!("search_root = {:?}\n", search_root().display().to_string()));
+
+    out.push_str("\n[colors]\n");
+    for (name, color, source) in theme::palette_sources() {
+        let key = match name.contains('+') {
+            true => format!("{name:?}"),
+            false => name.to_string(),
+        };
+        out.push_str(&format!("{key} = {:?}  # {source}\n", hex(color)));
+    }
+
+    out.push_str("\n[options]\n");
+    let defaults = options(&Args::parse_from(["tap"]));
+    for ((name, value), (_, default)) in options(&ARGS).into_iter().zip(defaults) {
+        match value {
+            None => out.push_str(&format!("# {name} is not set\n")),
+            Some(value) if Some(&value) == default.as_ref() => {
+                out.push_str(&format!("{name} = {value}\n"))
+            }
+            Some(value) => out.push_str(&format!("{name} = {value}  # command line\n")),
+        }
+    }
+
+    out
+}
+
+// The options in `args`, by their long name, as TOML values. Options that
+// aren't set are `None`.
+fn options(args: &Args) -> Vec<(&'static str, Option<String>)> {
+    let string = |s: &str| Some(format!("{s:?}"));
+    let optional = |s: &Option<String>| s.as_deref().and_then(string);
+    let list = |v: Vec<String>| Some(format!("[{}]", v.join(", ")));
+
+    vec![
+        ("exclude", Some(args.exclude.to_string())),
+        ("album-artist-view", Some(args.album_artist_view.to_string())),
+        ("natural-sort", Some(args.natural_sort.to_string())),
+        ("select-prefers-audio", Some(args.select_prefers_audio.to_string())),
+        ("merge-discs", Some(args.merge_discs.to_string())),
+        ("auto-select", Some(args.auto_select.to_string())),
+        ("reselect-restarts", Some(args.reselect_restarts.to_string())),
+        ("print-selection", Some(args.print_selection.to_string())),
+        ("hidden", Some(args.hidden.to_string())),
+        (
+            "exclude-dir",
+            list(args.exclude_dir.iter().map(|g| format!("{:?}", g.glob())).collect()),
+        ),
+        ("max-depth", args.max_depth.map(|depth| depth.to_string())),
+        ("prebuffer", args.prebuffer.map(|secs| secs.to_string())),
+        ("gap", args.gap.map(|secs| secs.to_string())),
+        ("prefetch", Some(args.prefetch.to_string())),
+        ("min-length", Some(args.min_track_seconds.to_string())),
+        ("mini", Some(args.mini.to_string())),
+        ("ascii", Some(args.ascii.to_string())),
+        (
+            "columns",
+            list(args.columns.iter().map(|c| format!("\"{c:?}\"").to_lowercase()).collect()),
+        ),
+        ("header-format", string(&args.header_format)),
+        ("show-percent", Some(args.show_percent.to_string())),
+        ("show-next", Some(args.show_next.to_string())),
+        ("sequential", Some(args.sequential.to_string())),
+        ("fps", Some(args.fps.to_string())),
+        ("max-volume", Some(args.max_volume.to_string())),
+        ("prevent-sleep", Some(args.prevent_sleep.to_string())),
+        ("on-track-change", optional(&args.on_track_change)),
+        ("on-pause", optional(&args.on_pause)),
+        ("on-stop", optional(&args.on_stop)),
+        (
+            "end-of-playlist",
+            args.end_of_playlist
+                .to_possible_value()
+                .and_then(|v| string(v.get_name())),
+        ),
+        (
+            "random-weighting",
+            args.random_weighting
+                .to_possible_value()
+                .and_then(|v| string(v.get_name())),
+        ),
+        (
+            "autoplay",
+            args.autoplay
+                .and_then(|a| a.to_possible_value())
+                .and_then(|v| string(v.get_name())),
+        ),
+        ("end-command", optional(&args.end_command)),
+        ("idle-timeout", args.idle_timeout.map(|mins| mins.to_string())),
+        ("audiobook", Some(args.audiobook.to_string())),
+        ("confirm-quit", Some(args.confirm_quit.to_string())),
+        ("shell-command", optional(&args.shell_command)),
+        ("timestamp-format", string(&args.timestamp_format)),
+        ("pause-on-output-change", Some(args.pause_on_output_change.to_string())),
+        ("no-clean-exit", Some(args.no_clean_exit.to_string())),
+        (
+            "record",
+            args.record.as_ref().and_then(|p| string(&p.display().to_string())),
+        ),
+        (
+            "status-file",
+            args.status_file.as_ref().and_then(|p| string(&p.display().to_string())),
+        ),
+        ("status-format", optional(&args.status_format)),
+        (
+            "socket",
+            args.socket.as_ref().and_then(|p| string(&p.display().to_string())),
+        ),
+        (
+            "theme",
+            args.theme.to_possible_value().and_then(|v| string(v.get_name())),
+        ),
+        ("term-bg", Some(args.term_bg.to_string())),
+        ("term-color", Some(args.term_color.to_string())),
+    ]
+}
+
+// The hex value of `color`, or "terminal" for the terminal colors.
+fn hex(color: Color) -> String {
+    match color {
+        Color::Rgb(r, g, b) => format!("{r:02x}{g:02x}{b:02x}"),
+        _ => String::from("terminal"),
+    }
+}
+
 fn is_valid_hex_string(s: &str) -> bool {
     for c in s.chars() {
         if !c.is_digit(16)  {
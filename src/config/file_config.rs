@@ -0,0 +1,269 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::bail;
+
+use super::{args, theme};
+
+type Color = cursive::theme::Color;
+
+// The environment variable that overrides the location of the config file.
+pub const CONFIG_VAR: &str = "TAP_CONFIG";
+
+// The top-level keys of the config file.
+const KEYS: [&str; 5] = [
+    "path",
+    "clean_exit",
+    "status_file",
+    "status_format",
+    "colors",
+];
+
+lazy_static::lazy_static! {
+    // The config file, verified on startup.
+    static ref FILE_CONFIG: FileConfig =
+        FileConfig::load(&mut std::io::sink()).unwrap_or_default();
+}
+
+// The settings read from the config file, which the command line arguments
+// take precedence over.
+#[derive(Debug, Default)]
+pub struct FileConfig {
+    // The file the settings were read from, if any.
+    pub source: Option<PathBuf>,
+    // The directory to search when no path is given.
+    pub path: Option<PathBuf>,
+    // The colors set in the `[colors]` table.
+    pub colors: Vec<(String, Color)>,
+    // Whether the terminal is restored on exit, see `args::clean_exit`.
+    pub clean_exit: Option<bool>,
+    // The file the current track is written to, see `args::status_file`.
+    pub status_file: Option<PathBuf>,
+    // The line written to the status file.
+    pub status_format: Option<String>,
+}
+
+impl FileConfig {
+    // Reads the config file, if there is one. A file that is given with
+    // `--config` or `TAP_CONFIG` must exist, but a file that can't be read only
+    // gives a warning, written to `out`.
+    pub fn load(out: &mut impl Write) -> Result<Self, anyhow::Error> {
+        match Self::find()? {
+            Some(path) => Ok(Self::read(&path, out)),
+            None => Ok(Self::default()),
+        }
+    }
+
+    // The settings in the file at `path`. If the file can't be read the whole
+    // file is ignored, so a warning is written to `out` saying why.
+    pub fn read(path: &Path, out: &mut impl Write) -> Self {
+        Self::deserialize(path).unwrap_or_else(|err| {
+            let _ = writeln!(
+                out,
+                "[tap warning]: {err}\n- using the default settings instead"
+            );
+            Self::default()
+        })
+    }
+
+    // The location of the config file. This is the path given with `--config`,
+    // then `TAP_CONFIG`, then `$XDG_CONFIG_HOME/tap/tap.toml` or
+    // `~/.config/tap/tap.toml` if it exists.
+    pub fn find() -> Result<Option<PathBuf>, anyhow::Error> {
+        let path = match args::config_path() {
+            Some(path) => Some(path.to_owned()),
+            None => std::env::var_os(CONFIG_VAR)
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from),
+        };
+
+        match path {
+            Some(path) if !path.is_file() => {
+                bail!("config file '{}' doesn't exist", path.display())
+            }
+            Some(path) => Ok(Some(path)),
+            None => Ok(default_path().filter(|path| path.is_file())),
+        }
+    }
+
+    // Reads the settings in the TOML file at `path`.
+    pub fn deserialize(path: &Path) -> Result<Self, anyhow::Error> {
+        let table = match fs::read_to_string(path)?.parse::<toml::Table>() {
+            Ok(table) => table,
+            Err(e) => bail!("could not read config file '{}'\n- `{}`", path.display(), e),
+        };
+
+        let unknown: Vec<_> = table
+            .keys()
+            .filter(|key| !KEYS.contains(&key.as_str()))
+            .map(|key| format!("'{key}'"))
+            .collect();
+        if !unknown.is_empty() {
+            bail!("unknown key {} in '{}'", unknown.join(", "), path.display())
+        }
+
+        let mut config = FileConfig {
+            source: Some(path.to_owned()),
+            ..Default::default()
+        };
+
+        if let Some(value) = table.get("path") {
+            match value.as_str() {
+                Some(dir) => config.path = Some(expand_home(dir)),
+                None => bail!("'path' in '{}' should be a string", path.display()),
+            }
+        }
+
+        if let Some(value) = table.get("clean_exit") {
+            match value.as_bool() {
+                Some(clean_exit) => config.clean_exit = Some(clean_exit),
+                None => bail!("'clean_exit' in '{}' should be a boolean", path.display()),
+            }
+        }
+
+        if let Some(value) = table.get("status_file") {
+            match value.as_str() {
+                Some(file) => config.status_file = Some(expand_home(file)),
+                None => bail!("'status_file' in '{}' should be a string", path.display()),
+            }
+        }
+
+        if let Some(value) = table.get("status_format") {
+            match value.as_str() {
+                Some(format) => config.status_format = Some(format.to_owned()),
+                None => bail!("'status_format' in '{}' should be a string", path.display()),
+            }
+        }
+
+        if let Some(value) = table.get("colors") {
+            let colors = match value.as_table() {
+                Some(colors) => colors,
+                None => bail!("'colors' in '{}' should be a table", path.display()),
+            };
+
+            for (name, hex) in colors.iter() {
+                if !theme::COLOR_MAP.contains_key(name) {
+                    bail!("invalid color name '{name}' in '{}'", path.display())
+                }
+                match hex.as_str().map(parse_hex) {
+                    Some(Some(color)) => config.colors.push((name.to_owned(), color)),
+                    _ => bail!(
+                        "invalid hex value for '{name}' in '{}', expected a string \
+                        in range '000000' -> 'ffffff'",
+                        path.display()
+                    ),
+                }
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+// The config file that tap was started with.
+pub fn get() -> &'static FileConfig {
+    &FILE_CONFIG
+}
+
+fn default_path() -> Option<PathBuf> {
+    let config_dir = match std::env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+
+    Some(config_dir.join("tap").join("tap.toml"))
+}
+
+// Expands a leading `~` in `path` to the home directory.
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn parse_hex(hex: &str) -> Option<Color> {
+    let hex = hex.trim_start_matches('#');
+    match hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        true => hex.parse().ok(),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn config_file(contents: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().expect("temp file");
+        file.write_all(contents.as_bytes()).expect("write config");
+        file
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let file = config_file(
+            "path = \"/music\"\nclean_exit = false\nstatus_file = \"/tmp/np\"\n\
+            [colors]\nfg = \"ff9999\"\nbg = \"#002b36\"\n",
+        );
+        let config = FileConfig::deserialize(file.path()).unwrap();
+
+        assert_eq!(config.path, Some(PathBuf::from("/music")));
+        assert_eq!(config.clean_exit, Some(false));
+        assert_eq!(config.status_file, Some(PathBuf::from("/tmp/np")));
+        assert_eq!(config.colors.len(), 2);
+        assert!(config.colors.contains(&("fg".to_string(), Color::Rgb(255, 153, 153))));
+        assert!(config.colors.contains(&("bg".to_string(), Color::Rgb(0, 43, 54))));
+    }
+
+    #[test]
+    fn test_deserialize_invalid_color() {
+        let file = config_file("[colors]\nfoo = \"ff9999\"\n");
+        assert!(FileConfig::deserialize(file.path()).is_err());
+
+        let file = config_file("[colors]\nfg = \"fff\"\n");
+        assert!(FileConfig::deserialize(file.path()).is_err());
+
+        let file = config_file("clean_exit = \"yes\"\n");
+        assert!(FileConfig::deserialize(file.path()).is_err());
+    }
+
+    #[test]
+    fn test_malformed_config_warns() {
+        let file = config_file("path = \"/music\"\n[colors\nfg = \"ff9999\"\n");
+        let mut out = vec![];
+        let config = FileConfig::read(file.path(), &mut out);
+        let warning = String::from_utf8(out).unwrap();
+
+        assert!(warning.starts_with("[tap warning]: could not read config file"));
+        assert!(warning.contains("line 2"), "{warning}");
+        assert_eq!(config.source, None);
+        assert_eq!(config.path, None);
+        assert!(config.colors.is_empty());
+
+        let file = config_file("clean-exit = false\nstatus_fromat = \"{title}\"\n");
+        let mut out = vec![];
+        let config = FileConfig::read(file.path(), &mut out);
+        let warning = String::from_utf8(out).unwrap();
+
+        assert!(
+            warning.starts_with("[tap warning]: unknown key"),
+            "{warning}"
+        );
+        assert!(warning.contains("'clean-exit'"), "{warning}");
+        assert!(warning.contains("'status_fromat'"), "{warning}");
+        assert_eq!(config.clean_exit, None);
+        assert_eq!(config.status_format, None);
+
+        let file = config_file("path = \"/music\"\n");
+        let mut out = vec![];
+        let config = FileConfig::read(file.path(), &mut out);
+        assert!(out.is_empty());
+        assert_eq!(config.path, Some(PathBuf::from("/music")));
+    }
+}
@@ -0,0 +1,87 @@
+use super::args;
+
+// The characters that the player and the fuzzy-finder are drawn with, other
+// than text. With `--ascii` they are all ASCII, for terminals and fonts that
+// can't show the Unicode ones.
+pub struct Glyphs {
+    // The solid part of the progress bar.
+    pub block: &'static str,
+    // The fractional part of the progress bar, from 0/8 to 8/8 of a block.
+    pub sub_blocks: [&'static str; 9],
+    // The borders of the fuzzy-finder.
+    pub vline: &'static str,
+    pub hline: &'static str,
+    // Marks text that was cut short.
+    pub ellipsis: &'static str,
+    // Marks favorites.
+    pub star: &'static str,
+    // The arrow keys shown in the help view.
+    pub up: &'static str,
+    pub down: &'static str,
+}
+
+pub const UNICODE: Glyphs = Glyphs {
+    block: "█",
+    sub_blocks: [" ", "▏", "▎", "▍", "▌", "▋", "▊", "▉", "█"],
+    vline: "│",
+    hline: "─",
+    ellipsis: "…",
+    star: "★",
+    up: "↑",
+    down: "↓",
+};
+
+pub const ASCII: Glyphs = Glyphs {
+    block: "#",
+    sub_blocks: [" ", " ", " ", "-", "-", "-", "=", "=", "#"],
+    vline: "|",
+    hline: "-",
+    ellipsis: "~",
+    star: "*",
+    up: "Up",
+    down: "Down",
+};
+
+impl Glyphs {
+    // The character for the fractional part of the progress bar, where `extra`
+    // is the number of eighths of a block.
+    pub fn sub_block(&self, extra: usize) -> &'static str {
+        self.sub_blocks[extra.min(8)]
+    }
+}
+
+// The glyphs selected on startup.
+pub fn get() -> &'static Glyphs {
+    match args::ascii() {
+        true => &ASCII,
+        false => &UNICODE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use unicode_width::UnicodeWidthStr;
+
+    use super::*;
+
+    fn all(g: &Glyphs) -> Vec<&'static str> {
+        let mut all = vec![g.block, g.vline, g.hline, g.ellipsis, g.star, g.up, g.down];
+        all.extend(g.sub_blocks);
+        all
+    }
+
+    #[test]
+    fn test_ascii_glyphs() {
+        assert!(all(&ASCII).iter().all(|g| g.is_ascii()));
+        assert!((0..=10).all(|extra| ASCII.sub_block(extra).is_ascii()));
+
+        // The glyphs drawn in a single column take one column in both sets.
+        for (ascii, unicode) in all(&ASCII).iter().zip(all(&UNICODE)).take(5) {
+            assert_eq!(ascii.width(), 1);
+            assert_eq!(unicode.width(), 1);
+        }
+        for extra in 0..=8 {
+            assert_eq!(ASCII.sub_block(extra).width(), UNICODE.sub_block(extra).width());
+        }
+    }
+}
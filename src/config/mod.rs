@@ -1,2 +1,4 @@
 pub mod args;
+pub mod file_config;
+pub mod glyphs;
 pub mod theme;
@@ -1,5 +1,6 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::RwLock};
 
+use clap::ValueEnum;
 use cursive::{
     theme::{
         BorderStyle,
@@ -8,68 +9,140 @@ use cursive::{
         PaletteColor::*,
         Theme,
     },
-    With,
+    Cursive, With,
 };
 
-use super::args;
+use super::{
+    args::{self, Preset},
+    file_config,
+};
 
 lazy_static::lazy_static! {
     pub static ref COLOR_MAP: HashMap<String, Color> = default_palette();
-    pub static ref PALETTE: HashMap<String, Color> = create_palette();
+    // The colors in use, replaced when moving to the next preset.
+    static ref PALETTE: RwLock<HashMap<String, Color>> = RwLock::new(create_palette());
+    // The preset in use.
+    static ref PRESET: RwLock<Preset> = RwLock::new(args::theme());
 }
 
 pub fn custom() -> Theme {
+    theme_of(&PALETTE.read().expect("should not be poisoned"))
+}
+
+fn theme_of(colors: &HashMap<String, Color>) -> Theme {
     Theme {
         shadow: false,
         borders: BorderStyle::Simple,
         palette: Palette::default().with(|palette| {
-            palette[Background] = PALETTE["bg"];
-            palette[View] = PALETTE["bg"];
-            palette[Primary] = PALETTE["hl"];
-            palette[TitlePrimary] = PALETTE["header"];
+            palette[Background] = colors["bg"];
+            palette[View] = colors["bg"];
+            palette[Primary] = colors["hl"];
+            palette[TitlePrimary] = colors["header"];
         }),
     }
 }
 
+// The color in use for `name`.
+fn color(name: &str) -> Color {
+    PALETTE.read().expect("should not be poisoned")[name]
+}
+
 pub fn fg() -> ColorStyle {
-    ColorStyle::front(PALETTE["fg"])
+    ColorStyle::front(color("fg"))
 }
 
 pub fn hl() -> ColorStyle {
-    ColorStyle::front(PALETTE["hl"])
+    ColorStyle::front(color("hl"))
 }
 
 pub fn prompt() -> ColorStyle {
-    ColorStyle::front(PALETTE["prompt"])
+    ColorStyle::front(color("prompt"))
 }
 
 pub fn header1() -> ColorStyle {
-    ColorStyle::front(PALETTE["header"])
+    ColorStyle::front(color("header"))
 }
 
 pub fn header2() -> ColorStyle {
-    ColorStyle::front(PALETTE["header+"])
+    ColorStyle::front(color("header+"))
 }
 
 pub fn progress() -> ColorStyle {
-    ColorStyle::front(PALETTE["progress"])
+    ColorStyle::front(color("progress"))
 }
 
 pub fn info() -> ColorStyle {
-    ColorStyle::front(PALETTE["info"])
+    ColorStyle::front(color("info"))
 }
 
 pub fn err() -> ColorStyle {
-    ColorStyle::front(PALETTE["err"])
+    ColorStyle::front(color("err"))
 }
 
 pub fn button() -> ColorStyle {
-    ColorStyle::new(PALETTE["bg"], PALETTE["fg"])
+    ColorStyle::new(color("bg"), color("fg"))
+}
+
+// Callback to recolor the interface with the next preset. Colors set with
+// `--color` or the config file are replaced by the preset's, while the terminal
+// colors are kept with `--term-bg` and `--term-color`.
+pub fn cycle(siv: &mut Cursive) {
+    if args::term_color() {
+        return;
+    }
+
+    let mut preset = PRESET.write().expect("should not be poisoned");
+    *preset = next_preset(*preset);
+
+    let mut colors = preset_colors(*preset);
+    if args::user_colors().1 {
+        colors.insert("bg".to_string(), Color::TerminalDefault);
+    }
+    *PALETTE.write().expect("should not be poisoned") = colors;
+
+    siv.set_theme(custom());
+}
+
+// The preset after `preset`, wrapping around to the first.
+fn next_preset(preset: Preset) -> Preset {
+    let presets = Preset::value_variants();
+    let index = presets.iter().position(|&p| p == preset).unwrap_or(0);
+    presets[(index + 1) % presets.len()]
+}
+
+// The names of the colors, in the order they are documented.
+pub const COLOR_NAMES: [&str; 9] = [
+    "fg", "bg", "hl", "prompt", "header", "header+", "progress", "info", "err",
+];
+
+// Each color of the palette, with where it was set.
+pub fn palette_sources() -> Vec<(&'static str, Color, &'static str)> {
+    let (user_colors, term_bg) = args::user_colors();
+
+    COLOR_NAMES
+        .iter()
+        .map(|&name| {
+            let source = if let Some(source) = args::term_color_source() {
+                source
+            } else if term_bg && name == "bg" {
+                "--term-bg"
+            } else if user_colors.iter().any(|(n, _)| n == name) {
+                "--color"
+            } else if file_config::get().colors.iter().any(|(n, _)| n == name) {
+                "config file"
+            } else if args::theme() != Preset::Default {
+                "--theme"
+            } else {
+                "default"
+            };
+            (name, color(name), source)
+        })
+        .collect()
 }
 
 fn create_palette() -> HashMap<String, Color> {
-    // Get the default colors.
-    let mut m = COLOR_MAP.to_owned();
+    // Get the colors of the preset.
+    let mut m = preset_colors(args::theme());
 
     if args::term_color() {
         // Use terminal colors for foreground and background.
@@ -77,8 +150,10 @@ fn create_palette() -> HashMap<String, Color> {
             *value = Color::TerminalDefault;
         }
     } else {
-        // Update any user-defined colors.
+        // Update any user-defined colors, with the command line taking
+        // precedence over the config file.
         let (user_colors, term_bg) = args::user_colors();
+        m.extend(file_config::get().colors.to_owned());
         m.extend(user_colors);
 
         // Update background color with terminal color, if using.
@@ -89,6 +164,53 @@ fn create_palette() -> HashMap<String, Color> {
     m
 }
 
+// The colors of `preset`, by name.
+fn preset_colors(preset: Preset) -> HashMap<String, Color> {
+    // In the order of `COLOR_NAMES`.
+    let colors = match preset {
+        Preset::Default => return default_palette(),
+        Preset::Solarized => [
+            Rgb(38, 139, 210),  // blue #268bd2
+            Rgb(0, 43, 54),     // base03 #002b36
+            Rgb(253, 246, 227), // base3 #fdf6e3
+            Rgb(88, 110, 117),  // base01 #586e75
+            Rgb(133, 153, 0),   // green #859900
+            Rgb(203, 75, 22),   // orange #cb4b16
+            Rgb(108, 113, 196), // violet #6c71c4
+            Rgb(42, 161, 152),  // cyan #2aa198
+            Rgb(220, 50, 47),   // red #dc322f
+        ],
+        Preset::Gruvbox => [
+            Rgb(131, 165, 152), // blue #83a598
+            Rgb(40, 40, 40),    // bg #282828
+            Rgb(235, 219, 178), // fg #ebdbb2
+            Rgb(80, 73, 69),    // bg2 #504945
+            Rgb(184, 187, 38),  // green #b8bb26
+            Rgb(250, 189, 47),  // yellow #fabd2f
+            Rgb(211, 134, 155), // purple #d3869b
+            Rgb(142, 192, 124), // aqua #8ec07c
+            Rgb(251, 73, 52),   // red #fb4934
+        ],
+        Preset::Nord => [
+            Rgb(129, 161, 193), // nord9 #81a1c1
+            Rgb(46, 52, 64),    // nord0 #2e3440
+            Rgb(236, 239, 244), // nord6 #eceff4
+            Rgb(76, 86, 106),   // nord3 #4c566a
+            Rgb(163, 190, 140), // nord14 #a3be8c
+            Rgb(235, 203, 139), // nord13 #ebcb8b
+            Rgb(180, 142, 173), // nord15 #b48ead
+            Rgb(136, 192, 208), // nord8 #88c0d0
+            Rgb(191, 97, 106),  // nord11 #bf616a
+        ],
+    };
+
+    COLOR_NAMES
+        .iter()
+        .zip(colors)
+        .map(|(name, color)| (name.to_string(), color))
+        .collect()
+}
+
 fn default_palette() -> HashMap<String, Color> {
     let mut m = HashMap::new();
     m.insert("fg".into(), Rgb(129, 162, 190)); // blue #81a2be
@@ -102,3 +224,43 @@ fn default_palette() -> HashMap<String, Color> {
     m.insert("err".into(), Rgb(204, 102, 102)); // red #cc6666
     m
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cycle_presets() {
+        // Every preset is visited before returning to the first.
+        let presets = Preset::value_variants();
+        let mut preset = presets[0];
+        for expected in presets.iter().cycle().skip(1).take(presets.len()) {
+            preset = next_preset(preset);
+            assert_eq!(preset, *expected);
+        }
+
+        // Each preset sets every color, and recolors the background.
+        for &preset in presets {
+            let colors = preset_colors(preset);
+            assert!(COLOR_NAMES.iter().all(|name| colors.contains_key(*name)));
+
+            let next = preset_colors(next_preset(preset));
+            assert_ne!(
+                theme_of(&colors).palette[Background],
+                theme_of(&next).palette[Background]
+            );
+        }
+
+        // The terminal colors are kept with `--term-color`.
+        if args::term_color() {
+            return;
+        }
+
+        let mut siv = Cursive::new();
+        siv.set_theme(custom());
+        let before = siv.current_theme().palette[Background];
+        cycle(&mut siv);
+        assert_ne!(siv.current_theme().palette[Background], before);
+        assert_eq!(siv.current_theme().palette[Background], color("bg"));
+    }
+}
@@ -0,0 +1,38 @@
+use std::{collections::HashSet, path::PathBuf, sync::RwLock};
+
+use crate::player::AudioFile;
+
+use super::persistent_data;
+
+lazy_static::lazy_static! {
+    // The tracks that are never played in sequence or at random, loaded from
+    // the cache on first use.
+    static ref BLOCKLIST: RwLock<HashSet<PathBuf>> =
+        RwLock::new(persistent_data::blocklist().into_iter().collect());
+}
+
+// The paths of the `playlist` tracks that are blocklisted.
+pub fn blocked(playlist: &[AudioFile]) -> HashSet<PathBuf> {
+    let blocklist = BLOCKLIST.read().expect("should not be poisoned");
+    playlist
+        .iter()
+        .filter(|f| blocklist.contains(&f.path))
+        .map(|f| f.path.to_owned())
+        .collect()
+}
+
+// Adds `path` to the blocklist, or removes it if it is already blocklisted,
+// and saves the blocklist. Returns whether `path` is now blocklisted.
+pub fn toggle(path: &PathBuf) -> Result<bool, anyhow::Error> {
+    let mut blocklist = BLOCKLIST.write().expect("should not be poisoned");
+    let is_blocked = match blocklist.remove(path) {
+        true => false,
+        false => blocklist.insert(path.to_owned()),
+    };
+
+    let mut paths = blocklist.iter().cloned().collect::<Vec<_>>();
+    paths.sort();
+    persistent_data::set_blocklist(paths)?;
+
+    Ok(is_blocked)
+}
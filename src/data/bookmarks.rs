@@ -0,0 +1,37 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+use super::persistent_data;
+
+lazy_static::lazy_static! {
+    // The track and position, in seconds, that each audiobook was left at, by
+    // the directory of the book. Loaded from the cache on first use.
+    static ref BOOKMARKS: RwLock<HashMap<PathBuf, (PathBuf, u64)>> = RwLock::new(
+        persistent_data::bookmarks()
+            .into_iter()
+            .map(|(dir, track, secs)| (dir, (track, secs)))
+            .collect()
+    );
+}
+
+// The track and position, in seconds, that the book in `dir` was left at.
+pub fn get(dir: &Path) -> Option<(PathBuf, u64)> {
+    BOOKMARKS.read().ok()?.get(dir).cloned()
+}
+
+// Records that the book in `dir` was left at `secs` into `track`, and saves
+// the bookmarks.
+pub fn set(dir: PathBuf, track: PathBuf, secs: u64) -> Result<(), anyhow::Error> {
+    let mut bookmarks = BOOKMARKS.write().expect("should not be poisoned");
+    bookmarks.insert(dir, (track, secs));
+
+    let mut entries = bookmarks
+        .iter()
+        .map(|(dir, (track, secs))| (dir.to_owned(), track.to_owned(), *secs))
+        .collect::<Vec<_>>();
+    entries.sort();
+    persistent_data::set_bookmarks(entries)
+}
@@ -0,0 +1,91 @@
+use std::{collections::HashSet, path::PathBuf, sync::RwLock};
+
+use crate::config::args;
+use crate::fuzzy::{sort_items, FuzzyItem};
+
+use super::persistent_data;
+
+lazy_static::lazy_static! {
+    // The favorite directories, loaded from the cache on first use.
+    static ref FAVORITES: RwLock<HashSet<PathBuf>> =
+        RwLock::new(persistent_data::favorites().into_iter().collect());
+}
+
+// Whether or not `path` is a favorite directory.
+pub fn is_favorite(path: &PathBuf) -> bool {
+    FAVORITES
+        .read()
+        .map_or(false, |favorites| favorites.contains(path))
+}
+
+// Adds `path` to the favorites, or removes it if it is already a favorite,
+// and saves the favorites. Returns whether `path` is now a favorite.
+pub fn toggle(path: &PathBuf) -> Result<bool, anyhow::Error> {
+    // Favorites are matched by canonical path, so that they survive rescans.
+    let path = path.canonicalize().unwrap_or(path.to_owned());
+    let mut favorites = FAVORITES.write().expect("should not be poisoned");
+    let is_favorite = toggle_in(&mut favorites, path);
+
+    let mut paths = favorites.iter().cloned().collect::<Vec<_>>();
+    paths.sort();
+    persistent_data::set_favorites(paths)?;
+
+    Ok(is_favorite)
+}
+
+// Gets the items that are favorites, sorted alphabetically.
+pub fn favorite_items(items: &Vec<FuzzyItem>) -> Vec<FuzzyItem> {
+    let favorites = FAVORITES.read().expect("should not be poisoned");
+    filter_favorites(items, &favorites)
+}
+
+fn toggle_in(favorites: &mut HashSet<PathBuf>, path: PathBuf) -> bool {
+    match favorites.remove(&path) {
+        true => false,
+        false => favorites.insert(path),
+    }
+}
+
+fn filter_favorites(items: &Vec<FuzzyItem>, favorites: &HashSet<PathBuf>) -> Vec<FuzzyItem> {
+    let mut items = items
+        .iter()
+        .filter(|e| favorites.contains(&e.path))
+        .cloned()
+        .collect::<Vec<FuzzyItem>>();
+    sort_items(&mut items, args::natural_sort());
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fuzzy::{create_items, WalkOpts};
+    use crate::utils::create_working_dir;
+
+    #[test]
+    fn test_toggled_favorite_is_filtered() {
+        let root = create_working_dir(
+            &["one", "two"],
+            &[
+                ("one/a.mp3", "test_mp3_audio.mp3"),
+                ("two/b.mp3", "test_mp3_audio.mp3"),
+            ],
+            &[],
+        )
+        .expect("create temp dir");
+        let root = root.path().canonicalize().expect("should canonicalize");
+
+        let items = create_items(&root, &WalkOpts::default()).expect("should create items");
+        let mut favorites = HashSet::new();
+        assert!(filter_favorites(&items, &favorites).is_empty());
+
+        assert!(toggle_in(&mut favorites, root.join("two")));
+        let filtered = filter_favorites(&items, &favorites);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].display, "two");
+
+        // Toggling again removes the favorite.
+        assert!(!toggle_in(&mut favorites, root.join("two")));
+        assert!(filter_favorites(&items, &favorites).is_empty());
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+};
+
+use anyhow::bail;
+use ebur128::{EbuR128, Mode};
+use rodio::Source;
+
+use crate::config::args;
+use crate::error::TapError;
+use crate::fuzzy;
+use crate::player::{self, tracks::decode, AudioFile};
+use crate::utils;
+
+// The name of the sidecar file that stores the track gains for an album.
+pub const GAIN_FILE: &str = ".tap_gain";
+
+// The target loudness in LUFS, as used by ReplayGain 2.0.
+const REFERENCE_LOUDNESS: f64 = -18.0;
+
+// The number of frames passed to the loudness meter at a time.
+const CHUNK_FRAMES: usize = 4096;
+
+// Analyzes the loudness of the tracks in each album found in `path` and writes
+// the gain needed to reach `REFERENCE_LOUDNESS` to a sidecar file in the album
+// directory. Tracks that have already been analyzed are skipped, so an
+// interrupted analysis can be resumed by running it again.
+pub fn analyze(path: PathBuf) -> Result<(), anyhow::Error> {
+    let opts = args::walk_opts();
+    let albums = fuzzy::walk(&path, &opts)
+        .filter(|item| item.has_audio)
+        .map(|item| item.path)
+        .collect::<Vec<PathBuf>>();
+
+    if albums.is_empty() {
+        bail!(TapError::NoAudio(format!(
+            "no audio files detected in '{}'",
+            path.display()
+        )))
+    }
+
+    for album in albums {
+        match utils::display_with_spinner(analyze_album, &album, "analyzing") {
+            Ok((analyzed, skipped, failed)) => println!(
+                "\r[tap]: '{}': {} analyzed, {} skipped, {} failed",
+                album.display(),
+                analyzed,
+                skipped,
+                failed
+            ),
+            Err(e) => eprintln!("\r[tap error]: '{}': {}", album.display(), e),
+        }
+    }
+
+    Ok(())
+}
+
+// Reads the track gains, in dB, from the sidecar file in `album`, keyed by file name.
+pub fn read_gains(album: &PathBuf) -> HashMap<String, f64> {
+    let contents = fs::read_to_string(album.join(GAIN_FILE)).unwrap_or_default();
+
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (name, gain) = line.rsplit_once('\t')?;
+            Some((name.to_string(), gain.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+// The gains, in dB, of the tracks in `files` that have been analyzed, keyed by
+// path. The sidecar file of each directory in `files` is read once.
+pub fn track_gains(files: &[AudioFile]) -> HashMap<PathBuf, f64> {
+    let mut dirs = files
+        .iter()
+        .filter_map(|f| f.path.parent())
+        .collect::<Vec<_>>();
+    dirs.dedup();
+
+    dirs.into_iter()
+        .flat_map(|dir| {
+            read_gains(&dir.to_path_buf())
+                .into_iter()
+                .map(move |(name, gain)| (dir.join(name), gain))
+        })
+        .collect()
+}
+
+// Analyzes the tracks in `album` that are missing from its sidecar file.
+// Returns the number of tracks that were analyzed, skipped and failed.
+fn analyze_album(album: &PathBuf) -> Result<(usize, usize, usize), anyhow::Error> {
+    let files = player::audio_files(album, &args::audio_opts())?;
+    let gains = read_gains(album);
+    // Opened once there is a gain to write, so that albums with nothing to
+    // analyze are left as they were.
+    let mut sidecar = None;
+    let (mut analyzed, mut skipped, mut failed) = (0, 0, 0);
+
+    for file in files {
+        let name = file
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        if gains.contains_key(&name) {
+            skipped += 1;
+            continue;
+        }
+
+        match loudness(&file.path) {
+            Ok(loudness) => {
+                if sidecar.is_none() {
+                    let path = album.join(GAIN_FILE);
+                    sidecar = Some(OpenOptions::new().create(true).append(true).open(path)?);
+                }
+                let sidecar = sidecar.as_mut().expect("should be open");
+                // Write each gain as it is computed so that progress isn't lost.
+                writeln!(sidecar, "{}\t{:.2}", name, REFERENCE_LOUDNESS - loudness)?;
+                analyzed += 1;
+            }
+            Err(_) => failed += 1,
+        }
+    }
+
+    Ok((analyzed, skipped, failed))
+}
+
+// Computes the integrated loudness (EBU R128) of the audio file, in LUFS.
+fn loudness(path: &PathBuf) -> Result<f64, anyhow::Error> {
+    let source = decode(path)?;
+    let channels = source.channels() as usize;
+    let mut meter = EbuR128::new(channels as u32, source.sample_rate(), Mode::I)?;
+    let mut chunk = Vec::with_capacity(CHUNK_FRAMES * channels);
+
+    for sample in source {
+        chunk.push(sample);
+        if chunk.len() == CHUNK_FRAMES * channels {
+            meter.add_frames_i16(&chunk)?;
+            chunk.clear();
+        }
+    }
+
+    // Only pass complete frames to the meter.
+    chunk.truncate(chunk.len() - chunk.len() % channels);
+    meter.add_frames_i16(&chunk)?;
+
+    let loudness = meter.loudness_global()?;
+
+    match loudness.is_finite() {
+        true => Ok(loudness),
+        false => bail!("'{}' is silent", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::create_working_dir;
+
+    #[test]
+    fn test_read_gains() {
+        let root = create_working_dir(&["one"], &[], &[])
+            .expect("create temp dir")
+            .into_path()
+            .join("one");
+
+        fs::write(
+            root.join(GAIN_FILE),
+            "a.mp3\t-3.25\nb c.flac\t1.50\nbad line\n",
+        )
+        .expect("write sidecar");
+
+        let gains = read_gains(&root);
+
+        assert_eq!(gains.len(), 2);
+        assert_eq!(gains["a.mp3"], -3.25);
+        assert_eq!(gains["b c.flac"], 1.5);
+    }
+
+    #[test]
+    fn test_read_gains_without_sidecar() {
+        let root = create_working_dir(&["one"], &[], &[])
+            .expect("create temp dir")
+            .into_path();
+
+        assert!(read_gains(&root).is_empty());
+    }
+}
@@ -0,0 +1,41 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::RwLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::persistent_data;
+
+lazy_static::lazy_static! {
+    // The time each album was last played, in seconds since the Unix epoch, by
+    // the directory of the album. Loaded from the cache on first use.
+    static ref HISTORY: RwLock<HashMap<PathBuf, u64>> =
+        RwLock::new(persistent_data::history().into_iter().collect());
+}
+
+// The time the album in `dir` was last played, in seconds since the Unix
+// epoch, if it has been played.
+pub fn last_played(dir: &Path) -> Option<u64> {
+    HISTORY.read().ok()?.get(dir).copied()
+}
+
+// Records that the album in `dir` was played now, and saves the history.
+pub fn set_played(dir: PathBuf) -> Result<(), anyhow::Error> {
+    let mut history = HISTORY.write().expect("should not be poisoned");
+    history.insert(dir, now());
+
+    let mut entries = history
+        .iter()
+        .map(|(dir, secs)| (dir.to_owned(), *secs))
+        .collect::<Vec<_>>();
+    entries.sort();
+    persistent_data::set_history(entries)
+}
+
+// The current time in seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
@@ -1,4 +1,10 @@
+pub mod blocklist;
+pub mod bookmarks;
+pub mod favorites;
+pub mod gain_data;
+pub mod history;
 pub mod persistent_data;
+pub mod play_counts;
 pub mod session_data;
 
 pub use self::session_data::SessionData;
@@ -6,8 +6,9 @@ use std::{
 };
 
 use anyhow::bail;
-use bincode::{config, Decode};
+use bincode::{config, Decode, Encode};
 
+use crate::config::args;
 use crate::fuzzy::{self, FuzzyItem};
 use crate::utils;
 
@@ -26,9 +27,38 @@ fn cached_last_modified() -> Result<SystemTime, anyhow::Error> {
     get_cached::<SystemTime>("last_modified")
 }
 
+// The options that the cached items were found with. Caches written before
+// they were stored are always updated.
+fn cached_walk_opts() -> Option<CachedWalkOpts> {
+    // ~/.cache/tap/walk_opts
+    get_cached::<CachedWalkOpts>("walk_opts").ok()
+}
+
+// The options that change which items are found, or their order, when walking
+// the default directory. The cache is updated when any of them change.
+#[derive(Debug, PartialEq, Encode, Decode)]
+struct CachedWalkOpts {
+    hidden: bool,
+    exclude_dir: Vec<String>,
+    natural_sort: bool,
+    max_depth: Option<usize>,
+}
+
+impl CachedWalkOpts {
+    fn current() -> Self {
+        let opts = args::walk_opts();
+        Self {
+            hidden: opts.hidden,
+            exclude_dir: args::exclude_dir(),
+            natural_sort: opts.natural_sort,
+            max_depth: opts.max_depth,
+        }
+    }
+}
+
 pub fn needs_update(path: &PathBuf) -> Result<bool, anyhow::Error> {
     let res = utils::last_modified(path)?.eq(&cached_last_modified()?);
-    Ok(!res)
+    Ok(!res || cached_walk_opts() != Some(CachedWalkOpts::current()))
 }
 
 pub fn uses_default(path: &PathBuf) -> bool {
@@ -36,6 +66,94 @@ pub fn uses_default(path: &PathBuf) -> bool {
     cached_path.eq(path)
 }
 
+// Removes `path`, and any items below it, from the cached items for `root`.
+pub fn remove_cached(root: &PathBuf, path: &PathBuf) -> Result<(), anyhow::Error> {
+    let mut items = cached_items()?;
+    items.retain(|e| !e.path.starts_with(path));
+    set_cached("items", items)?;
+
+    // Removing a child of `root` modifies `root`, which would otherwise
+    // cause a full update the next time the cache is accessed.
+    if path.parent() == Some(root.as_path()) {
+        set_cached("last_modified", utils::last_modified(root)?)?;
+    }
+
+    Ok(())
+}
+
+// The index of the track the automated player last stopped on in `path`, if any.
+pub fn automated_progress(path: &PathBuf) -> Option<usize> {
+    // ~/.cache/tap/progress
+    match get_cached::<(PathBuf, usize)>("progress") {
+        Ok((cached_path, index)) if cached_path.eq(path) => Some(index),
+        _ => None,
+    }
+}
+
+// Records the index of the track the automated player stopped on in `path`.
+pub fn set_automated_progress(path: &PathBuf, index: usize) -> Result<(), anyhow::Error> {
+    set_cached("progress", (path.to_owned(), index))
+}
+
+// The favorite directories, if any.
+pub fn favorites() -> Vec<PathBuf> {
+    // ~/.cache/tap/favorites
+    get_cached::<Vec<PathBuf>>("favorites").unwrap_or_default()
+}
+
+pub fn set_favorites(paths: Vec<PathBuf>) -> Result<(), anyhow::Error> {
+    set_cached("favorites", paths)
+}
+
+// The blocklisted tracks, if any.
+pub fn blocklist() -> Vec<PathBuf> {
+    // ~/.cache/tap/blocklist
+    get_cached::<Vec<PathBuf>>("blocklist").unwrap_or_default()
+}
+
+pub fn set_blocklist(paths: Vec<PathBuf>) -> Result<(), anyhow::Error> {
+    set_cached("blocklist", paths)
+}
+
+// The track and position, in seconds, that each audiobook was left at, by the
+// directory of the book.
+pub fn bookmarks() -> Vec<(PathBuf, PathBuf, u64)> {
+    // ~/.cache/tap/bookmarks
+    get_cached::<Vec<(PathBuf, PathBuf, u64)>>("bookmarks").unwrap_or_default()
+}
+
+pub fn set_bookmarks(bookmarks: Vec<(PathBuf, PathBuf, u64)>) -> Result<(), anyhow::Error> {
+    set_cached("bookmarks", bookmarks)
+}
+
+// The time each album was last played, in seconds since the Unix epoch, by the
+// directory of the album.
+pub fn history() -> Vec<(PathBuf, u64)> {
+    // ~/.cache/tap/history
+    get_cached::<Vec<(PathBuf, u64)>>("history").unwrap_or_default()
+}
+
+pub fn set_history(history: Vec<(PathBuf, u64)>) -> Result<(), anyhow::Error> {
+    set_cached("history", history)
+}
+
+// The number of times each album has been played, by the directory of the album.
+pub fn play_counts() -> Vec<(PathBuf, u64)> {
+    // ~/.cache/tap/play_counts
+    get_cached::<Vec<(PathBuf, u64)>>("play_counts").unwrap_or_default()
+}
+
+pub fn set_play_counts(counts: Vec<(PathBuf, u64)>) -> Result<(), anyhow::Error> {
+    set_cached("play_counts", counts)
+}
+
+fn set_cached<T: Encode>(file_name: &str, value: T) -> Result<(), anyhow::Error> {
+    let encoded = bincode::encode_to_vec(value, config::standard())?;
+    let mut file = File::create(cache_dir()?.join(file_name))?;
+    file.write_all(&encoded)?;
+    Ok(())
+}
+
 fn get_cached<T: Decode>(file_name: &str) -> Result<T, anyhow::Error> {
     let file_path = cache_dir()?.join(file_name);
 
@@ -67,7 +185,7 @@ fn cache_dir() -> Result<PathBuf, anyhow::Error> {
 
 pub fn update_cache(path: &PathBuf) -> Result<Vec<FuzzyItem>, anyhow::Error> {
     let last_modified = utils::last_modified(path)?;
-    let items = fuzzy::create_items(path)?;
+    let items = fuzzy::create_items(path, &args::walk_opts())?;
 
     let config = config::standard();
     let cache_dir = cache_dir()?;
@@ -85,6 +203,8 @@ pub fn update_cache(path: &PathBuf) -> Result<Vec<FuzzyItem>, anyhow::Error> {
     let mut items_file = File::create(cache_dir.join("items"))?;
     items_file.write_all(&encoded_items)?;
 
+    set_cached("walk_opts", CachedWalkOpts::current())?;
+
     Ok(items)
 }
 
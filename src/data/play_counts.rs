@@ -0,0 +1,118 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+use crate::fuzzy::FuzzyItem;
+
+use super::persistent_data;
+
+lazy_static::lazy_static! {
+    // The number of times each album has been played, by the canonical path of
+    // the directory of the album. Loaded from the cache on first use.
+    static ref PLAY_COUNTS: RwLock<HashMap<PathBuf, u64>> =
+        RwLock::new(persistent_data::play_counts().into_iter().collect());
+}
+
+// The number of times the album in `dir` has been played.
+pub fn play_count(dir: &Path) -> u64 {
+    PLAY_COUNTS
+        .read()
+        .map_or(0, |counts| counts.get(&key(dir)).copied().unwrap_or(0))
+}
+
+// Records that the album in `dir` was played once more, and saves the counts.
+pub fn add_play(dir: &Path) -> Result<(), anyhow::Error> {
+    let mut counts = PLAY_COUNTS.write().expect("should not be poisoned");
+    count_play(&mut counts, dir);
+
+    let mut entries = counts
+        .iter()
+        .map(|(dir, count)| (dir.to_owned(), *count))
+        .collect::<Vec<_>>();
+    entries.sort();
+    persistent_data::set_play_counts(entries)
+}
+
+fn count_play(counts: &mut HashMap<PathBuf, u64>, dir: &Path) {
+    *counts.entry(key(dir)).or_default() += 1;
+}
+
+// Gets the albums that have been played, the most played first.
+pub fn most_played_items(items: &Vec<FuzzyItem>) -> Vec<FuzzyItem> {
+    played_most(items, play_count)
+}
+
+fn played_most(items: &Vec<FuzzyItem>, count: impl Fn(&Path) -> u64) -> Vec<FuzzyItem> {
+    let mut played = items
+        .iter()
+        .filter(|e| e.has_audio)
+        .map(|e| (count(&e.path), e))
+        .filter(|(count, _)| *count > 0)
+        .collect::<Vec<_>>();
+    played.sort_by(|a, b| b.0.cmp(&a.0));
+    played.into_iter().map(|(_, e)| e.to_owned()).collect()
+}
+
+// Albums are counted by their canonical path, so that the counts are the same
+// however the search root was given.
+fn key(dir: &Path) -> PathBuf {
+    dir.canonicalize().unwrap_or_else(|_| dir.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fuzzy::{create_items, WalkOpts};
+    use crate::utils::create_working_dir;
+
+    #[test]
+    fn test_plays_are_counted_by_canonical_path() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        let album = dir.path().join("album");
+        std::fs::create_dir(&album).expect("create album dir");
+
+        let mut counts = HashMap::new();
+        count_play(&mut counts, &album);
+        count_play(&mut counts, &dir.path().join(".").join("album"));
+
+        assert_eq!(counts.len(), 1);
+        assert_eq!(counts.get(&key(&album)), Some(&2));
+    }
+
+    #[test]
+    fn test_most_played_order() {
+        let root = create_working_dir(
+            &["never", "once", "twice"],
+            &[
+                ("never/a.mp3", "test_mp3_audio.mp3"),
+                ("once/b.mp3", "test_mp3_audio.mp3"),
+                ("twice/c.mp3", "test_mp3_audio.mp3"),
+            ],
+            &[],
+        )
+        .expect("create temp dir")
+        .into_path();
+
+        let items = create_items(&root, &WalkOpts::default()).expect("should create items");
+        let mut counts = HashMap::new();
+        let names = |counts: &HashMap<PathBuf, u64>| {
+            played_most(&items, |path| counts.get(path).copied().unwrap_or(0))
+                .into_iter()
+                .map(|e| e.display)
+                .collect::<Vec<_>>()
+        };
+        assert!(names(&counts).is_empty());
+
+        for name in ["twice", "once", "twice"] {
+            *counts.entry(root.join(name)).or_default() += 1;
+        }
+        assert_eq!(names(&counts), ["twice", "once"]);
+
+        for _ in 0..2 {
+            *counts.entry(root.join("once")).or_default() += 1;
+        }
+        assert_eq!(names(&counts), ["once", "twice"]);
+    }
+}
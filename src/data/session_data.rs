@@ -1,7 +1,5 @@
 use std::{collections::VecDeque, path::PathBuf};
 
-use anyhow::bail;
-
 use crate::fuzzy::{self, FuzzyItem};
 use crate::player::{Player, PlayerOpts};
 use crate::utils::IntoInner;
@@ -20,26 +18,22 @@ pub struct SessionData {
 }
 
 impl SessionData {
-    pub fn new(path: &PathBuf, items: &Vec<FuzzyItem>) -> Result<Self, anyhow::Error> {
+    // The queue is empty if `items` contain no audio.
+    pub fn new(items: &Vec<FuzzyItem>) -> Self {
         let paths = fuzzy::leaf_paths(&items);
-        let queue: VecDeque<Track> = match Player::randomized(&paths) {
-            Some(first) => VecDeque::from([first]),
-            None => bail!("no audio files detected in '{}'", path.display()),
-        };
+        let queue: VecDeque<Track> = Player::randomized(&paths).into_iter().collect();
 
-        let data = Self {
+        Self {
             opts: PlayerOpts::default(),
             paths,
             queue,
-        };
-
-        Ok(data)
+        }
     }
 }
 
 impl IntoInner for SessionData {
     type T = (
-        (u8, u8, bool, bool),
+        (u8, u8, bool, bool, bool),
         Vec<PathBuf>,
         VecDeque<(PathBuf, usize)>,
     );
@@ -51,7 +45,7 @@ impl IntoInner for SessionData {
 
 impl Into<SessionData>
     for (
-        (u8, u8, bool, bool),
+        (u8, u8, bool, bool, bool),
         Vec<PathBuf>,
         VecDeque<(PathBuf, usize)>,
     )
@@ -0,0 +1,136 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+use rayon::prelude::*;
+
+use crate::config::args;
+use crate::error::TapError;
+use crate::fuzzy;
+use crate::player::{audio_file::unsupported_format, tracks::decode, valid_audio_ext, AudioFile};
+use crate::utils;
+
+// The problems found with an audio file, in the order they are reported.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Problem {
+    // The format can't be decoded by tap.
+    Unsupported,
+    // The file can't be opened or decoded.
+    Undecodable,
+    // The file has no tags, or no title or artist tag.
+    Untagged,
+    // The length of the file is unknown.
+    NoDuration,
+}
+
+impl Problem {
+    fn name(&self) -> &'static str {
+        match self {
+            Problem::Unsupported => "unsupported",
+            Problem::Undecodable => "undecodable",
+            Problem::Untagged => "untagged",
+            Problem::NoDuration => "no-duration",
+        }
+    }
+}
+
+// Checks every audio file below `path` and prints the problems found, one per
+// line as the name of the problem and the path relative to `path`, separated by
+// a tab, so that the output can be filtered with `grep` or `cut`.
+pub fn run(path: PathBuf) -> Result<(), anyhow::Error> {
+    let (files, problems) = utils::display_with_spinner(check_all, &path, "checking")?;
+
+    if files == 0 {
+        bail!(TapError::NoAudio(format!(
+            "no audio files detected in '{}'",
+            path.display()
+        )))
+    }
+
+    for (problem, file) in problems.iter() {
+        let file = file.strip_prefix(&path).unwrap_or(file);
+        println!("{}\t{}", problem.name(), file.display());
+    }
+
+    eprintln!(
+        "[tap]: checked {} files, {} problems found",
+        files,
+        problems.len()
+    );
+
+    Ok(())
+}
+
+// Checks the audio files in every directory below `path`. Returns the number of
+// files checked and the problems found, sorted by problem and then path.
+fn check_all(path: &PathBuf) -> Result<(usize, Vec<(Problem, PathBuf)>), anyhow::Error> {
+    let opts = args::walk_opts();
+    let files = fuzzy::walk(path, &opts)
+        .flat_map(|item| audio_paths(&item.path))
+        .collect::<Vec<PathBuf>>();
+
+    let mut problems = files
+        .par_iter()
+        .flat_map_iter(|file| check(file).into_iter().map(|p| (p, file.to_owned())))
+        .collect::<Vec<_>>();
+    problems.sort();
+
+    Ok((files.len(), problems))
+}
+
+// The audio files in `dir`, including those that tap can't play.
+fn audio_paths(dir: &Path) -> Vec<PathBuf> {
+    let entries = match dir.read_dir() {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+
+    entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && (valid_audio_ext(p) || unsupported_format(p).is_some()))
+        .collect()
+}
+
+// The problems with the audio file at `path`.
+pub fn check(path: &PathBuf) -> Vec<Problem> {
+    if unsupported_format(path).is_some() {
+        return vec![Problem::Unsupported];
+    }
+    if decode(path).is_err() {
+        return vec![Problem::Undecodable];
+    }
+
+    let mut problems = vec![];
+    match AudioFile::new(path.to_owned()) {
+        Ok(file) => {
+            if file.title == "None" || file.artist == "None" {
+                problems.push(Problem::Untagged);
+            }
+            if file.duration == 0 {
+                problems.push(Problem::NoDuration);
+            }
+        }
+        Err(_) => problems.push(Problem::Untagged),
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::find_assets_dir;
+
+    #[test]
+    fn test_check() {
+        let assets = find_assets_dir();
+
+        assert!(check(&assets.join("test_mp3_audio.mp3")).is_empty());
+        assert_eq!(
+            check(&assets.join("test_audio_invalid.mp3")),
+            vec![Problem::Undecodable]
+        );
+        assert_eq!(
+            check(&assets.join("test_audio_no_tags.mp3")),
+            vec![Problem::Untagged]
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use std::fmt;
+
+// The errors that tap exits with a distinct code for, so that scripts can tell
+// them apart. Any other error exits with 1.
+#[derive(Debug)]
+pub enum TapError {
+    // An invalid combination of options.
+    InvalidArgument(String),
+    // The path argument doesn't exist.
+    PathNotFound(String),
+    // There are no audio files to play.
+    NoAudio(String),
+    // The audio device couldn't be opened.
+    AudioDevice(String),
+    // The fuzzy-finder was closed without selecting anything.
+    NoSelection(String),
+}
+
+impl TapError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            TapError::InvalidArgument(_) => 2,
+            TapError::PathNotFound(_) => 3,
+            TapError::NoAudio(_) => 4,
+            TapError::AudioDevice(_) => 5,
+            TapError::NoSelection(_) => 6,
+        }
+    }
+}
+
+impl fmt::Display for TapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TapError::InvalidArgument(msg)
+            | TapError::PathNotFound(msg)
+            | TapError::NoAudio(msg)
+            | TapError::AudioDevice(msg)
+            | TapError::NoSelection(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for TapError {}
+
+// The code that tap exits with for `err`.
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    match err.downcast_ref::<TapError>() {
+        Some(e) => e.exit_code(),
+        None => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_code() {
+        let err = anyhow::Error::new(TapError::NoAudio(String::from("foo")));
+        assert_eq!(exit_code(&err), 4);
+        assert_eq!(err.to_string(), "foo");
+        assert_eq!(exit_code(&anyhow::anyhow!("bar")), 1);
+    }
+}
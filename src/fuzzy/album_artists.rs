@@ -0,0 +1,205 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::RwLock,
+    thread,
+};
+
+use cursive::Cursive;
+use lofty::{Accessor, ItemKey, Probe, TaggedFileExt};
+
+use crate::config::args;
+use crate::player::valid_audio_ext;
+
+use super::{sort_items, sort_key, Filter, FuzzyItem, FuzzyView, LoadingView};
+
+lazy_static::lazy_static! {
+    // The albums of each album artist, grouped on first use. Cleared when the
+    // library changes.
+    static ref ARTISTS: RwLock<Option<BTreeMap<String, Vec<FuzzyItem>>>> = RwLock::new(None);
+}
+
+// Loads a fuzzy view of the album artists of `items`. The albums are grouped
+// in the background the first time, since every album has to be read.
+pub fn load(items: Vec<FuzzyItem>, siv: &mut Cursive) {
+    if let Some(artists) = ARTISTS.read().expect("should not be poisoned").as_ref() {
+        return FuzzyView::load(artist_items(artists), Filter::Artists, siv);
+    }
+
+    if LoadingView::is_loading(siv) {
+        return;
+    }
+    LoadingView::load(siv, "grouping");
+
+    let cb_sink = siv.cb_sink().clone();
+
+    thread::spawn(move || {
+        let artists = group(&items);
+        let items = artist_items(&artists);
+        *ARTISTS.write().expect("should not be poisoned") = Some(artists);
+
+        cb_sink
+            .send(Box::new(move |siv| {
+                LoadingView::remove(siv);
+                FuzzyView::load(items, Filter::Artists, siv);
+            }))
+            .unwrap_or_default();
+    });
+}
+
+// Gets the albums of `item`, if it is an album artist.
+pub fn albums(item: &FuzzyItem) -> Option<Vec<FuzzyItem>> {
+    if !is_artist(item) {
+        return None;
+    }
+
+    ARTISTS.read().ok()?.as_ref()?.get(&item.display).cloned()
+}
+
+// Whether `item` is an album artist rather than a directory. The search root is
+// the only directory at depth zero and it is only an item if it contains audio.
+pub fn is_artist(item: &FuzzyItem) -> bool {
+    item.depth == 0 && !item.has_audio
+}
+
+// Clears the grouped albums, so that they are grouped again on next use.
+pub fn clear() {
+    if let Ok(mut artists) = ARTISTS.write() {
+        *artists = None;
+    }
+}
+
+// Groups the albums in `items` by album artist, sorted alphabetically.
+fn group(items: &Vec<FuzzyItem>) -> BTreeMap<String, Vec<FuzzyItem>> {
+    let mut artists: BTreeMap<String, Vec<FuzzyItem>> = BTreeMap::new();
+
+    for item in items.iter().filter(|e| e.has_audio) {
+        let artist = album_artist(&item.path).unwrap_or_else(|| dir_artist(item));
+        artists.entry(artist).or_default().push(item.to_owned());
+    }
+
+    for albums in artists.values_mut() {
+        sort_items(albums, args::natural_sort());
+    }
+
+    artists
+}
+
+// Creates the items shown for the album artists. The path of an artist is the
+// deepest directory that contains all of their albums.
+fn artist_items(artists: &BTreeMap<String, Vec<FuzzyItem>>) -> Vec<FuzzyItem> {
+    let mut items = artists
+        .iter()
+        .map(|(name, albums)| FuzzyItem {
+            path: common_path(albums),
+            depth: 0,
+            display: name.to_owned(),
+            key: name.chars().next().unwrap_or_default().to_ascii_uppercase(),
+            has_audio: false,
+            child_count: albums.len(),
+            indices: vec![],
+            weight: 1,
+        })
+        .collect::<Vec<FuzzyItem>>();
+
+    if args::natural_sort() {
+        items.sort_by_cached_key(|e| sort_key(&e.display));
+    }
+    items
+}
+
+// Reads the album artist of the first audio file in `dir`, falling back to the
+// artist if the album artist isn't tagged.
+fn album_artist(dir: &Path) -> Option<String> {
+    let mut paths = dir
+        .read_dir()
+        .ok()?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| valid_audio_ext(p))
+        .collect::<Vec<PathBuf>>();
+    paths.sort();
+
+    let tagged_file = Probe::open(paths.first()?).ok()?.read().ok()?;
+    let tag = tagged_file
+        .primary_tag()
+        .or_else(|| tagged_file.first_tag())?;
+
+    let artist = match tag.get_string(&ItemKey::AlbumArtist) {
+        Some(artist) => artist.to_string(),
+        None => tag.artist()?.to_string(),
+    };
+
+    let artist = artist.trim();
+    (!artist.is_empty() && artist != "None").then(|| artist.to_string())
+}
+
+// The artist of an untagged album is the name of the directory containing it,
+// as in `artist/album`, unless the album is at the top of the search root.
+fn dir_artist(item: &FuzzyItem) -> String {
+    match item.depth > 1 {
+        true => item
+            .path
+            .parent()
+            .and_then(|p| p.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or(item.display.to_owned()),
+        false => item.display.to_owned(),
+    }
+}
+
+// The deepest path that all the `items` are below.
+fn common_path(items: &[FuzzyItem]) -> PathBuf {
+    let mut paths = items.iter().map(|e| e.path.to_owned());
+    let first = paths.next().unwrap_or_default();
+
+    paths.fold(first, |common, path| {
+        common
+            .components()
+            .zip(path.components())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a)
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fuzzy::{create_items, WalkOpts};
+    use crate::utils::create_working_dir;
+
+    #[test]
+    fn test_group_albums_by_artist() {
+        let root = create_working_dir(
+            &["x/one", "y/two", "z/three"],
+            &[
+                ("x/one/a.mp3", "test_mp3_audio.mp3"),
+                ("y/two/b.flac", "test_flac_audio.flac"),
+                ("z/three/c.mp3", "test_audio_no_tags.mp3"),
+            ],
+            &[],
+        )
+        .expect("create temp dir")
+        .into_path();
+
+        let items = create_items(&root, &WalkOpts::default()).expect("should create items");
+        let artists = group(&items);
+
+        // Albums without tags are grouped by the parent directory.
+        assert_eq!(artists.keys().collect::<Vec<_>>(), ["foo", "z"]);
+        let names = |artist: &str| {
+            artists[artist]
+                .iter()
+                .map(|e| e.display.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("foo"), ["one", "two"]);
+        assert_eq!(names("z"), ["three"]);
+
+        let items = artist_items(&artists);
+        assert!(items.iter().all(is_artist));
+        assert_eq!(items[0].child_count, 2);
+        assert_eq!(items[0].path, root);
+        assert_eq!(items[1].path, root.join("z").join("three"));
+    }
+}
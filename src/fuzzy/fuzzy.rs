@@ -1,14 +1,71 @@
 use std::{
     cmp::Ordering,
+    collections::HashMap,
+    fs, io,
     path::{Path, PathBuf},
+    sync::RwLock,
+    time::{Duration, SystemTime},
 };
 
 use anyhow::bail;
 use bincode::{Decode, Encode};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
 use walkdir::{DirEntry, WalkDir};
 
+use crate::error::TapError;
 use crate::player::valid_audio_ext;
 
+// Hidden directories that are never searched, even with `--hidden`.
+const IGNORED_HIDDEN_DIRS: [&str; 8] = [
+    ".git",
+    ".svn",
+    ".hg",
+    ".cache",
+    ".Trash",
+    ".Trashes",
+    ".Spotlight-V100",
+    ".fseventsd",
+];
+
+// A file that excludes the directory it is in, and everything below it.
+const NOMEDIA: &str = ".nomedia";
+
+// A file that excludes the directory it is in if it is empty, or otherwise the
+// subdirectories whose names match the globs it contains, one per line.
+const TAPIGNORE: &str = ".tapignore";
+
+lazy_static::lazy_static! {
+    // The directories that couldn't be read, by the path that was walked to
+    // find them. Replaced each time a path is walked by `create_items`.
+    static ref DENIED: RwLock<HashMap<PathBuf, Vec<PathBuf>>> = RwLock::new(HashMap::new());
+}
+
+// The number of days that folders count as recently added for.
+pub const RECENT_DAYS: u64 = 30;
+
+// The articles that are ignored at the start of names by `sort_key`.
+const ARTICLES: [&str; 3] = ["the ", "a ", "an "];
+
+// The width that numbers are padded to by `sort_key`, so that they sort by value.
+const NUMBER_WIDTH: usize = 10;
+
+/// Options for walking the search root.
+#[derive(Clone, Debug, Default)]
+pub struct WalkOpts {
+    /// Whether or not hidden directories are searched.
+    pub hidden: bool,
+    /// The directories to skip, matched against the path relative to `root`.
+    pub exclude: GlobSet,
+    /// The search root.
+    pub root: PathBuf,
+    /// Whether or not subdirectories are walked in the order of `sort_key`.
+    pub natural_sort: bool,
+    /// The deepest level below the walked path that is searched, if limited.
+    pub max_depth: Option<usize>,
+}
+
+/// A directory that contains audio, or leads to directories that do.
 #[derive(Clone, Debug, Eq, PartialEq, Ord, Encode, Decode)]
 pub struct FuzzyItem {
     // The path of the directory entry.
@@ -85,16 +142,138 @@ impl PartialOrd for FuzzyItem {
     }
 }
 
-// Creates the list of fuzzy items from the non-hidden subdirectories of `path`.
-pub fn create_items(path: &PathBuf) -> Result<Vec<FuzzyItem>, anyhow::Error> {
-    let items = WalkDir::new(path)
+/// Lazily walks the visible subdirectories of `path`, including `path` itself,
+/// yielding the ones that are valid fuzzy items.
+pub fn walk<'a>(path: &PathBuf, opts: &'a WalkOpts) -> impl Iterator<Item = FuzzyItem> + 'a {
+    entries(path, opts).filter_map(|res| FuzzyItem::new(res).ok())
+}
+
+/// Creates the list of fuzzy items from the visible subdirectories of `path`.
+///
+/// Directories that can't be read are skipped rather than failing the walk.
+/// Those denied by permissions are kept for [`denied_dirs`].
+pub fn create_items(path: &PathBuf, opts: &WalkOpts) -> Result<Vec<FuzzyItem>, anyhow::Error> {
+    let (items, denied) = scan(path, opts);
+
+    if let Ok(mut dirs) = DENIED.write() {
+        match denied.is_empty() {
+            true => dirs.remove(path),
+            false => dirs.insert(path.to_owned(), denied),
+        };
+    }
+
+    Ok(items)
+}
+
+/// Gets the directories below the search `roots` that were skipped by the last
+/// walk of each root because they couldn't be read, sorted by path.
+pub fn denied_dirs(roots: &[PathBuf]) -> Vec<PathBuf> {
+    let dirs = DENIED.read().expect("should not be poisoned");
+    let mut denied = roots
+        .iter()
+        .filter_map(|root| dirs.get(root))
+        .flatten()
+        .cloned()
+        .collect::<Vec<PathBuf>>();
+    denied.sort();
+    denied
+}
+
+// Walks the visible subdirectories of `path`, returning the valid fuzzy items and
+// the directories that were skipped because permission to read them was denied.
+fn scan(path: &PathBuf, opts: &WalkOpts) -> (Vec<FuzzyItem>, Vec<PathBuf>) {
+    let mut items = vec![];
+    let mut denied = vec![];
+
+    for res in entries(path, opts) {
+        // Unreadable directories are listed by their parent, then fail to be
+        // read by both `FuzzyItem::new` and the walker.
+        let entry = match res {
+            Ok(entry) => entry,
+            Err(e) => {
+                if is_permission_denied(e.io_error()) {
+                    denied.extend(e.path().map(Path::to_path_buf));
+                }
+                continue;
+            }
+        };
+
+        let dir = entry.path().to_path_buf();
+        match FuzzyItem::new(Ok(entry)) {
+            Ok(item) => items.push(item),
+            Err(e) if is_permission_denied(e.downcast_ref::<io::Error>()) => denied.push(dir),
+            Err(_) => (),
+        }
+    }
+
+    denied.sort();
+    denied.dedup();
+    (items, denied)
+}
+
+fn entries<'a>(
+    path: &PathBuf,
+    opts: &'a WalkOpts,
+) -> impl Iterator<Item = Result<DirEntry, walkdir::Error>> + 'a {
+    let mut walker = WalkDir::new(path);
+    if let Some(depth) = opts.max_depth {
+        walker = walker.max_depth(depth);
+    }
+    if opts.natural_sort {
+        walker = walker.sort_by_key(|entry| sort_key(&entry.file_name().to_string_lossy()));
+    }
+
+    walker
         .into_iter()
-        .filter_entry(is_non_hidden_dir)
-        .filter_map(|res| FuzzyItem::new(res).ok())
-        .collect::<Vec<FuzzyItem>>();
+        .filter_entry(move |entry| is_visible_dir(entry, opts))
+}
+
+fn is_permission_denied(e: Option<&io::Error>) -> bool {
+    e.map_or(false, |e| e.kind() == io::ErrorKind::PermissionDenied)
+}
+
+/// Creates the list of fuzzy items for each of the search `roots`, in order.
+/// The depth of each item is relative to the root it was found in.
+pub fn create_items_in(
+    roots: &[PathBuf],
+    opts: &WalkOpts,
+) -> Result<Vec<FuzzyItem>, anyhow::Error> {
+    let mut items = vec![];
+
+    for root in roots {
+        let opts = WalkOpts {
+            root: root.to_owned(),
+            ..opts.to_owned()
+        };
+        items.append(&mut create_items(root, &opts)?);
+    }
+
     Ok(items)
 }
 
+// The search root that `path` is below, or the first root if it is below none
+// of them.
+pub fn root_of(path: &Path, roots: &[PathBuf]) -> PathBuf {
+    roots
+        .iter()
+        .find(|root| path.starts_with(root))
+        .or(roots.first())
+        .cloned()
+        .unwrap_or_default()
+}
+
+// The directory two levels above `path`, without going above `root`.
+pub fn parent_dir(path: &Path, root: &Path) -> PathBuf {
+    let mut parent = path.to_path_buf();
+
+    for _ in 0..2 {
+        if parent != root && parent.starts_with(root) {
+            parent.pop();
+        }
+    }
+    parent
+}
+
 // Gets all the non-leaf items that start with the letter `key`.
 pub fn key_items(key: Option<char>, items: &Vec<FuzzyItem>) -> Vec<FuzzyItem> {
     if let Some(key) = key {
@@ -108,22 +287,22 @@ pub fn key_items(key: Option<char>, items: &Vec<FuzzyItem>) -> Vec<FuzzyItem> {
 }
 
 // Gets all the items that are `depth` level directories, sorted alphabetically.
-pub fn depth_items(depth: usize, items: &Vec<FuzzyItem>) -> Vec<FuzzyItem> {
+pub fn depth_items(depth: usize, items: &Vec<FuzzyItem>, natural_sort: bool) -> Vec<FuzzyItem> {
     let mut items = items
         .into_iter()
         .filter(|e| e.depth == depth)
         .collect::<Vec<FuzzyItem>>();
-    items.sort();
+    sort_items(&mut items, natural_sort);
     items
 }
 
 // Gets all the non-leaf items, sorted alphabetically.
-pub fn non_leaf_items(items: &Vec<FuzzyItem>) -> Vec<FuzzyItem> {
+pub fn non_leaf_items(items: &Vec<FuzzyItem>, natural_sort: bool) -> Vec<FuzzyItem> {
     let mut items = items
         .into_iter()
         .filter(|e| e.child_count > 0)
         .collect::<Vec<FuzzyItem>>();
-    items.sort();
+    sort_items(&mut items, natural_sort);
     items
 }
 
@@ -148,10 +327,11 @@ pub fn only_audio_path(path: &PathBuf, items: &Vec<FuzzyItem>) -> Option<PathBuf
 }
 
 // Returns the path to the first directory that contains audio, if any.
-pub fn first_audio_path(path: &PathBuf) -> Result<PathBuf, anyhow::Error> {
+pub fn first_audio_path(path: &PathBuf, opts: &WalkOpts) -> Result<PathBuf, anyhow::Error> {
     let entries = WalkDir::new(path)
+        .max_depth(opts.max_depth.unwrap_or(usize::MAX))
         .into_iter()
-        .filter_entry(is_non_hidden_dir)
+        .filter_entry(|entry| is_visible_dir(entry, opts))
         .filter_map(|entry| entry.ok());
 
     for entry in entries {
@@ -159,19 +339,103 @@ pub fn first_audio_path(path: &PathBuf) -> Result<PathBuf, anyhow::Error> {
             return Ok(path.to_owned());
         }
     }
-    bail!("no audio files detected in '{}'", path.display())
+    bail!(TapError::NoAudio(format!(
+        "no audio files detected in '{}'",
+        path.display()
+    )))
 }
 
 // Gets all the leaf items, sorted alphabetically.
-pub fn audio_items(items: &Vec<FuzzyItem>) -> Vec<FuzzyItem> {
+pub fn audio_items(items: &Vec<FuzzyItem>, natural_sort: bool) -> Vec<FuzzyItem> {
     let mut items = items
         .into_iter()
         .filter(|e| e.has_audio)
         .collect::<Vec<FuzzyItem>>();
-    items.sort();
+    sort_items(&mut items, natural_sort);
     items
 }
 
+// Gets the leaf items that were added in the last `days` days, newest first.
+pub fn recent_items(items: &Vec<FuzzyItem>, days: u64) -> Vec<FuzzyItem> {
+    let since = SystemTime::now()
+        .checked_sub(Duration::from_secs(days * 24 * 60 * 60))
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    added_since(items, since, added)
+}
+
+fn added_since(
+    items: &Vec<FuzzyItem>,
+    since: SystemTime,
+    added: fn(&Path) -> Option<SystemTime>,
+) -> Vec<FuzzyItem> {
+    let mut recent = items
+        .iter()
+        .filter(|e| e.has_audio)
+        .filter_map(|e| Some((added(&e.path)?, e)))
+        .filter(|(time, _)| *time >= since)
+        .collect::<Vec<_>>();
+    recent.sort_by(|a, b| b.0.cmp(&a.0));
+    recent.into_iter().map(|(_, e)| e.to_owned()).collect()
+}
+
+// The time the directory was created, or last modified on filesystems that
+// don't record creation times.
+fn added(path: &Path) -> Option<SystemTime> {
+    let metadata = path.metadata().ok()?;
+    metadata.created().or_else(|_| metadata.modified()).ok()
+}
+
+/// Sorts `items` by path. With `natural_sort` each component of the path is
+/// compared by its `sort_key` instead.
+pub fn sort_items(items: &mut Vec<FuzzyItem>, natural_sort: bool) {
+    match natural_sort {
+        true => items.sort_by_cached_key(|e| {
+            e.path
+                .iter()
+                .map(|name| sort_key(&name.to_string_lossy()))
+                .collect::<Vec<String>>()
+        }),
+        false => items.sort(),
+    }
+}
+
+/// Normalizes `name` for sorting, so that names sort the way people expect
+/// rather than by their literal characters. Case and diacritics are ignored,
+/// a leading "The", "A" or "An" is ignored and numbers are zero-padded so that
+/// "Album 2" sorts before "Album 10".
+pub fn sort_key(name: &str) -> String {
+    let name = name
+        .nfd()
+        .filter(|c| !is_combining_mark(*c))
+        .collect::<String>()
+        .to_lowercase();
+    let name = name.trim();
+
+    let name = ARTICLES
+        .iter()
+        .find_map(|article| name.strip_prefix(article))
+        .map(str::trim_start)
+        .filter(|rest| !rest.is_empty())
+        .unwrap_or(name);
+
+    let mut key = String::with_capacity(name.len());
+    let mut chars = name.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c.is_ascii_digit() {
+            let mut number = String::from(c);
+            while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                number.push(digit);
+            }
+            key.push_str(&format!("{:0>width$}", number, width = NUMBER_WIDTH));
+        } else {
+            key.push(c);
+        }
+    }
+
+    key
+}
+
 // Gets all the leaf paths.
 pub fn leaf_paths(items: &Vec<FuzzyItem>) -> Vec<PathBuf> {
     items
@@ -181,14 +445,66 @@ pub fn leaf_paths(items: &Vec<FuzzyItem>) -> Vec<PathBuf> {
         .collect::<Vec<PathBuf>>()
 }
 
-// Whether the entry is a directory or not. Excludes hidden directories.
-fn is_non_hidden_dir(entry: &walkdir::DirEntry) -> bool {
-    entry.file_type().is_dir()
-        && !entry
-            .file_name()
-            .to_str()
-            .map(|s| s.starts_with("."))
-            .unwrap_or(false)
+// Whether the entry is a directory that should be searched. Directories matching
+// `opts.exclude` or excluded by a marker file are skipped along with their
+// descendants. Hidden directories are excluded unless `opts.hidden` is set, in
+// which case only the directories in `IGNORED_HIDDEN_DIRS` are excluded.
+fn is_visible_dir(entry: &DirEntry, opts: &WalkOpts) -> bool {
+    if !entry.file_type().is_dir() {
+        return false;
+    }
+
+    let relative = entry
+        .path()
+        .strip_prefix(&opts.root)
+        .unwrap_or(entry.path());
+
+    if opts.exclude.is_match(relative) {
+        return false;
+    }
+
+    let name = entry.file_name().to_str().unwrap_or_default();
+
+    if name.starts_with('.') && !(opts.hidden && !IGNORED_HIDDEN_DIRS.contains(&name)) {
+        return false;
+    }
+
+    // The path that was walked is searched even if it is marked.
+    entry.depth() == 0 || !is_ignored(entry.path())
+}
+
+// Whether the directory is excluded by a `.nomedia` or `.tapignore` file, in it
+// or, for `.tapignore` globs, in its parent.
+fn is_ignored(path: &Path) -> bool {
+    if path.join(NOMEDIA).exists() {
+        return true;
+    }
+
+    if ignore_globs(path).map_or(false, |globs| globs.is_empty()) {
+        return true;
+    }
+
+    match (path.parent().and_then(ignore_globs), path.file_name()) {
+        (Some(globs), Some(name)) => globs.is_match(name),
+        _ => false,
+    }
+}
+
+// The globs in the `.tapignore` file in `dir`, if there is one. Blank lines,
+// lines starting with `#` and invalid globs are skipped.
+fn ignore_globs(dir: &Path) -> Option<GlobSet> {
+    let text = fs::read_to_string(dir.join(TAPIGNORE)).ok()?;
+    let mut builder = GlobSetBuilder::new();
+
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| Glob::new(line).ok())
+        .for_each(|glob| {
+            builder.add(glob);
+        });
+
+    builder.build().ok()
 }
 
 // Whether or not the path is a directory that contains audio.
@@ -229,3 +545,339 @@ fn validate(path: &PathBuf) -> Result<(bool, usize), anyhow::Error> {
 
     Ok((has_audio, dir_count))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::create_working_dir;
+
+    #[test]
+    fn test_sort_key() {
+        // Articles are ignored, unless they are the whole name.
+        assert_eq!(sort_key("The Beatles"), "beatles");
+        assert_eq!(sort_key("A Tribe Called Quest"), "tribe called quest");
+        assert_eq!(sort_key("An  Album"), "album");
+        assert_eq!(sort_key("The"), "the");
+        assert_eq!(sort_key("Theatre"), "theatre");
+
+        // Numbers sort by value.
+        assert!(sort_key("Album 2") < sort_key("Album 10"));
+        assert!(sort_key("2Pac") < sort_key("10cc"));
+        assert!(sort_key("Disc 9") < sort_key("disc 09b"));
+
+        // Diacritics and case are ignored.
+        assert_eq!(sort_key("Sigur Rós"), "sigur ros");
+        assert_eq!(sort_key("Björk"), sort_key("bjork"));
+        assert!(sort_key("Édith Piaf") < sort_key("Fleetwood Mac"));
+    }
+
+    #[test]
+    fn test_hidden_dir_requires_hidden_opt() {
+        let root = create_working_dir(
+            &["one", ".hidden", ".git"],
+            &[
+                ("one/a.mp3", "test_mp3_audio.mp3"),
+                (".hidden/b.mp3", "test_mp3_audio.mp3"),
+                (".git/c.mp3", "test_mp3_audio.mp3"),
+            ],
+            &[],
+        )
+        .expect("create temp dir")
+        .into_path();
+
+        let contains = |items: &Vec<FuzzyItem>, name: &str| items.iter().any(|e| e.display == name);
+
+        let items = create_items(&root, &WalkOpts::default()).expect("should create items");
+        assert!(contains(&items, "one"));
+        assert!(!contains(&items, ".hidden"));
+
+        let opts = WalkOpts {
+            hidden: true,
+            ..Default::default()
+        };
+        let items = create_items(&root, &opts).expect("should create items");
+        assert!(contains(&items, "one"));
+        assert!(contains(&items, ".hidden"));
+        assert!(!contains(&items, ".git"));
+    }
+
+    #[test]
+    fn test_walk_matches_create_items() {
+        let root = create_working_dir(
+            &["b", "b/two", "a", "a/one", "c"],
+            &[
+                ("b/two/a.mp3", "test_mp3_audio.mp3"),
+                ("a/one/b.mp3", "test_mp3_audio.mp3"),
+                ("c/c.mp3", "test_mp3_audio.mp3"),
+            ],
+            &[],
+        )
+        .expect("create temp dir")
+        .into_path();
+
+        // The lazy walk used by `--doctor` finds the same directories, in the
+        // same order, as the walk that the fuzzy-finder is loaded from.
+        for natural_sort in [false, true] {
+            let opts = WalkOpts {
+                natural_sort,
+                ..Default::default()
+            };
+            let walked = walk(&root, &opts).map(|e| e.path).collect::<Vec<_>>();
+            let created = create_items(&root, &opts)
+                .expect("should create items")
+                .into_iter()
+                .map(|e| e.path)
+                .collect::<Vec<_>>();
+
+            assert_eq!(walked.len(), 6);
+            assert_eq!(walked, created);
+        }
+    }
+
+    #[test]
+    fn test_audio_items_exclude_non_audio_parents() {
+        let root = create_working_dir(
+            &["artist", "artist/album_a", "artist/album_b"],
+            &[
+                ("artist/album_a/a.mp3", "test_mp3_audio.mp3"),
+                ("artist/album_b/b.mp3", "test_mp3_audio.mp3"),
+            ],
+            &[],
+        )
+        .expect("create temp dir")
+        .into_path();
+
+        let items = create_items(&root, &WalkOpts::default()).expect("should create items");
+        let names = audio_items(&items, false)
+            .into_iter()
+            .map(|e| e.display)
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, vec!["album_a", "album_b"]);
+    }
+
+    #[test]
+    fn test_excluded_subtree_is_absent() {
+        let root = create_working_dir(
+            &[
+                "one",
+                "one/samples",
+                "one/samples/kicks",
+                "two",
+                "two/stems",
+            ],
+            &[
+                ("one/a.mp3", "test_mp3_audio.mp3"),
+                ("one/samples/b.mp3", "test_mp3_audio.mp3"),
+                ("one/samples/kicks/c.mp3", "test_mp3_audio.mp3"),
+                ("two/d.mp3", "test_mp3_audio.mp3"),
+                ("two/stems/e.mp3", "test_mp3_audio.mp3"),
+            ],
+            &[],
+        )
+        .expect("create temp dir")
+        .into_path();
+
+        let mut builder = globset::GlobSetBuilder::new();
+        builder.add(globset::Glob::new("one/samples").expect("valid glob"));
+        builder.add(globset::Glob::new("**/stems").expect("valid glob"));
+
+        let opts = WalkOpts {
+            exclude: builder.build().expect("valid glob set"),
+            root: root.to_owned(),
+            ..Default::default()
+        };
+        let items = create_items(&root, &opts).expect("should create items");
+        let names = items.iter().map(|e| e.display.as_str()).collect::<Vec<_>>();
+
+        assert!(names.contains(&"one"));
+        assert!(names.contains(&"two"));
+        assert!(!names.contains(&"samples"));
+        assert!(!names.contains(&"kicks"));
+        assert!(!names.contains(&"stems"));
+    }
+
+    #[test]
+    fn test_max_depth_bounds_the_walk() {
+        let root = create_working_dir(
+            &["one/two/three/four"],
+            &[
+                ("one/a.mp3", "test_mp3_audio.mp3"),
+                ("one/two/b.mp3", "test_mp3_audio.mp3"),
+                ("one/two/three/c.mp3", "test_mp3_audio.mp3"),
+                ("one/two/three/four/d.mp3", "test_mp3_audio.mp3"),
+            ],
+            &[],
+        )
+        .expect("create temp dir")
+        .into_path();
+
+        let names = |opts: &WalkOpts| {
+            create_items(&root, opts)
+                .expect("should create items")
+                .into_iter()
+                .skip(1)
+                .map(|e| e.display)
+                .collect::<Vec<_>>()
+        };
+
+        let opts = WalkOpts {
+            max_depth: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(names(&opts), vec!["one", "two"]);
+        assert_eq!(names(&WalkOpts::default()), vec!["one", "two", "three", "four"]);
+
+        // The directories below the cap are found when walking from the deepest
+        // directory that was searched.
+        let items = create_items(&root.join("one/two"), &opts).expect("should create items");
+        assert_eq!(items.len(), 3);
+    }
+
+    #[test]
+    fn test_marked_subtree_is_absent() {
+        let root = create_working_dir(
+            &[
+                "one",
+                "one/podcasts",
+                "one/podcasts/episode",
+                "two",
+                "two/demos",
+                "two/live",
+                "three",
+            ],
+            &[
+                ("one/a.mp3", "test_mp3_audio.mp3"),
+                ("one/podcasts/b.mp3", "test_mp3_audio.mp3"),
+                ("one/podcasts/episode/c.mp3", "test_mp3_audio.mp3"),
+                ("two/d.mp3", "test_mp3_audio.mp3"),
+                ("two/demos/e.mp3", "test_mp3_audio.mp3"),
+                ("two/live/f.mp3", "test_mp3_audio.mp3"),
+                ("three/g.mp3", "test_mp3_audio.mp3"),
+            ],
+            &["one/podcasts/.nomedia", "three/.tapignore"],
+        )
+        .expect("create temp dir")
+        .into_path();
+
+        fs::write(root.join("two").join(TAPIGNORE), "# not these\ndemo*\n").unwrap();
+
+        let items = create_items(&root, &WalkOpts::default()).expect("should create items");
+        let names = items.iter().map(|e| e.display.as_str()).collect::<Vec<_>>();
+
+        assert!(names.contains(&"one"));
+        assert!(names.contains(&"two"));
+        assert!(names.contains(&"live"));
+        assert!(!names.contains(&"podcasts"));
+        assert!(!names.contains(&"episode"));
+        assert!(!names.contains(&"demos"));
+        assert!(!names.contains(&"three"));
+
+        // The path that was walked is searched even if it is marked.
+        let items = create_items(&root.join("three"), &WalkOpts::default())
+            .expect("should create items");
+        assert_eq!(items.len(), 1);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_unreadable_dir_is_skipped_and_reported() {
+        use std::{fs, os::unix::fs::PermissionsExt};
+
+        let root = create_working_dir(
+            &["one", "locked/two"],
+            &[
+                ("one/a.mp3", "test_mp3_audio.mp3"),
+                ("locked/two/b.mp3", "test_mp3_audio.mp3"),
+            ],
+            &[],
+        )
+        .expect("create temp dir")
+        .into_path();
+        let locked = root.join("locked");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).expect("chmod");
+
+        // Permissions don't apply to root, so there is nothing to test.
+        if locked.read_dir().is_ok() {
+            return;
+        }
+
+        let items = create_items(&root, &WalkOpts::default()).expect("should create items");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).expect("chmod");
+
+        let names = items.iter().map(|e| e.display.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["one"]);
+        assert_eq!(denied_dirs(&[root.to_owned()]), [locked]);
+    }
+
+    #[test]
+    fn test_recently_added_window() {
+        use std::fs::File;
+
+        let root = create_working_dir(
+            &["old", "new", "newer"],
+            &[
+                ("old/a.mp3", "test_mp3_audio.mp3"),
+                ("new/b.mp3", "test_mp3_audio.mp3"),
+                ("newer/c.mp3", "test_mp3_audio.mp3"),
+            ],
+            &[],
+        )
+        .expect("create temp dir")
+        .into_path();
+
+        let day = Duration::from_secs(24 * 60 * 60);
+        let now = SystemTime::now();
+        for (name, days) in [("old", 40), ("new", 10), ("newer", 2)] {
+            File::open(root.join(name))
+                .and_then(|dir| dir.set_modified(now - day * days))
+                .expect("should set mtime");
+        }
+
+        let items = create_items(&root, &WalkOpts::default()).expect("should create items");
+        let modified = |path: &Path| path.metadata().ok()?.modified().ok();
+        let names = |since| {
+            added_since(&items, since, modified)
+                .into_iter()
+                .map(|e| e.display)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(now - day * 30), ["newer", "new"]);
+        assert_eq!(names(now - day * 5), ["newer"]);
+        assert!(names(now).is_empty());
+    }
+
+    #[test]
+    fn test_multiple_roots() {
+        let music = create_working_dir(
+            &["artist", "artist/album"],
+            &[("artist/album/a.mp3", "test_mp3_audio.mp3")],
+            &[],
+        )
+        .expect("create temp dir")
+        .into_path();
+        let archive = create_working_dir(&["old"], &[("old/b.mp3", "test_mp3_audio.mp3")], &[])
+            .expect("create temp dir")
+            .into_path();
+        let roots = vec![music.to_owned(), archive.to_owned()];
+
+        let items = create_items_in(&roots, &WalkOpts::default()).expect("should create items");
+        // The items of each root are kept in order.
+        let albums = items
+            .iter()
+            .filter(|e| e.has_audio)
+            .map(|e| (e.display.as_str(), e.depth))
+            .collect::<Vec<_>>();
+        assert_eq!(albums, [("album", 2), ("old", 1)]);
+
+        let old = archive.join("old");
+        assert_eq!(root_of(&old, &roots), archive);
+        assert_eq!(root_of(&music.join("artist"), &roots), music);
+
+        // Parent navigation stops at the root of the path.
+        assert_eq!(parent_dir(&old, &archive), archive);
+        let album = music.join("artist").join("album");
+        assert_eq!(parent_dir(&album, &music), music);
+    }
+}
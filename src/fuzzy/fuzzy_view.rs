@@ -1,22 +1,106 @@
-use std::path::PathBuf;
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    sync::RwLock,
+    thread,
+    time::{Duration, Instant},
+};
 
+use anyhow::bail;
 use cursive::{
     event::{Event, EventResult, EventTrigger, Key, MouseButton, MouseEvent},
     theme::Effect,
-    view::Resizable,
+    view::{Nameable, Resizable},
     views::LayerPosition,
     Cursive, Printer, View, XY,
 };
 use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use rayon::prelude::*;
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 
-use crate::config::{args, theme};
-use crate::data::session_data::SessionData;
-use crate::player::{PlayerBuilder, PlayerView};
+use crate::config::{
+    args::{self, Column},
+    glyphs, theme,
+};
+use crate::data::{favorites, persistent_data, play_counts, session_data::SessionData};
+use crate::error::TapError;
+use crate::player::{discs, player_view, terminal, PlayerBuilder, PlayerView};
 use crate::utils::{self, InnerType};
 
-use super::{create_items, ErrorView, FuzzyItem};
+use super::{
+    album_artists, audio_items, create_items, create_items_in, denied_dirs, leaf_paths, parent_dir,
+    ErrorView, FuzzyItem, LoadingView, TracklistView, WalkOpts,
+};
+
+// The name used to find the fuzzy view in the view stack.
+pub const NAME: &str = "fuzzy";
+
+// How long a request to move the selected item to the trash waits for confirmation.
+const TRASH_TIMEOUT: Duration = Duration::from_secs(2);
+
+// How long typing has to pause before the only match is selected, with
+// `--auto-select`.
+const AUTO_SELECT_DELAY: Duration = Duration::from_millis(200);
+
+lazy_static::lazy_static! {
+    // The items used by the fuzzy-finder callbacks. Replaced on reload.
+    static ref LIBRARY: RwLock<Vec<FuzzyItem>> = RwLock::new(vec![]);
+    // The directory selected with `--print-selection`, printed once the event
+    // loop ends.
+    static ref SELECTION: RwLock<Option<PathBuf>> = RwLock::new(None);
+    // The fuzzy-finder that was last left for the player, with its query and
+    // selection, to return to with `Shift` + `Tab`.
+    static ref LAST_FINDER: RwLock<Option<FuzzyView>> = RwLock::new(None);
+}
+
+// The subset of the library that a fuzzy-finder searches, shown on its top row.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Filter {
+    // Everything, or the items of a search that isn't filtered.
+    #[default]
+    None,
+    // The artists, with `Ctrl` + `a`.
+    Artists,
+    // The albums, with `Ctrl` + `s`.
+    Albums,
+    // The favorites, with `Ctrl` + `f`.
+    Favorites,
+    // The recently added albums, with `Ctrl` + `w`.
+    Recent,
+    // The most played albums, with `Ctrl` + `b`.
+    MostPlayed,
+    // The directories at a depth, with `F1` to `F4`.
+    Depth(usize),
+    // The artists starting with a letter, with `A` to `Z`.
+    Key(char),
+    // The albums of an album artist.
+    AlbumArtist(String),
+    // The contents of a directory.
+    Dir(PathBuf),
+}
+
+impl Filter {
+    // The filter in words, if there is one.
+    pub fn label(&self) -> Option<String> {
+        let label = match self {
+            Filter::None => return None,
+            Filter::Artists => "artists".to_string(),
+            Filter::Albums => "albums".to_string(),
+            Filter::Favorites => "favorites".to_string(),
+            Filter::Recent => "recently added".to_string(),
+            Filter::MostPlayed => "most played".to_string(),
+            Filter::Depth(depth) => format!("depth {depth}"),
+            Filter::Key(key) => format!("starting with {}", key.to_ascii_uppercase()),
+            Filter::AlbumArtist(artist) => format!("by {artist}"),
+            Filter::Dir(dir) => match dir.file_name() {
+                Some(name) => format!("in {}", name.to_string_lossy()),
+                None => format!("in {}", dir.display()),
+            },
+        };
+        Some(label)
+    }
+}
 
 #[derive(Clone)]
 pub struct FuzzyView {
@@ -36,6 +120,22 @@ pub struct FuzzyView {
     available_y: usize,
     // The size of the view.
     size: XY<usize>,
+    // The instant the selected item was requested to be moved to the trash.
+    // `Some` while waiting for confirmation.
+    trash_requested: Option<Instant>,
+    // The instant the query was last edited, if it left a single match to
+    // select with `--auto-select`.
+    single_match_at: Option<Instant>,
+    // The number of directories below the search roots that couldn't be read.
+    denied: usize,
+    // Whether or not the scrollbar is being dragged with the mouse.
+    scrolling: bool,
+    // The subset of the library the items are from.
+    filter: Filter,
+    // Whether or not the selection follows the album that is playing.
+    following: bool,
+    // The path of the album last selected while following, if any.
+    followed: Option<PathBuf>,
 }
 
 impl FuzzyView {
@@ -49,22 +149,52 @@ impl FuzzyView {
             items,
             available_y: 0,
             size: XY { x: 0, y: 0 },
+            trash_requested: None,
+            single_match_at: None,
+            denied: denied_dirs(args::search_roots()).len(),
+            scrolling: false,
+            filter: Filter::None,
+            following: false,
+            followed: None,
         }
     }
 
-    // Loads a new FuzzyView from the provided items. Providing a `key` will
-    // pre-match the results using the char.
-    pub fn load(items: Vec<FuzzyItem>, key: Option<char>, siv: &mut Cursive) {
+    // Loads a new FuzzyView from the provided items, which are the subset of the
+    // library given by `filter`. Filtering by key will pre-match the results
+    // using the char.
+    pub fn load(items: Vec<FuzzyItem>, filter: Filter, siv: &mut Cursive) {
         let mut fuzzy = FuzzyView::new(items);
 
-        if let Some(key) = key {
+        if let Filter::Key(key) = filter {
             fuzzy.insert(key.to_ascii_lowercase());
         }
+        fuzzy.filter = filter;
+        fuzzy.show(siv);
+    }
 
-        siv.add_layer(fuzzy.full_screen());
+    // Loads a new FuzzyView of the contents of `dir`, with `child`, the
+    // directory that was navigated up from, selected.
+    pub fn load_parent(dir: PathBuf, child: &Path, siv: &mut Cursive) {
+        if let Ok(items) = load_items(&dir) {
+            let mut fuzzy = FuzzyView::new(items);
+            fuzzy.select_path(child);
+            fuzzy.filter = Filter::Dir(dir);
+            fuzzy.show(siv);
+        }
+    }
+
+    fn show(self, siv: &mut Cursive) {
+        siv.add_layer(self.with_name(NAME).full_screen());
         remove_layer(siv);
     }
 
+    // Replaces the items to fuzzy search on, keeping the current query.
+    fn set_items(&mut self, items: Vec<FuzzyItem>) {
+        self.items = items;
+        self.denied = denied_dirs(args::search_roots()).len();
+        self.update_list(&self.query.clone());
+    }
+
     // Moves the selection down one row.
     fn move_down(&mut self) {
         if self.selected == 0 {
@@ -78,7 +208,7 @@ impl FuzzyView {
 
     // Moves the selection up one row.
     fn move_up(&mut self) {
-        if self.selected == self.matches - 1 {
+        if self.matches == 0 || self.selected == self.matches - 1 {
             return;
         }
         if self.selected - self.offset_y >= self.available_y {
@@ -120,6 +250,60 @@ impl FuzzyView {
         }
     }
 
+    // Moves the selection to the next match that starts with `ch`, wrapping
+    // around to the first match. The query is left unchanged.
+    fn jump_to_key(&mut self, ch: char) {
+        let key = ch.to_uppercase().next().unwrap_or(ch);
+        let next = (1..=self.matches)
+            .map(|i| (self.selected + i) % self.matches)
+            .find(|&i| self.items[i].key == key);
+
+        if let Some(index) = next {
+            self.select(index);
+        }
+    }
+
+    // Selects the match at `index`, scrolling the list the least needed to show it.
+    fn select(&mut self, index: usize) {
+        self.selected = index;
+        if self.selected < self.offset_y {
+            self.offset_y = self.selected;
+        } else if self.selected > self.offset_y + self.available_y {
+            self.offset_y = self.selected - self.available_y;
+        }
+    }
+
+    // Selects the item for `path`, or else the closest item that contains it.
+    fn select_path(&mut self, path: &Path) {
+        let index = self
+            .items
+            .iter()
+            .take(self.matches)
+            .enumerate()
+            .filter(|(_, item)| path.starts_with(&item.path))
+            .max_by_key(|(_, item)| item.path.components().count())
+            .map(|(index, _)| index);
+
+        if let Some(index) = index {
+            self.select(index);
+        }
+    }
+
+    // Starts or stops the selection following the album that is playing.
+    fn toggle_follow(&mut self) {
+        self.following = !self.following;
+        self.followed = None;
+    }
+
+    // Selects the album at `path` when following, if it has changed.
+    fn follow(&mut self, path: &Path) {
+        if !self.following || self.followed.as_deref() == Some(path) {
+            return;
+        }
+        self.select_path(path);
+        self.followed = Some(path.to_owned());
+    }
+
     // Moves the selection to a random page.
     fn random_page(&mut self) {
         if self.items.len() <= self.available_y {
@@ -190,7 +374,11 @@ impl FuzzyView {
         self.query.insert(self.cursor, ch);
         let shift = ch.len_utf8();
         self.cursor += shift;
-        self.update_list(&self.query.to_owned());
+
+        // Items that didn't match the query can't match it with a character
+        // added, so only the current matches are matched again.
+        let count = self.matches;
+        self.match_list(&self.query.to_owned(), count);
     }
 
     // Removes the current fuzzy query.
@@ -202,10 +390,16 @@ impl FuzzyView {
 
     // Runs the fuzzy matcher on the query.
     fn update_list(&mut self, pattern: &str) {
+        self.match_list(pattern, self.items.len())
+    }
+
+    // Runs the fuzzy matcher on the query for the first `count` items. The
+    // remaining items are left unmatched.
+    fn match_list(&mut self, pattern: &str, count: usize) {
         if self.query.is_empty() {
-            for (i, _) in self.items.clone().into_iter().enumerate() {
-                self.items[i].weight = 1;
-                self.items[i].indices.clear();
+            for item in self.items.iter_mut() {
+                item.weight = 1;
+                item.indices.clear();
             }
             self.matches = self.items.len();
             self.selected = 0;
@@ -213,32 +407,75 @@ impl FuzzyView {
             return;
         }
 
-        self.matches = self.fuzzy_match(pattern);
-        self.sort();
+        self.matches = self.fuzzy_match(pattern, count);
+        self.sort(count);
         self.selected = 0;
         self.offset_y = 0;
     }
 
-    // Sort the items by `weight` in descending order.
-    fn sort(&mut self) {
-        self.items.sort_by(|a, b| b.weight.cmp(&a.weight))
+    // Sort the first `count` items by `weight` in descending order. The sort is
+    // stable, so items with equal weights keep their order.
+    fn sort(&mut self, count: usize) {
+        self.items[..count].par_sort_by(|a, b| b.weight.cmp(&a.weight))
     }
 
-    // Computes the weights for the items on fuzzy matching with the query.
-    fn fuzzy_match(&mut self, pattern: &str) -> usize {
-        let mut count = 0;
-        let matcher = Box::new(SkimMatcherV2::default());
-        for (i, item) in self.items.clone().into_iter().enumerate() {
-            if let Some((weight, indices)) = matcher.fuzzy_indices(&item.display, pattern) {
-                self.items[i].weight = weight;
-                self.items[i].indices = indices;
-                count += 1;
-            } else {
-                self.items[i].weight = 0;
-                self.items[i].indices.clear();
-            }
+    // Computes the weights for the first `count` items on fuzzy matching with
+    // the query. Each item is matched independently, so they are matched in
+    // parallel.
+    fn fuzzy_match(&mut self, pattern: &str, count: usize) -> usize {
+        let matcher = SkimMatcherV2::default();
+        self.items[..count]
+            .par_iter_mut()
+            .map(|item| match matcher.fuzzy_indices(&item.display, pattern) {
+                Some((weight, indices)) => {
+                    item.weight = weight;
+                    item.indices = indices;
+                    1
+                }
+                None => {
+                    item.weight = 0;
+                    item.indices.clear();
+                    0
+                }
+            })
+            .sum()
+    }
+
+    // The extra information shown after the display name of `item`, as
+    // selected with `--columns`.
+    fn columns(&self, item: &FuzzyItem) -> String {
+        args::columns()
+            .iter()
+            .filter_map(|column| match column {
+                Column::Path => item
+                    .path
+                    .strip_prefix(args::root_of(&item.path))
+                    .ok()
+                    .and_then(|p| p.parent())
+                    .filter(|p| !p.as_os_str().is_empty())
+                    .map(|p| p.display().to_string()),
+                Column::Depth => Some(format!("depth {}", item.depth)),
+                Column::Audio => item.has_audio.then(|| String::from("audio")),
+                Column::Count => (item.child_count > 0).then(|| match item.child_count {
+                    1 => String::from("1 folder"),
+                    n => format!("{} folders", n),
+                }),
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+    }
+
+    // The selected item, if there are any matches.
+    pub fn selected_item(&self) -> Option<&FuzzyItem> {
+        match self.matches {
+            0 => None,
+            _ => self.items.get(self.selected),
         }
-        count
+    }
+
+    // The number of matched items and the total number of items.
+    pub fn counts(&self) -> (usize, usize) {
+        (self.matches, self.items.len())
     }
 
     // The number of matched items over total items.
@@ -247,8 +484,10 @@ impl FuzzyView {
     }
 
     // Handles a fuzzy match being selected.
-    fn on_select(&mut self) -> EventResult {
-        if self.items.is_empty() {
+    // With `search_subdirs`, directories with subdirectories are always searched,
+    // even if they would otherwise be played.
+    fn on_select(&mut self, search_subdirs: bool) -> EventResult {
+        if self.matches == 0 {
             return EventResult::with_cb(|siv| {
                 let err = anyhow::Error::msg("Nothing to select!");
                 ErrorView::load(siv, err)
@@ -256,12 +495,17 @@ impl FuzzyView {
         }
 
         let item = self.items[self.selected].to_owned();
+        self.stash();
 
         EventResult::with_cb(move |siv| {
-            if item.child_count == 0 {
+            if let Some(albums) = album_artists::albums(&item) {
+                FuzzyView::load(albums, Filter::AlbumArtist(item.display.to_owned()), siv);
+            } else if plays_on_select(&item, prefers_audio() && !search_subdirs)
+                || (!search_subdirs && is_multi_disc(&item))
+            {
                 select_player(item.to_owned(), siv);
             } else {
-                let items = create_items(&item.path).expect("should always exist");
+                let items = load_items(&item.path).expect("should always exist");
 
                 if items.len() == 1 {
                     let item = items.first().unwrap();
@@ -271,47 +515,136 @@ impl FuzzyView {
                     }
                 }
 
-                FuzzyView::load(items, None, siv);
+                FuzzyView::load(items, Filter::Dir(item.path.to_owned()), siv);
             }
         })
     }
 
+    // Keeps a copy of the fuzzy-finder, so that it can be returned to as it is.
+    fn stash(&self) {
+        *LAST_FINDER.write().expect("should not be poisoned") = Some(self.to_owned());
+    }
+
     // Handles a selection from mouse input.
     fn mouse_select(&mut self, position: XY<usize>) -> EventResult {
         if position.y < 1 || position.y > self.available_y + 1 {
             return EventResult::Consumed(None);
         }
 
+        // The last column is a scrollbar for the whole list.
+        if position.x + 1 == self.size.x {
+            self.scrolling = true;
+            self.mouse_scroll(position);
+            return EventResult::Consumed(None);
+        }
+
         let next_selected = self.available_y + 1 + self.offset_y - position.y;
 
-        if next_selected == self.selected {
-            return self.on_select();
+        if next_selected >= self.matches {
+            EventResult::Consumed(None)
+        } else if next_selected == self.selected {
+            return self.on_select(false);
         } else {
             self.selected = next_selected;
             EventResult::Consumed(None)
         }
     }
 
-    // Loads a fuzzy view for the parent of the current directory.
+    // Selects the match at the same position in the list as `position` is on
+    // the scrollbar, while the scrollbar is being dragged.
+    fn mouse_scroll(&mut self, position: XY<usize>) {
+        if self.scrolling && self.matches > 0 {
+            self.select(scroll_index(position.y, self.available_y, self.matches));
+        }
+    }
+
+    // Loads a fuzzy view for the parent of the current directory, with the
+    // current directory selected.
     fn parent(&self) -> EventResult {
-        let mut parent = match self.items.first() {
-            Some(parent) => parent.path.to_owned(),
+        let (parent, child) = match self.items.first() {
+            Some(item) => (
+                parent_dir(&item.path, &args::root_of(&item.path)),
+                item.path.parent().unwrap_or(&item.path).to_owned(),
+            ),
             None => return EventResult::Ignored,
         };
 
-        let root = args::search_root();
-        if parent != root {
-            parent.pop();
-            if parent != root {
-                parent.pop();
+        return EventResult::with_cb(move |siv| {
+            FuzzyView::load_parent(parent.to_owned(), &child, siv)
+        });
+    }
+
+    // Whether or not moving the selected item to the trash awaits confirmation.
+    fn is_trash_requested(&self) -> bool {
+        self.trash_requested
+            .map_or(false, |instant| instant.elapsed() < TRASH_TIMEOUT)
+    }
+
+    // Moves the selected item to the trash and removes it, and any items
+    // below it, from the fuzzy-finder. Items are never deleted outright.
+    fn trash(&mut self) -> EventResult {
+        self.trash_with(|path| trash::delete(path))
+    }
+
+    // Moves the selected item out of the library once `delete` has removed its
+    // directory.
+    fn trash_with(&mut self, delete: fn(&Path) -> Result<(), trash::Error>) -> EventResult {
+        // Album artists aren't directories, so there's nothing to trash.
+        if self.matches == 0 || album_artists::is_artist(&self.items[self.selected]) {
+            return EventResult::Consumed(None);
+        }
+
+        let path = self.items[self.selected].path.to_owned();
+
+        if let Err(e) = delete(&path) {
+            let err = anyhow::anyhow!("could not move '{}' to the trash: {}", path.display(), e);
+            return EventResult::with_cb(move |siv| ErrorView::load(siv, err));
+        }
+
+        self.items.retain(|e| !e.path.starts_with(&path));
+        self.update_list(&self.query.clone());
+
+        EventResult::with_cb(move |siv| remove_from_library(siv, &path))
+    }
+
+    // The instant to wait from before selecting the only match, if the query
+    // matches a single item that is played on selection.
+    fn single_match(&self) -> Option<Instant> {
+        let is_single = args::auto_select()
+            && self.matches == 1
+            && !self.query.is_empty()
+            && plays_on_select(&self.items[0], prefers_audio());
+
+        is_single.then(Instant::now)
+    }
+
+    // Selects the only match once typing has paused.
+    fn auto_select(&mut self) -> EventResult {
+        match self.single_match_at {
+            Some(instant) if instant.elapsed() >= AUTO_SELECT_DELAY => {
+                self.single_match_at = None;
+                self.on_select(false)
             }
+            _ => EventResult::Ignored,
         }
+    }
 
-        return EventResult::with_cb(move |siv| {
-            if let Ok(items) = create_items(&parent) {
-                FuzzyView::load(items, None, siv);
+    // Adds the selected item to the favorites, or removes it.
+    fn toggle_favorite(&self) -> EventResult {
+        let path = match self.selected_item() {
+            Some(item) if !album_artists::is_artist(item) => item.path.to_owned(),
+            _ => return EventResult::Consumed(None),
+        };
+
+        match favorites::toggle(&path) {
+            Ok(_) => EventResult::Consumed(None),
+            Err(e) => {
+                let msg = e.to_string();
+                EventResult::with_cb(move |siv| {
+                    ErrorView::load(siv, anyhow::Error::msg(msg.clone()))
+                })
             }
-        });
+        }
     }
 
     // Opens the current selected item in the preferred file manager.
@@ -321,6 +654,31 @@ impl FuzzyView {
             _ = utils::open_file_manager(path);
         }
     }
+
+    // Shows the tracks of the selected folder, without playing them.
+    fn show_tracks(&self) -> EventResult {
+        let path = match self.selected_item() {
+            Some(item) if !album_artists::is_artist(item) => item.path.to_owned(),
+            _ => return EventResult::Consumed(None),
+        };
+
+        EventResult::with_cb(move |siv| TracklistView::load(&path, siv))
+    }
+
+    // Opens a shell in the selected item.
+    fn open_shell(&self) -> EventResult {
+        // Album artists aren't directories.
+        let path = match self.selected_item() {
+            Some(item) if !album_artists::is_artist(item) => item.path.to_owned(),
+            _ => return EventResult::Consumed(None),
+        };
+
+        EventResult::with_cb(move |siv| {
+            if let Err(e) = terminal::open_shell(siv, path.to_owned()) {
+                ErrorView::load(siv, e)
+            }
+        })
+    }
 }
 
 impl View for FuzzyView {
@@ -336,8 +694,27 @@ impl View for FuzzyView {
         if h > 3 {
             // The first row of the list.
             let start_row = h - 3;
-            // The number of visible rows.
-            let visible = std::cmp::min(self.matches - self.offset_y, h - 2);
+            // The page count, shown on the top row.
+            let page = self.selected / start_row;
+            let pages = self.matches / start_row;
+            let digits = page.checked_ilog10().unwrap_or(0) as usize
+                + pages.checked_ilog10().unwrap_or(0) as usize
+                + 2;
+            // The start of the page count column.
+            let page_column = self.size.x.saturating_sub(digits + 2);
+            // The filter, shown before the page count if there is room.
+            let label = match (self.filter.label(), self.following) {
+                (Some(label), true) => Some(format!("{label}, following")),
+                (None, true) => Some(String::from("following")),
+                (label, false) => label,
+            };
+            let label = label
+                .map(|label| format!(" {label} "))
+                .filter(|label| label.width() + w / 2 < page_column);
+            // The start of the filter column, which is the end of the top row.
+            let label_column = page_column - label.as_ref().map_or(0, |l| l.width());
+            // The number of visible rows, which is zero if nothing matches the query.
+            let visible = std::cmp::min(self.matches.saturating_sub(self.offset_y), h - 2);
 
             for y in 0..visible {
                 let index = y + self.offset_y;
@@ -359,6 +736,32 @@ impl View for FuzzyView {
                     p.with_color(primary, |p| {
                         p.print((2, row), self.items[index].display.as_str())
                     });
+                    // The start of the extra columns and the end of the row, which
+                    // leaves room for the page count on the top row.
+                    let mut column = self.items[index].display.width() + 4;
+                    let mut end = if row == 0 { label_column } else { w - 1 };
+                    // Draw a star after favorite items.
+                    if column < end && favorites::is_favorite(&self.items[index].path) {
+                        p.with_color(theme::header2(), |p| p.print((column - 1, row), glyphs::get().star));
+                        column += 2;
+                    }
+                    // Draw the match score at the end of the row, for troubleshooting.
+                    if args::show_match_score() {
+                        let score = format!(" {}", self.items[index].weight);
+                        if column + score.len() < end {
+                            end -= score.len();
+                            p.with_color(theme::info(), |p| {
+                                p.with_effect(Effect::Italic, |p| p.print((end, row), &score))
+                            });
+                        }
+                    }
+                    // Draw the extra columns.
+                    let columns = self.columns(&self.items[index]);
+                    if !columns.is_empty() && column < end {
+                        p.cropped((end, h)).with_color(theme::info(), |p| {
+                            p.print((column, row), columns.as_str())
+                        });
+                    }
                     // Draw the fuzzy matched indices in a highlighting color.
                     for x in &self.items[index].indices {
                         let mut chars = self.items[index].display.chars();
@@ -374,15 +777,16 @@ impl View for FuzzyView {
                 }
             }
 
+            // Draw the filter.
+            if let Some(label) = label {
+                p.with_color(theme::info(), |p| {
+                    p.with_effect(Effect::Italic, |p| p.print((label_column, 0), &label))
+                });
+            }
+
             // Draw the page count.
             p.with_color(theme::prompt(), |p| {
-                let page = self.selected / start_row;
-                let pages = self.matches / start_row;
-                let digits = page.checked_ilog10().unwrap_or(0) as usize
-                    + pages.checked_ilog10().unwrap_or(0) as usize
-                    + 2;
-                let column = self.size.x - digits - 2;
-                p.print((column, 0), format!(" {}/{}", page, pages).as_str());
+                p.print((page_column, 0), format!(" {}/{}", page, pages).as_str());
             });
         }
 
@@ -393,15 +797,43 @@ impl View for FuzzyView {
             // Draw the match count and some borders.
             p.with_color(theme::progress(), |p| {
                 let lines = std::cmp::min(self.matches / 4, h / 4);
-                p.print_vline((w - 1, query_row - 1 - lines), lines, "│");
-                p.print_hline((2, query_row - 1), w - 3, "─");
+                p.print_vline((w - 1, query_row - 1 - lines), lines, glyphs::get().vline);
+                p.print_hline((2, query_row - 1), w.saturating_sub(3), glyphs::get().hline);
                 p.print((2, query_row - 1), &self.count());
             });
 
+            // Draw the number of directories that were skipped during the walk
+            // because they couldn't be read, at the end of the border.
+            if self.denied > 0 {
+                let plural = if self.denied == 1 { "dir" } else { "dirs" };
+                let notice = format!(" {} {} skipped: permission denied ", self.denied, plural);
+                let column = w.saturating_sub(notice.len() + 1);
+                if column > self.count().len() + 3 {
+                    p.with_color(theme::err(), |p| p.print((column, query_row - 1), &notice));
+                }
+            }
+
+            // Draw the prompt to confirm moving the selected item to the trash.
+            if self.is_trash_requested() && self.matches > 0 {
+                p.with_color(theme::err(), |p| {
+                    let name = &self.items[self.selected].display;
+                    let prompt = format!(" move '{}' to trash? <Enter> ", name);
+                    p.print((self.count().len() + 3, query_row - 1), prompt.as_str());
+                });
+            }
+
+            // The start of the visible query, which is scrolled horizontally
+            // to keep the cursor in view.
+            let start = query_start(&self.query, self.cursor, w.saturating_sub(2));
+            let column = if start > 0 { 3 } else { 2 };
+
             // Draw the text input area that shows the query.
             p.with_color(theme::hl(), |p| {
                 p.print_hline((0, query_row), w, " ");
-                p.print((2, query_row), &self.query);
+                if start > 0 {
+                    p.print((2, query_row), glyphs::get().ellipsis);
+                }
+                p.print((column, query_row), &self.query[start..]);
             });
 
             let c = if self.cursor == self.query.len() {
@@ -412,9 +844,9 @@ impl View for FuzzyView {
                     .next()
                     .expect("should find a char")
             };
-            let offset = self.query[..self.cursor].width();
+            let offset = self.query[start..self.cursor].width();
             p.with_effect(Effect::Reverse, |p| {
-                p.print((offset + 2, query_row), c);
+                p.print((offset + column, query_row), c);
             });
 
             // Draw the symbol to show the start of the text input area.
@@ -424,15 +856,46 @@ impl View for FuzzyView {
 
     // Keybindings for the fuzzy view.
     fn on_event(&mut self, event: Event) -> EventResult {
+        if event == Event::Refresh {
+            return self.auto_select();
+        }
+
+        // Any event other than `Enter` cancels a request to move to the trash.
+        let is_trash_confirmed = self.is_trash_requested();
+        self.trash_requested = None;
+
+        // Only editing the query starts the wait to select a single match.
+        let is_edit = matches!(
+            event,
+            Event::Char(_) | Event::Key(Key::Backspace) | Event::Key(Key::Del)
+        );
+        self.single_match_at = None;
+
+        // Typing or moving the selection stops it following the player.
+        let is_move = matches!(
+            event,
+            Event::Key(Key::Up | Key::Down | Key::PageUp | Key::PageDown)
+                | Event::CtrlChar('h' | 'l' | 'z')
+                | Event::AltChar(_)
+                | Event::Mouse { .. }
+        );
+        if is_edit || is_move {
+            self.following = false;
+        }
+
         match event {
             Event::Char(ch) => self.insert(ch),
-            Event::Key(Key::Enter) => return self.on_select(),
+            Event::Key(Key::Enter) if is_trash_confirmed => return self.trash(),
+            Event::Key(Key::Enter) => return self.on_select(false),
+            Event::CtrlChar('n') => return self.on_select(true),
+            Event::CtrlChar('d') => self.trash_requested = Some(Instant::now()),
             Event::Key(Key::Esc) => return on_cancel(),
             Event::Key(Key::Down) => self.move_down(),
             Event::Key(Key::Up) => self.move_up(),
             Event::Key(Key::PageUp) | Event::CtrlChar('h') => self.page_up(),
             Event::Key(Key::PageDown) | Event::CtrlChar('l') => self.page_down(),
             Event::CtrlChar('z') => self.random_page(),
+            Event::AltChar(ch) => self.jump_to_key(ch),
             Event::Key(Key::Backspace) => self.backspace(),
             Event::Key(Key::Del) => self.delete(),
             Event::Key(Key::Left) => self.move_left(),
@@ -442,38 +905,257 @@ impl View for FuzzyView {
             Event::CtrlChar('u') => self.clear(),
             Event::CtrlChar('p') => return self.parent(),
             Event::CtrlChar('o') => self.open_file_manager(),
+            Event::CtrlChar('e') => return self.open_shell(),
+            Event::CtrlChar('k') => return self.show_tracks(),
+            Event::CtrlChar('t') => return self.toggle_favorite(),
+            Event::CtrlChar('y') => self.toggle_follow(),
 
             Event::Mouse {
                 event, position, ..
             } => match event {
                 MouseEvent::Press(MouseButton::Right) => return on_cancel(),
                 MouseEvent::Press(MouseButton::Left) => return self.mouse_select(position),
+                MouseEvent::Hold(MouseButton::Left) => self.mouse_scroll(position),
+                MouseEvent::Release(MouseButton::Left) => self.scrolling = false,
                 MouseEvent::WheelDown => self.move_down(),
                 MouseEvent::WheelUp => self.move_up(),
                 _ => (),
             },
             _ => (),
         }
+
+        if is_edit {
+            self.single_match_at = self.single_match();
+        }
         EventResult::Consumed(None)
     }
 }
 
-pub fn fuzzy_finder(event: &Event, items: &Vec<FuzzyItem>) -> Option<EventResult> {
+// Sets the items used by the fuzzy-finder callbacks.
+pub fn set_library(items: Vec<FuzzyItem>) {
+    *LIBRARY.write().expect("should not be poisoned") = items;
+    album_artists::clear();
+}
+
+// Callback to load a fuzzy view of the items that match the trigger.
+pub fn fuzzy_finder(event: &Event) -> Option<EventResult> {
+    let items = LIBRARY.read().expect("should not be poisoned");
     let key = event.char();
-    let (items, key) = match key {
-        Some('A'..='Z') => (super::key_items(key, &items), key),
-        Some('a') => (super::non_leaf_items(&items), None),
-        Some('s') => (super::audio_items(&items), None),
+    let natural_sort = args::natural_sort();
+    let (items, filter) = match key {
+        Some(c @ 'A'..='Z') => (super::key_items(key, &items), Filter::Key(c)),
+        Some('a') if args::album_artist_view() => {
+            let items = items.to_owned();
+            return Some(EventResult::with_cb(move |siv| {
+                album_artists::load(items.to_owned(), siv)
+            }));
+        }
+        Some('a') => (super::non_leaf_items(&items, natural_sort), Filter::Artists),
+        Some('s') => (super::audio_items(&items, natural_sort), Filter::Albums),
+        Some('f') => (favorites::favorite_items(&items), Filter::Favorites),
+        Some('w') => (
+            super::recent_items(&items, super::RECENT_DAYS),
+            Filter::Recent,
+        ),
+        Some('b') => (play_counts::most_played_items(&items), Filter::MostPlayed),
         _ => match event.f_num() {
-            Some(depth) => (super::depth_items(depth, &items), None),
-            None => (items.to_owned(), None),
+            Some(depth) => (
+                super::depth_items(depth, &items, natural_sort),
+                Filter::Depth(depth),
+            ),
+            None => (items.to_owned(), Filter::None),
         },
     };
     Some(EventResult::with_cb(move |siv| {
-        FuzzyView::load(items.to_owned(), key, siv)
+        FuzzyView::load(items.to_owned(), filter.to_owned(), siv)
+    }))
+}
+
+// Trigger for the callback that switches between the fuzzy-finder and the player.
+pub fn toggle_trigger() -> EventTrigger {
+    EventTrigger::from(Event::Shift(Key::Tab))
+}
+
+// Callback to switch between the fuzzy-finder and the player, keeping the query
+// and selection of the fuzzy-finder.
+pub fn toggle_finder(_: &Event) -> Option<EventResult> {
+    Some(EventResult::with_cb(toggle))
+}
+
+// Returns to the player from the fuzzy-finder, or to the fuzzy-finder that was
+// last left from the player. The whole library is searched if there isn't one.
+fn toggle(siv: &mut Cursive) {
+    if current_path(siv).is_none() {
+        return;
+    }
+
+    if siv.call_on_name(NAME, |fuzzy: &mut FuzzyView| fuzzy.stash()).is_some() {
+        siv.pop_layer();
+        return;
+    }
+
+    let last = LAST_FINDER.read().expect("should not be poisoned").to_owned();
+    match last {
+        Some(fuzzy) => siv.add_layer(fuzzy.with_name(NAME).full_screen()),
+        None => {
+            let items = LIBRARY.read().expect("should not be poisoned").to_owned();
+            FuzzyView::load(items, Filter::None, siv)
+        }
+    }
+}
+
+// The index of the match at row `y` of the scrollbar, where the scrollbar spans
+// the rows of the list, `1..=available_y + 1`, as used by `mouse_select`. The
+// list is drawn upwards, so the bottom row is the first match and the top row
+// the last, with the rows between spread evenly over the matches.
+fn scroll_index(y: usize, available_y: usize, matches: usize) -> usize {
+    if matches == 0 || available_y == 0 {
+        return 0;
+    }
+
+    // The rows from the bottom of the list, in range 0..=available_y.
+    let rows_up = (available_y + 1).saturating_sub(y.clamp(1, available_y + 1));
+    (rows_up * (matches - 1) + available_y / 2) / available_y
+}
+
+// The byte index of `query` to draw from, so that the text up to and including
+// the cursor fits in `width` columns. When scrolled, the first column is used
+// to show that the start of the query is hidden.
+fn query_start(query: &str, cursor: usize, width: usize) -> usize {
+    // The cursor takes one column.
+    if query[..cursor].width() < width {
+        return 0;
+    }
+
+    query[..cursor]
+        .grapheme_indices(true)
+        .map(|(i, _)| i)
+        .find(|&i| query[i..cursor].width() + 2 <= width)
+        .unwrap_or(cursor)
+}
+
+// Callback to rescan the search root, replacing the items to fuzzy search on
+// when the scan finishes. Playback continues while scanning.
+pub fn reload(_: &Event) -> Option<EventResult> {
+    Some(EventResult::with_cb(|siv| {
+        if LoadingView::is_loading(siv) {
+            return;
+        }
+        LoadingView::load(siv, "reloading");
+
+        let cb_sink = siv.cb_sink().clone();
+
+        thread::spawn(move || {
+            let result = rescan(args::search_roots());
+
+            cb_sink
+                .send(Box::new(move |siv| {
+                    LoadingView::remove(siv);
+                    match result {
+                        Ok(items) => replace_library(siv, items),
+                        Err(e) => ErrorView::load(siv, e),
+                    }
+                }))
+                .unwrap_or_default();
+        });
     }))
 }
 
+// Scans the search `roots` for changes, updating the cache if the only root is
+// the default directory.
+fn rescan(roots: &[PathBuf]) -> Result<Vec<FuzzyItem>, anyhow::Error> {
+    let items = match roots {
+        [root] if persistent_data::uses_default(root) => persistent_data::update_cache(root)?,
+        _ => create_items_in(roots, &args::walk_opts())?,
+    };
+
+    if leaf_paths(&items).is_empty() {
+        let roots = roots
+            .iter()
+            .map(|root| format!("'{}'", root.display()))
+            .collect::<Vec<_>>();
+        bail!("no audio files detected in {}", roots.join(", "))
+    }
+
+    match args::audio_only() {
+        true => Ok(audio_items(&items, args::natural_sort())),
+        false => Ok(items),
+    }
+}
+
+// Replaces the items used by the fuzzy-finder callbacks, the session paths
+// and the items of the current fuzzy view, if any.
+fn replace_library(siv: &mut Cursive, items: Vec<FuzzyItem>) {
+    siv.with_user_data(|(_, paths, _): &mut InnerType<SessionData>| {
+        *paths = leaf_paths(&items);
+    });
+
+    siv.call_on_name(NAME, |fuzzy: &mut FuzzyView| {
+        fuzzy.set_items(items.to_owned())
+    });
+
+    set_library(items);
+}
+
+// Removes `path`, and any items below it, from the items used by the fuzzy-finder
+// callbacks, the session paths and the cache.
+fn remove_from_library(siv: &mut Cursive, path: &PathBuf) {
+    LIBRARY
+        .write()
+        .expect("should not be poisoned")
+        .retain(|e| !e.path.starts_with(path));
+    album_artists::clear();
+
+    siv.with_user_data(|(_, paths, _): &mut InnerType<SessionData>| {
+        paths.retain(|p| !p.starts_with(path));
+    });
+
+    let root = args::root_of(path);
+    if args::search_roots().len() == 1 && persistent_data::uses_default(&root) {
+        if let Err(e) = persistent_data::remove_cached(&root, path) {
+            ErrorView::load(siv, e);
+        }
+    }
+}
+
+// Creates the items for `path` using the search options from the command line.
+pub fn load_items(path: &PathBuf) -> Result<Vec<FuzzyItem>, anyhow::Error> {
+    let opts = WalkOpts {
+        root: args::root_of(path),
+        ..args::walk_opts()
+    };
+    let items = create_items(path, &opts)?;
+
+    match args::audio_only() {
+        true => Ok(audio_items(&items, args::natural_sort())),
+        false => Ok(items),
+    }
+}
+
+// Whether selecting `item` plays it, rather than searching its subdirectories.
+// Directories that contain audio are played if `prefers_audio`, even if they
+// have subdirectories.
+fn plays_on_select(item: &FuzzyItem, prefers_audio: bool) -> bool {
+    item.child_count == 0 || (item.has_audio && prefers_audio)
+}
+
+// Whether `item` is an album with its discs in subdirectories, which is played
+// when selected with `--merge-discs`.
+fn is_multi_disc(item: &FuzzyItem) -> bool {
+    args::merge_discs() && !item.has_audio && discs::dirs(&item.path).is_some()
+}
+
+// Only albums are listed when excluding directories without audio, so
+// selections are played rather than searched.
+fn prefers_audio() -> bool {
+    args::audio_only() || args::select_prefers_audio()
+}
+
+// Trigger for the reload callback.
+pub fn reload_trigger() -> EventTrigger {
+    EventTrigger::from(Event::CtrlChar('r'))
+}
+
 // Trigger for the fuzzy-finder callbacks.
 pub fn trigger() -> EventTrigger {
     EventTrigger::from_fn(|event| {
@@ -483,6 +1165,9 @@ pub fn trigger() -> EventTrigger {
                 | Event::Char('A'..='Z')
                 | Event::CtrlChar('a')
                 | Event::CtrlChar('s')
+                | Event::CtrlChar('f')
+                | Event::CtrlChar('w')
+                | Event::CtrlChar('b')
                 | Event::Key(Key::F1)
                 | Event::Key(Key::F2)
                 | Event::Key(Key::F3)
@@ -495,20 +1180,62 @@ pub fn trigger() -> EventTrigger {
     })
 }
 
+// What selecting a directory to play in the fuzzy-finder does.
+#[derive(Debug, PartialEq)]
+enum Selection {
+    // Load a player for the directory.
+    Load,
+    // Return to the player, which is already playing the directory.
+    Return,
+    // Return to the player and play the album from the first track.
+    Restart,
+}
+
+// Selecting the directory that is playing doesn't load it again, which would
+// interrupt playback, unless `restarts` is set.
+fn selection(selected: &PathBuf, current: Option<&PathBuf>, restarts: bool) -> Selection {
+    match (Some(selected) == current, restarts) {
+        (false, _) => Selection::Load,
+        (true, false) => Selection::Return,
+        (true, true) => Selection::Restart,
+    }
+}
+
 fn select_player(item: FuzzyItem, siv: &mut Cursive) {
-    let selected = Some(item.path);
+    if args::print_selection() {
+        return select_for_printing(item.path, siv);
+    }
+
     let current = current_path(siv);
 
-    match PlayerBuilder::FuzzyFinder.from(selected.to_owned(), siv) {
-        Ok(player) => {
-            // Don't reload the player if the selection hasn't changed.
-            if selected.eq(&current) {
-                siv.pop_layer();
-            } else {
-                PlayerView::load(player, siv);
-            }
+    match selection(&item.path, current.as_ref(), args::reselect_restarts()) {
+        Selection::Load => match PlayerBuilder::FuzzyFinder.from(Some(item.path), siv) {
+            Ok(player) => PlayerView::load(player, siv),
+            Err(e) => ErrorView::load(siv, e),
+        },
+        Selection::Return => {
+            siv.pop_layer();
+        }
+        Selection::Restart => {
+            siv.pop_layer();
+            siv.call_on_name(player_view::NAME, |v: &mut PlayerView| v.restart_album());
         }
-        Err(e) => ErrorView::load(siv, e),
+    }
+}
+
+// Stores the selected directory and quits, so that it is printed rather than
+// played.
+fn select_for_printing(path: PathBuf, siv: &mut Cursive) {
+    *SELECTION.write().expect("should not be poisoned") = Some(path);
+    siv.quit();
+}
+
+// Prints the directory selected with `--print-selection` to `out`. Fails if the
+// fuzzy-finder was closed without a selection.
+pub fn print_selection(out: &mut impl Write) -> Result<(), anyhow::Error> {
+    match SELECTION.write().expect("should not be poisoned").take() {
+        Some(path) => Ok(writeln!(out, "{}", path.display())?),
+        None => bail!(TapError::NoSelection(String::from("nothing was selected"))),
     }
 }
 
@@ -523,6 +1250,14 @@ fn on_cancel() -> EventResult {
     })
 }
 
+// Keeps the selection of the fuzzy-finder on the album that is playing, while
+// following it.
+pub fn follow(siv: &mut Cursive) {
+    if let Some(path) = current_path(siv) {
+        siv.call_on_name(NAME, |fuzzy: &mut FuzzyView| fuzzy.follow(&path));
+    }
+}
+
 // The path of the current player, if any.
 pub fn current_path(siv: &mut Cursive) -> Option<PathBuf> {
     match siv.user_data::<InnerType<SessionData>>() {
@@ -542,3 +1277,436 @@ fn remove_layer(siv: &mut Cursive) {
         siv.screen_mut().remove_layer(LayerPosition::FromFront(1));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fuzzy::WalkOpts;
+    use crate::utils::create_working_dir;
+    use std::collections::VecDeque;
+
+    use cursive::{backends::puppet, theme::Theme, views::TextView};
+
+    use crate::player::PlayerOpts;
+    use crate::utils::IntoInner;
+
+    #[test]
+    fn test_query_survives_toggle_to_player() {
+        let root = create_working_dir(
+            &["one", "two", "three"],
+            &[
+                ("one/a.mp3", "test_mp3_audio.mp3"),
+                ("two/b.mp3", "test_mp3_audio.mp3"),
+                ("three/c.mp3", "test_mp3_audio.mp3"),
+            ],
+            &[],
+        )
+        .expect("create temp dir")
+        .into_path();
+
+        let items = create_items(&root, &WalkOpts::default()).expect("should create items");
+        let queue = VecDeque::from(vec![(root.join("one"), 1), (root.join("two"), 1)]);
+
+        // A player is playing "two", under the fuzzy-finder.
+        let mut siv = Cursive::new();
+        siv.set_user_data((PlayerOpts::default().into_inner(), vec![], queue));
+        siv.add_layer(TextView::new("player"));
+        FuzzyView::load(items, Filter::None, &mut siv);
+
+        let state = |siv: &mut Cursive| {
+            siv.call_on_name(NAME, |fuzzy: &mut FuzzyView| {
+                let selected = &fuzzy.items[fuzzy.selected];
+                (fuzzy.query.to_owned(), selected.display.to_owned())
+            })
+        };
+
+        siv.call_on_name(NAME, |fuzzy: &mut FuzzyView| {
+            fuzzy.insert('t');
+            fuzzy.move_up();
+        });
+        let before = state(&mut siv).expect("should show the fuzzy-finder");
+        assert_eq!(before.0, "t");
+
+        toggle(&mut siv);
+        assert_eq!(siv.screen().len(), 1);
+        assert!(state(&mut siv).is_none());
+
+        toggle(&mut siv);
+        assert_eq!(siv.screen().len(), 2);
+        assert_eq!(state(&mut siv), Some(before));
+    }
+
+    #[test]
+    fn test_follow_selects_playing_album() {
+        let root = create_working_dir(
+            &["one", "two", "three"],
+            &[
+                ("one/a.mp3", "test_mp3_audio.mp3"),
+                ("two/b.mp3", "test_mp3_audio.mp3"),
+                ("three/c.mp3", "test_mp3_audio.mp3"),
+            ],
+            &[],
+        )
+        .expect("create temp dir")
+        .into_path();
+
+        let items = create_items(&root, &WalkOpts::default()).expect("should create items");
+        let play = |siv: &mut Cursive, name: &str| {
+            let queue = VecDeque::from(vec![(root.join("one"), 0), (root.join(name), 0)]);
+            siv.set_user_data((PlayerOpts::default().into_inner(), vec![], queue));
+            follow(siv);
+        };
+        let selected = |siv: &mut Cursive| {
+            siv.call_on_name(NAME, |fuzzy: &mut FuzzyView| {
+                fuzzy.items[fuzzy.selected].display.to_owned()
+            })
+            .expect("should show the fuzzy-finder")
+        };
+
+        let mut siv = Cursive::new();
+        siv.add_layer(TextView::new("player"));
+        FuzzyView::load(items, Filter::None, &mut siv);
+        let first = selected(&mut siv);
+
+        // The selection stays put until following is toggled on.
+        play(&mut siv, "two");
+        assert_eq!(selected(&mut siv), first);
+
+        siv.call_on_name(NAME, |fuzzy: &mut FuzzyView| {
+            fuzzy.on_event(Event::CtrlChar('y'));
+        });
+        play(&mut siv, "two");
+        assert_eq!(selected(&mut siv), "two");
+        play(&mut siv, "three");
+        assert_eq!(selected(&mut siv), "three");
+
+        // Moving the selection stops following.
+        siv.call_on_name(NAME, |fuzzy: &mut FuzzyView| {
+            fuzzy.on_event(Event::Key(Key::Up));
+        });
+        let moved = selected(&mut siv);
+        let other = ["one", "two", "three"]
+            .into_iter()
+            .find(|name| *name != moved)
+            .unwrap();
+        play(&mut siv, other);
+        assert_eq!(selected(&mut siv), moved);
+    }
+
+    #[test]
+    fn test_parent_selects_child() {
+        let root = create_working_dir(
+            &["one/album", "two/album", "two/other"],
+            &[
+                ("one/album/a.mp3", "test_mp3_audio.mp3"),
+                ("two/album/b.mp3", "test_mp3_audio.mp3"),
+                ("two/other/c.mp3", "test_mp3_audio.mp3"),
+            ],
+            &[],
+        )
+        .expect("create temp dir")
+        .into_path();
+
+        let items = create_items(&root, &WalkOpts::default()).expect("should create items");
+        let mut fuzzy = FuzzyView::new(items);
+        let selected = |fuzzy: &FuzzyView| fuzzy.items[fuzzy.selected].path.to_owned();
+
+        fuzzy.select_path(&root.join("two/album"));
+        assert_eq!(selected(&fuzzy), root.join("two/album"));
+
+        // Without an item for the path, the item that contains it is selected.
+        fuzzy.select_path(&root.join("two/album/cd1"));
+        assert_eq!(selected(&fuzzy), root.join("two/album"));
+
+        let other = root.join("two/other");
+        fuzzy.items.retain(|item| item.path != other);
+        fuzzy.select_path(&root.join("two/other"));
+        assert_eq!(selected(&fuzzy), root.join("two"));
+    }
+
+    #[test]
+    fn test_filter_label() {
+        let labels = [
+            (Filter::None, None),
+            (Filter::Artists, Some("artists")),
+            (Filter::Albums, Some("albums")),
+            (Filter::Favorites, Some("favorites")),
+            (Filter::Recent, Some("recently added")),
+            (Filter::MostPlayed, Some("most played")),
+            (Filter::Depth(2), Some("depth 2")),
+            (Filter::Key('B'), Some("starting with B")),
+            (Filter::AlbumArtist("Nina".into()), Some("by Nina")),
+            (Filter::Dir(PathBuf::from("/music/jazz")), Some("in jazz")),
+        ];
+
+        for (filter, label) in labels {
+            assert_eq!(filter.label().as_deref(), label, "{filter:?}");
+        }
+    }
+
+    #[test]
+    fn test_print_selection() {
+        let root = create_working_dir(
+            &["one", "two"],
+            &[
+                ("one/a.mp3", "test_mp3_audio.mp3"),
+                ("two/b.mp3", "test_mp3_audio.mp3"),
+            ],
+            &[],
+        )
+        .expect("create temp dir")
+        .into_path();
+
+        let items = create_items(&root, &WalkOpts::default()).expect("should create items");
+        let mut fuzzy = FuzzyView::new(items);
+        for ch in "two".chars() {
+            fuzzy.insert(ch);
+        }
+        assert_eq!(fuzzy.matches, 1);
+
+        let mut siv = Cursive::new();
+        let item = fuzzy.items[fuzzy.selected].to_owned();
+        select_for_printing(item.path, &mut siv);
+        assert!(!siv.is_running());
+
+        let mut out = vec![];
+        print_selection(&mut out).expect("should print the selection");
+        let expected = format!("{}\n", root.join("two").display());
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+
+        // Nothing is printed once the selection is taken, as when cancelled.
+        let mut out = vec![];
+        let err = print_selection(&mut out).unwrap_err();
+        assert_eq!(crate::error::exit_code(&err), 6);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_selection_of_playing_dir() {
+        let playing = PathBuf::from("/music/Kind of Blue");
+        let other = PathBuf::from("/music/Blue Train");
+
+        assert_eq!(selection(&other, Some(&playing), false), Selection::Load);
+        assert_eq!(selection(&other, Some(&playing), true), Selection::Load);
+        assert_eq!(selection(&other, None, true), Selection::Load);
+        assert_eq!(selection(&playing, Some(&playing), false), Selection::Return);
+        assert_eq!(selection(&playing, Some(&playing), true), Selection::Restart);
+    }
+
+    #[test]
+    fn test_scroll_index() {
+        // The bottom and top rows of the list are the first and last matches.
+        assert_eq!(scroll_index(11, 10, 500), 0);
+        assert_eq!(scroll_index(1, 10, 500), 499);
+        // The middle row is the middle match.
+        assert_eq!(scroll_index(6, 10, 501), 250);
+        // Rows outside the list are clamped.
+        assert_eq!(scroll_index(0, 10, 500), 499);
+        assert_eq!(scroll_index(20, 10, 500), 0);
+        // Fewer matches than rows.
+        assert_eq!(scroll_index(1, 10, 3), 2);
+        assert_eq!(scroll_index(5, 10, 3), 1);
+        assert_eq!(scroll_index(1, 10, 0), 0);
+    }
+
+    #[test]
+    fn test_no_matches_then_backspace_does_not_panic() {
+        let root = create_working_dir(
+            &["one", "two"],
+            &[
+                ("one/a.mp3", "test_mp3_audio.mp3"),
+                ("two/b.mp3", "test_mp3_audio.mp3"),
+            ],
+            &[],
+        )
+        .expect("create temp dir")
+        .into_path();
+
+        let items = create_items(&root, &WalkOpts::default()).expect("should create items");
+        let mut fuzzy = FuzzyView::new(items);
+
+        let size = XY { x: 40, y: 10 };
+        let backend = puppet::Backend::init(Some(size));
+        let theme = Theme::default();
+        fuzzy.layout(size);
+
+        for ch in "zzz".chars() {
+            fuzzy.insert(ch);
+        }
+        assert_eq!(fuzzy.matches, 0);
+
+        fuzzy.move_up();
+        fuzzy.page_up();
+        fuzzy.draw(&Printer::new(size, &theme, &*backend));
+
+        for _ in 0..3 {
+            fuzzy.backspace();
+            fuzzy.draw(&Printer::new(size, &theme, &*backend));
+        }
+        assert_eq!(fuzzy.matches, 2);
+    }
+
+    #[test]
+    fn test_typed_query_matches_full_match() {
+        let root = create_working_dir(
+            &["alpha", "beta", "bravo", "cobra", "zebra"],
+            &[
+                ("alpha/a.mp3", "test_mp3_audio.mp3"),
+                ("beta/b.mp3", "test_mp3_audio.mp3"),
+                ("bravo/c.mp3", "test_mp3_audio.mp3"),
+                ("cobra/d.mp3", "test_mp3_audio.mp3"),
+                ("zebra/e.mp3", "test_mp3_audio.mp3"),
+            ],
+            &[],
+        )
+        .expect("create temp dir")
+        .into_path();
+
+        let items = create_items(&root, &WalkOpts::default()).expect("should create items");
+        let names = |fuzzy: &FuzzyView| {
+            fuzzy.items[..fuzzy.matches]
+                .iter()
+                .map(|e| e.display.to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        // Typing narrows the previous matches, which gives the same results as
+        // matching every item.
+        let mut typed = FuzzyView::new(items.to_owned());
+        for ch in "bra".chars() {
+            typed.insert(ch);
+        }
+
+        let mut full = FuzzyView::new(items);
+        full.query = String::from("bra");
+        full.update_list("bra");
+
+        assert_eq!(typed.matches, 3);
+        assert_eq!(names(&typed), names(&full));
+
+        // Removing characters matches every item again.
+        typed.backspace();
+        typed.backspace();
+        assert_eq!(typed.matches, 4);
+    }
+
+    #[test]
+    fn test_jump_to_key_among_matches() {
+        let root = create_working_dir(
+            &["alpha", "beta", "bravo", "charlie"],
+            &[
+                ("alpha/a.mp3", "test_mp3_audio.mp3"),
+                ("beta/b.mp3", "test_mp3_audio.mp3"),
+                ("bravo/c.mp3", "test_mp3_audio.mp3"),
+                ("charlie/d.mp3", "test_mp3_audio.mp3"),
+            ],
+            &[],
+        )
+        .expect("create temp dir")
+        .into_path();
+
+        let items = create_items(&root, &WalkOpts::default()).expect("should create items");
+        let mut fuzzy = FuzzyView::new(items);
+        fuzzy.layout(XY { x: 40, y: 4 });
+
+        // Jumps between the items starting with 'b', wrapping around.
+        fuzzy.jump_to_key('b');
+        let first = fuzzy.selected;
+        assert_eq!(fuzzy.items[first].key, 'B');
+        fuzzy.jump_to_key('b');
+        let second = fuzzy.selected;
+        assert_eq!(fuzzy.items[second].key, 'B');
+        assert_ne!(first, second);
+        fuzzy.jump_to_key('B');
+        assert_eq!(fuzzy.selected, first);
+        assert!(fuzzy.query.is_empty());
+
+        // Only the matched items are jumped to.
+        for ch in "av".chars() {
+            fuzzy.insert(ch);
+        }
+        assert_eq!(fuzzy.matches, 1);
+        fuzzy.jump_to_key('c');
+        assert_eq!(fuzzy.selected, 0);
+        fuzzy.jump_to_key('b');
+        assert_eq!(fuzzy.items[fuzzy.selected].display, "bravo");
+        assert_eq!(fuzzy.query, "av");
+    }
+
+    #[test]
+    fn test_select_album_with_subdir() {
+        let root = create_working_dir(
+            &["album/scans", "album/bonus"],
+            &[
+                ("album/a.mp3", "test_mp3_audio.mp3"),
+                ("album/bonus/b.mp3", "test_mp3_audio.mp3"),
+            ],
+            &[],
+        )
+        .expect("create temp dir")
+        .into_path();
+
+        let items = create_items(&root, &WalkOpts::default()).expect("should create items");
+        let album = items
+            .iter()
+            .find(|e| e.display == "album")
+            .expect("should find the album");
+        assert!(album.has_audio && album.child_count > 0);
+
+        // By default the subdirectories are searched.
+        assert!(!plays_on_select(album, false));
+        // With `--select-prefers-audio` the album is played.
+        assert!(plays_on_select(album, true));
+
+        let bonus = items.iter().find(|e| e.display == "bonus").unwrap();
+        assert!(plays_on_select(bonus, false));
+    }
+
+    #[test]
+    fn test_long_query_scrolls_to_cursor() {
+        let query = "a very long query for a narrow terminal";
+        let width = 12;
+
+        // The query is drawn from the start when the cursor is in view.
+        assert_eq!(query_start(query, 5, width), 0);
+
+        // Otherwise the text before the cursor, the cursor and the leading
+        // ellipsis fit the width exactly.
+        let start = query_start(query, query.len(), width);
+        assert!(start > 0);
+        assert_eq!(query[start..].width() + 2, width);
+
+        // Degenerate widths don't panic.
+        assert_eq!(query_start(query, query.len(), 0), query.len());
+        assert_eq!(query_start("", 0, 0), 0);
+    }
+
+    #[test]
+    fn test_trash_removes_selected_dir() {
+        let temp_dir = create_working_dir(
+            &["one", "two"],
+            &[
+                ("one/a.mp3", "test_mp3_audio.mp3"),
+                ("two/b.mp3", "test_mp3_audio.mp3"),
+            ],
+            &[],
+        )
+        .expect("create temp dir");
+        let root = temp_dir.path().to_path_buf();
+
+        let items = create_items(&root, &WalkOpts::default()).expect("should create items");
+        let mut fuzzy = FuzzyView::new(items);
+        let path = fuzzy.items[fuzzy.selected].path.to_owned();
+
+        assert!(!fuzzy.is_trash_requested());
+        fuzzy.trash_requested = Some(Instant::now());
+        assert!(fuzzy.is_trash_requested());
+
+        // The directory is removed in place of moving it to the system trash.
+        _ = fuzzy.trash_with(|path| Ok(std::fs::remove_dir_all(path).expect("remove dir")));
+
+        assert!(!path.exists());
+        assert!(fuzzy.items.iter().all(|e| e.path != path));
+        assert_eq!(fuzzy.matches, 1);
+    }
+}
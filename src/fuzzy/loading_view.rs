@@ -0,0 +1,62 @@
+use std::time::Instant;
+
+use cursive::{
+    view::{Nameable, Resizable},
+    Cursive, Printer, View,
+};
+
+use crate::config::theme;
+
+// The name used to find the loading view in the view stack.
+const NAME: &str = "loading";
+
+// Shows a message with a spinner while a task runs in the background.
+pub struct LoadingView {
+    // The message to show.
+    msg: &'static str,
+    // The time the view was loaded, used to animate the spinner.
+    start_time: Instant,
+}
+
+impl LoadingView {
+    fn new(msg: &'static str) -> Self {
+        LoadingView {
+            msg,
+            start_time: Instant::now(),
+        }
+    }
+
+    // Adds the loading view on top of the current layers.
+    pub fn load(siv: &mut Cursive, msg: &'static str) {
+        siv.screen_mut()
+            .add_transparent_layer(LoadingView::new(msg).with_name(NAME).full_screen());
+    }
+
+    // Removes the loading view, if it is still in the view stack.
+    pub fn remove(siv: &mut Cursive) {
+        if let Some(position) = siv.screen_mut().find_layer_from_name(NAME) {
+            siv.screen_mut().remove_layer(position);
+        }
+    }
+
+    // Whether or not a loading view is currently shown.
+    pub fn is_loading(siv: &mut Cursive) -> bool {
+        siv.screen_mut().find_layer_from_name(NAME).is_some()
+    }
+}
+
+impl View for LoadingView {
+    fn draw(&self, p: &Printer) {
+        let ellipses = ["   ", ".  ", ".. ", "..."];
+        let frame = (self.start_time.elapsed().as_millis() / 300) as usize % ellipses.len();
+
+        if p.size.y > 1 {
+            p.with_color(theme::prompt(), |p| {
+                p.print(
+                    (0, p.size.y - 2),
+                    format!(" [tap]: {}{} ", self.msg, ellipses[frame]).as_str(),
+                )
+            });
+        }
+    }
+}
@@ -1,9 +1,24 @@
+#[cfg(feature = "tui")]
+pub mod album_artists;
+#[cfg(feature = "tui")]
 pub mod error_view;
 pub mod fuzzy;
+#[cfg(feature = "tui")]
 pub mod fuzzy_view;
+#[cfg(feature = "tui")]
+pub mod loading_view;
+#[cfg(feature = "tui")]
+pub mod tracklist_view;
 
+pub use self::fuzzy::*;
+
+#[cfg(feature = "tui")]
 pub use self::{
     error_view::ErrorView,
-    fuzzy::*,
-    fuzzy_view::{fuzzy_finder, trigger, FuzzyView},
+    fuzzy_view::{
+        current_path, follow, fuzzy_finder, load_items, print_selection, reload, reload_trigger,
+        set_library, toggle_finder, toggle_trigger, trigger, Filter, FuzzyView,
+    },
+    loading_view::LoadingView,
+    tracklist_view::TracklistView,
 };
@@ -0,0 +1,105 @@
+use std::path::PathBuf;
+
+use cursive::{
+    event::{Event, Key},
+    view::Resizable,
+    views::{Dialog, ListView, OnEventView, ScrollView, TextView},
+    Cursive,
+};
+
+use crate::player::{player::playlist, player_view::track_length};
+
+pub struct TracklistView {}
+
+impl TracklistView {
+    pub fn new(path: &PathBuf) -> Dialog {
+        let title = path.file_name().unwrap_or_default().to_string_lossy();
+
+        let dialog = match rows(path) {
+            Ok(rows) => {
+                let list = rows
+                    .into_iter()
+                    .fold(ListView::new(), |list, (label, length)| {
+                        list.child(&label, TextView::new(length))
+                    });
+                Dialog::around(ScrollView::new(list).show_scrollbars(true))
+            }
+            Err(e) => Dialog::around(TextView::new(e.to_string())),
+        };
+
+        dialog.title(title)
+    }
+
+    // Loads the tracks of the album at `path` over the fuzzy-finder, without
+    // playing them. Closed with Esc.
+    pub fn load(path: &PathBuf, siv: &mut Cursive) {
+        siv.add_layer(
+            OnEventView::new(TracklistView::new(path).max_height(siv.screen_size().y))
+                .on_event(Event::Key(Key::Esc), |siv| {
+                    siv.pop_layer();
+                })
+                .on_event(Event::CtrlChar('k'), |siv| {
+                    siv.pop_layer();
+                }),
+        )
+    }
+}
+
+// The number and title of each track of the album at `path`, with its length,
+// followed by the length of the album.
+fn rows(path: &PathBuf) -> Result<Vec<(String, String)>, anyhow::Error> {
+    let (list, _) = playlist(path)?;
+    let total = list.iter().map(|f| f.duration).sum();
+
+    let mut rows = list
+        .iter()
+        .enumerate()
+        .map(|(i, f)| {
+            (
+                format!("{:>2}. {}", i + 1, f.title),
+                track_length(f.duration),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    rows.push((String::from("total"), track_length(total)));
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::create_working_dir;
+
+    #[test]
+    fn test_rows_match_the_tracks() {
+        let root = create_working_dir(
+            &["album"],
+            &[
+                ("album/a.mp3", "test_mp3_audio.mp3"),
+                ("album/b.ogg", "test_ogg_audio.ogg"),
+            ],
+            &[],
+        )
+        .expect("create temp dir")
+        .into_path();
+
+        let album = root.join("album");
+        let (list, _) = playlist(&album).unwrap();
+        let listed = rows(&album).expect("should list the tracks");
+
+        assert_eq!(listed.len(), list.len() + 1);
+        for (i, file) in list.iter().enumerate() {
+            assert_eq!(listed[i].0, format!("{:>2}. {}", i + 1, file.title));
+            assert_eq!(listed[i].1, track_length(file.duration));
+        }
+        let total = list.iter().map(|f| f.duration).sum();
+        assert_eq!(
+            listed[list.len()],
+            (String::from("total"), track_length(total))
+        );
+
+        // A folder without audio can't be listed.
+        assert!(rows(&root).is_err());
+    }
+}
@@ -0,0 +1,254 @@
+use std::{
+    io::Write,
+    os::unix::{fs::FileTypeExt, net::UnixListener},
+    path::PathBuf,
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use anyhow::bail;
+use cursive::{reexports::crossbeam_channel::Sender, Cursive};
+
+use crate::fuzzy::{fuzzy_view, FuzzyView};
+use crate::player::{player_view, PlayerStatus, PlayerView};
+
+// The version of the JSON schema. Fields may be added without changing the
+// version; it only changes when existing fields change or are removed.
+const VERSION: u32 = 1;
+
+// How long a client waits for the state before the connection is closed.
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+// The state of the fuzzy-finder.
+#[derive(Debug)]
+struct FinderState {
+    selected: Option<String>,
+    matches: usize,
+    total: usize,
+}
+
+// The state of the player.
+#[derive(Debug)]
+struct PlayerState {
+    status: &'static str,
+    artist: String,
+    album: String,
+    title: String,
+    cover: Option<String>,
+    track: u32,
+    elapsed: u64,
+    duration: usize,
+}
+
+// The socket that tap listens on, which is removed when dropped.
+pub struct Socket(PathBuf);
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        _ = std::fs::remove_file(&self.0);
+    }
+}
+
+// Listens on the Unix socket at `path` and writes the current state of tap as
+// a single line of JSON to each client that connects, for example:
+//
+// {"version":1,"mode":"finder","finder":{"selected":"Blue Train","matches":3,"total":412},"player":null}
+//
+// `mode` is "finder" when the fuzzy-finder is shown and "player" otherwise.
+// `finder` and `player` are `null` when not loaded. The socket is removed once
+// the returned `Socket` is dropped.
+pub fn spawn(
+    path: PathBuf,
+    cb: Sender<Box<dyn FnOnce(&mut Cursive) + Send>>,
+) -> Result<Socket, anyhow::Error> {
+    // Remove the socket left behind by a previous run, but never anything else.
+    if let Ok(metadata) = path.symlink_metadata() {
+        if !metadata.file_type().is_socket() {
+            bail!("'{}' already exists and isn't a socket", path.display())
+        }
+        std::fs::remove_file(&path)?;
+    }
+
+    let listener = match UnixListener::bind(&path) {
+        Ok(listener) => listener,
+        Err(e) => bail!("could not bind '{}'\n- `{}`", path.display(), e),
+    };
+
+    thread::spawn(move || {
+        for mut stream in listener.incoming().filter_map(Result::ok) {
+            let (tx, rx) = mpsc::channel();
+
+            let sent = cb.send(Box::new(move |siv| {
+                _ = tx.send(to_json(finder_state(siv), player_state(siv)));
+            }));
+
+            // The event loop has stopped, so tap is quitting.
+            if sent.is_err() {
+                break;
+            }
+
+            if let Ok(json) = rx.recv_timeout(TIMEOUT) {
+                _ = writeln!(stream, "{}", json);
+            }
+        }
+    });
+
+    Ok(Socket(path))
+}
+
+fn finder_state(siv: &mut Cursive) -> Option<FinderState> {
+    siv.call_on_name(fuzzy_view::NAME, |v: &mut FuzzyView| {
+        let (matches, total) = v.counts();
+        FinderState {
+            selected: v.selected_item().map(|item| item.display.to_owned()),
+            matches,
+            total,
+        }
+    })
+}
+
+fn player_state(siv: &mut Cursive) -> Option<PlayerState> {
+    siv.call_on_name(player_view::NAME, |v: &mut PlayerView| {
+        let player = v.player();
+        let file = player.file();
+        PlayerState {
+            status: match player.status {
+                PlayerStatus::Playing => "playing",
+                PlayerStatus::Paused => "paused",
+                PlayerStatus::Stopped => "stopped",
+            },
+            artist: file.artist.to_owned(),
+            album: file.album.to_owned(),
+            title: file.title.to_owned(),
+            cover: file.cover().map(|p| p.to_string_lossy().to_string()),
+            track: player.track_number(player.index),
+            elapsed: player.elapsed().as_secs(),
+            duration: file.duration,
+        }
+    })
+}
+
+fn to_json(finder: Option<FinderState>, player: Option<PlayerState>) -> String {
+    let mode = match finder {
+        Some(_) => "finder",
+        None => "player",
+    };
+
+    let finder = match finder {
+        Some(f) => format!(
+            "{{\"selected\":{},\"matches\":{},\"total\":{}}}",
+            f.selected
+                .as_deref()
+                .map_or(String::from("null"), json_string),
+            f.matches,
+            f.total,
+        ),
+        None => String::from("null"),
+    };
+
+    let player = match player {
+        Some(p) => format!(
+            "{{\"status\":\"{}\",\"artist\":{},\"album\":{},\"title\":{},\
+            \"cover\":{},\"track\":{},\"elapsed\":{},\"duration\":{}}}",
+            p.status,
+            json_string(&p.artist),
+            json_string(&p.album),
+            json_string(&p.title),
+            p.cover.as_deref().map_or(String::from("null"), json_string),
+            p.track,
+            p.elapsed,
+            p.duration,
+        ),
+        None => String::from("null"),
+    };
+
+    format!(
+        "{{\"version\":{},\"mode\":\"{}\",\"finder\":{},\"player\":{}}}",
+        VERSION, mode, finder, player
+    )
+}
+
+// Quotes and escapes `s` as a JSON string.
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_string_escapes() {
+        assert_eq!(json_string("Blue Train"), "\"Blue Train\"");
+        assert_eq!(json_string("a \"b\" \\ c\n"), "\"a \\\"b\\\" \\\\ c\\n\"");
+        assert_eq!(json_string("\u{1}"), "\"\\u0001\"");
+    }
+
+    #[test]
+    fn test_to_json() {
+        let finder = FinderState {
+            selected: Some(String::from("Blue Train")),
+            matches: 3,
+            total: 412,
+        };
+        assert_eq!(
+            to_json(Some(finder), None),
+            "{\"version\":1,\"mode\":\"finder\",\
+            \"finder\":{\"selected\":\"Blue Train\",\"matches\":3,\"total\":412},\
+            \"player\":null}"
+        );
+
+        let player = PlayerState {
+            status: "paused",
+            artist: String::from("John Coltrane"),
+            album: String::from("Blue Train"),
+            title: String::from("Moment's Notice"),
+            cover: Some(String::from("/music/Blue Train/cover.jpg")),
+            track: 2,
+            elapsed: 61,
+            duration: 551,
+        };
+        assert_eq!(
+            to_json(None, Some(player)),
+            "{\"version\":1,\"mode\":\"player\",\"finder\":null,\
+            \"player\":{\"status\":\"paused\",\"artist\":\"John Coltrane\",\
+            \"album\":\"Blue Train\",\"title\":\"Moment's Notice\",\
+            \"cover\":\"/music/Blue Train/cover.jpg\",\"track\":2,\"elapsed\":61,\"duration\":551}}"
+        );
+    }
+
+    #[test]
+    fn test_spawn_only_replaces_a_socket() {
+        let temp_dir = tempfile::tempdir().expect("temp dir");
+        let path = temp_dir.path().join("tap.sock");
+        let (cb, _rx) = cursive::reexports::crossbeam_channel::unbounded();
+
+        std::fs::write(&path, "notes").expect("write file");
+        assert!(spawn(path.to_owned(), cb.clone()).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "notes");
+
+        std::fs::remove_file(&path).unwrap();
+        let stale = UnixListener::bind(&path).expect("bind");
+        drop(stale);
+
+        let socket = spawn(path.to_owned(), cb).expect("spawn");
+        assert!(path.exists());
+        drop(socket);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,40 @@
+//! tap is an audio player for the terminal with fuzzy-finder.
+//!
+//! Besides the `tap` binary, the crate exposes the parts of tap that don't
+//! depend on the TUI, for use in other frontends. These don't read tap's
+//! command line arguments, and are all that is built without the default `tui`
+//! feature:
+//!
+//! - [`walk`] and [`create_items`] search a directory tree for the
+//!   directories that contain audio, or lead to directories that do.
+//! - [`audio_files`] reads the tagged [`AudioFile`]s in a directory, in
+//!   playlist order, with the given [`AudioOpts`].
+//!
+//! ```no_run
+//! use std::path::PathBuf;
+//!
+//! let root = PathBuf::from("/path/to/music");
+//! let opts = tap::WalkOpts::default();
+//!
+//! for item in tap::walk(&root, &opts).filter(|item| item.has_audio) {
+//!     let files = tap::audio_files(&item.path, &tap::AudioOpts::default())?;
+//!     println!("{}: {} tracks", item.display, files.len());
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+#[cfg(feature = "tui")]
+pub mod config;
+#[cfg(feature = "tui")]
+pub mod data;
+#[cfg(feature = "tui")]
+pub mod doctor;
+pub mod error;
+pub mod fuzzy;
+#[cfg(all(unix, feature = "tui"))]
+pub mod ipc;
+pub mod player;
+pub mod utils;
+
+pub use fuzzy::{create_items, walk, FuzzyItem, WalkOpts};
+pub use player::{audio_files, valid_audio_ext, AudioFile, AudioOpts};
@@ -1,28 +1,33 @@
-mod config;
-mod data;
-mod fuzzy;
-mod player;
-mod utils;
-
 use std::path::PathBuf;
 
-use cursive::{event::Event, CursiveRunnable};
+use anyhow::bail;
+use cursive::{
+    event::{Event, Key},
+    CursiveRunnable,
+};
 
-use config::{
-    args::{self, Opts},
+use tap::config::{
+    args::{self, Autoplay, Opts},
     theme,
 };
-use data::{persistent_data, session_data, SessionData};
-use fuzzy::{FuzzyItem, FuzzyView};
-use player::{PlayerBuilder, PlayerView};
-use utils::IntoInner;
+use tap::data::{gain_data, persistent_data, SessionData};
+use tap::error::TapError;
+use tap::fuzzy::{self, ErrorView, Filter, FuzzyItem, FuzzyView};
+use tap::player::{self, terminal, PlayerBuilder, PlayerView};
+use tap::utils::{self, IntoInner};
 
 fn main() {
     let result = setup_and_run();
+    // The players have been dropped, so the recording is complete.
+    player::record::finish();
+    player::now_playing::clear();
 
     match result {
         Ok(()) => (),
-        Err(err) => eprintln!("[tap error]: {err}"),
+        Err(err) => {
+            eprintln!("[tap error]: {err}");
+            std::process::exit(tap::error::exit_code(&err));
+        }
     }
 }
 
@@ -30,44 +35,138 @@ fn main() {
 fn setup_and_run() -> Result<(), anyhow::Error> {
     let (path, opts) = args::parse()?;
 
+    // Network streams are played directly, without the fuzzy-finder.
+    let is_url = player::is_url(&path);
+
     match opts {
+        Opts::Automate if is_url => return player::run_automated(path),
         Opts::Automate => {
-            let path = fuzzy::first_audio_path(&path)?;
+            let path = fuzzy::first_audio_path(&path, &args::walk_opts())?;
             return player::run_automated(path);
         }
+        Opts::Analyze => return gain_data::analyze(path),
+        Opts::Doctor => return tap::doctor::run(path),
         Opts::Set => return persistent_data::set_default_path(path),
         Opts::Print => return persistent_data::print_default_path(),
+        Opts::Dump => {
+            print!("{}", args::dump_config());
+            return Ok(());
+        }
         _ => (),
     }
 
     // The items to fuzzy search on.
-    let items = get_items(&path, opts)?;
+    let items = match is_url {
+        true => vec![],
+        false => get_items(&path, opts)?,
+    };
+
+    // With `--print-selection` there is nothing to choose between if there is
+    // only one audio item.
+    if args::print_selection() {
+        let only = match is_url {
+            true => Some(path.to_owned()),
+            false => fuzzy::only_audio_path(&path, &items),
+        };
+        if let Some(path) = only {
+            println!("{}", path.display());
+            return Ok(());
+        }
+    }
+
+    // Held until the event loop ends and the backend has restored the terminal.
+    let screen = terminal::AlternateScreen::enter();
 
     // The cursive root.
     let mut siv = cursive::ncurses();
 
     siv.set_theme(theme::custom());
-    siv.set_fps(15);
+    siv.set_on_pre_event(Event::Key(Key::F5), theme::cycle);
+    siv.set_fps(args::fps());
+    siv.set_on_pre_event(Event::Refresh, player::update_fps);
+    siv.set_on_pre_event(Event::Refresh, player::sleep_inhibitor::update);
+    siv.set_on_pre_event(Event::Refresh, player::hooks::update);
+    siv.set_on_pre_event(Event::Refresh, player::lyrics::update);
+    siv.set_on_pre_event(Event::Refresh, player::on_playlist_end);
+
+    // Quit through the event loop on SIGINT, SIGTERM or SIGHUP, so that the
+    // player is dropped and the terminal is restored.
+    let cb_sink = siv.cb_sink().clone();
+    ctrlc::set_handler(move || {
+        cb_sink.send(Box::new(|siv| siv.quit())).unwrap_or_default();
+    })?;
 
-    // Don't load the fuzzy-finder if there is only one audio item.
-    if let Some(path) = fuzzy::only_audio_path(&path, &items) {
+    if args::audiobook() {
+        siv.set_on_pre_event(Event::Refresh, player::audiobook::update);
+    }
+
+    if args::idle_timeout().is_some() {
+        siv.set_on_pre_event_inner(player::input_trigger(), player::record_input);
+        siv.set_on_pre_event(Event::Refresh, player::quit_if_idle);
+    }
+
+    if args::status_file().is_some() {
+        siv.set_on_pre_event(Event::Refresh, player::now_playing::update);
+    }
+
+    // Held until tap quits, so that the socket is removed.
+    #[cfg(unix)]
+    let _socket = match args::socket() {
+        Some(socket) => Some(tap::ipc::spawn(socket, siv.cb_sink().clone())?),
+        None => None,
+    };
+
+    #[cfg(all(target_os = "linux", feature = "output-monitor"))]
+    if args::pause_on_output_change() {
+        player::output_monitor::spawn(siv.cb_sink().clone());
+    }
+
+    // Don't load the fuzzy-finder if there is only one audio item. A file has
+    // no items, so it's always played.
+    if is_url {
+        load_standalone_player(path, &mut siv)?;
+    } else if path.is_dir() && fuzzy::leaf_paths(&items).is_empty() {
+        load_without_audio(items, &mut siv, path)?;
+    } else if let Some(path) = fuzzy::only_audio_path(&path, &items) {
         load_standalone_player(path, &mut siv)?;
     } else {
-        load_fuzzy_finder(items, &mut siv, path)?;
+        load_fuzzy_finder(items, &mut siv);
     }
 
-    run_or_test(siv)
+    run_or_test(siv)?;
+
+    // Printed once the main screen is restored, so that it isn't lost.
+    drop(screen);
+    match args::print_selection() {
+        true => fuzzy::print_selection(&mut std::io::stdout()),
+        false => Ok(()),
+    }
 }
 
 fn get_items(path: &PathBuf, opts: Opts) -> Result<Vec<FuzzyItem>, anyhow::Error> {
-    let items = if opts == Opts::Default || persistent_data::uses_default(path) {
+    let roots = args::search_roots().to_vec();
+
+    // Only the default directory is cached, so multiple roots are always walked.
+    let items = if roots.len() > 1 {
+        let walk_opts = args::walk_opts();
+        utils::display_with_spinner(
+            move |_| fuzzy::create_items_in(&roots, &walk_opts),
+            path,
+            "loading",
+        )?
+    } else if opts == Opts::Default || persistent_data::uses_default(path) {
         persistent_data::get_cached_items(path)?
     } else {
-        utils::display_with_spinner(fuzzy::create_items, path, "loading")?
+        let walk_opts = args::walk_opts();
+        utils::display_with_spinner(
+            move |path| fuzzy::create_items(path, &walk_opts),
+            path,
+            "loading",
+        )?
     };
 
     if args::audio_only() {
-        Ok(fuzzy::audio_items(&items))
+        Ok(fuzzy::audio_items(&items, args::natural_sort()))
     } else {
         Ok(items)
     }
@@ -82,26 +181,80 @@ fn load_standalone_player(
     Ok(())
 }
 
-fn load_fuzzy_finder(
+// Loads the fuzzy-finder over the directories below `path` when none of them
+// contain audio, so that they can still be browsed, or reloaded once audio is
+// added. Fails if there are no directories to browse.
+fn load_without_audio(
     items: Vec<FuzzyItem>,
     siv: &mut CursiveRunnable,
     path: PathBuf,
 ) -> Result<(), anyhow::Error> {
-    FuzzyView::load(items.to_owned(), None, siv);
+    if let Err(e) = path.read_dir() {
+        bail!("could not read '{}'\n- `{}`", path.display(), e)
+    }
 
-    let session_data = SessionData::new(&path, &items)?;
-    siv.set_user_data(session_data.into_inner());
+    let msg = format!(
+        "no audio files detected in '{}'\n\
+        - run `tap <PATH>` with a directory that contains audio, \
+        or set a default directory with `tap -s <PATH>`",
+        path.display()
+    );
 
-    siv.set_on_pre_event_inner('-', player::previous_album);
-    siv.set_on_pre_event_inner('=', player::random_album);
+    if items.is_empty() {
+        bail!(TapError::NoAudio(msg))
+    }
 
-    siv.set_on_pre_event_inner(fuzzy::trigger(), move |event: &Event| {
-        fuzzy::fuzzy_finder(event, &items)
-    });
+    load_fuzzy_finder(items, siv);
+    ErrorView::load(siv, anyhow::Error::msg(msg));
 
     Ok(())
 }
 
+fn load_fuzzy_finder(items: Vec<FuzzyItem>, siv: &mut CursiveRunnable) {
+    let session_data = SessionData::new(&items);
+    siv.set_user_data(session_data.into_inner());
+
+    // Random albums are played rather than selected.
+    if !args::print_selection() {
+        siv.set_on_pre_event_inner('-', player::previous_album);
+        siv.set_on_pre_event_inner('=', player::random_album);
+    }
+
+    fuzzy::set_library(items.to_owned());
+    siv.set_on_pre_event_inner(fuzzy::trigger(), fuzzy::fuzzy_finder);
+    siv.set_on_pre_event_inner(fuzzy::reload_trigger(), fuzzy::reload);
+    siv.set_on_pre_event_inner(fuzzy::toggle_trigger(), fuzzy::toggle_finder);
+    siv.set_on_pre_event(Event::Refresh, fuzzy::follow);
+
+    // The library is kept when an album is autoplayed, so that the
+    // fuzzy-finder can still be opened.
+    match args::autoplay() {
+        Some(autoplay) => autoplay_album(autoplay, items, siv),
+        None => FuzzyView::load(items, Filter::None, siv),
+    }
+}
+
+// Plays the first or a random album instead of opening the fuzzy-finder. The
+// fuzzy-finder is opened if there is no album to play, with the error if the
+// album can't be played.
+fn autoplay_album(autoplay: Autoplay, items: Vec<FuzzyItem>, siv: &mut CursiveRunnable) {
+    let player = match autoplay {
+        Autoplay::First => match fuzzy::audio_items(&items, args::natural_sort()).first() {
+            Some(item) => PlayerBuilder::FuzzyFinder.from(Some(item.path.to_owned()), siv),
+            None => return FuzzyView::load(items, Filter::None, siv),
+        },
+        Autoplay::Random => PlayerBuilder::RandomAlbum.from(None, siv),
+    };
+
+    match player {
+        Ok(player) => PlayerView::load(player, siv),
+        Err(e) => {
+            FuzzyView::load(items, Filter::None, siv);
+            ErrorView::load(siv, e);
+        }
+    }
+}
+
 fn run_or_test(mut siv: CursiveRunnable) -> Result<(), anyhow::Error> {
     // Exit the process in test builds.
     #[cfg(feature = "run_tests")]
@@ -117,6 +270,10 @@ fn run_or_test(mut siv: CursiveRunnable) -> Result<(), anyhow::Error> {
     #[cfg(not(feature = "run_tests"))]
     {
         siv.run();
+        if args::audiobook() {
+            player::audiobook::save(&mut siv);
+        }
+        player::sleep_inhibitor::release();
         Ok(())
     }
 }
@@ -0,0 +1,54 @@
+use std::{fs, path::Path, time::Duration};
+
+// The file in an album directory with the settings for that album only.
+pub const SETTINGS_FILE: &str = ".tap";
+
+// The settings read from the `.tap` file of an album, which take precedence
+// over the command line arguments while the album is playing.
+#[derive(Debug, Default, PartialEq)]
+pub struct AlbumSettings {
+    // The silence between tracks, from `gap_seconds`.
+    pub gap: Option<Duration>,
+}
+
+// Reads the `.tap` file in `dir`, if there is one. A file that can't be read,
+// or a value that isn't a number of seconds, is ignored.
+pub fn read(dir: &Path) -> AlbumSettings {
+    let table = match fs::read_to_string(dir.join(SETTINGS_FILE)) {
+        Ok(text) => text.parse::<toml::Table>().unwrap_or_default(),
+        Err(_) => return AlbumSettings::default(),
+    };
+
+    let seconds = |key: &str| match table.get(key) {
+        Some(toml::Value::Integer(secs)) => u64::try_from(*secs).ok().map(Duration::from_secs),
+        Some(toml::Value::Float(secs)) => Duration::try_from_secs_f64(*secs).ok(),
+        _ => None,
+    };
+
+    AlbumSettings {
+        gap: seconds("gap_seconds"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_gap() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        assert_eq!(read(dir.path()), AlbumSettings::default());
+
+        let gap = |text: &str| {
+            fs::write(dir.path().join(SETTINGS_FILE), text).expect("write settings");
+            read(dir.path()).gap
+        };
+
+        assert_eq!(gap("gap_seconds = 2\n"), Some(Duration::from_secs(2)));
+        assert_eq!(gap("gap_seconds = 0.5\n"), Some(Duration::from_millis(500)));
+        assert_eq!(gap("gap_seconds = 0\n"), Some(Duration::ZERO));
+        assert_eq!(gap("gap_seconds = -1\n"), None);
+        assert_eq!(gap("gap_seconds = \"2\"\n"), None);
+        assert_eq!(gap("gap_seconds = \n"), None);
+    }
+}
@@ -1,26 +1,47 @@
 use core::cmp::Ordering;
-use std::{collections::HashSet, path::PathBuf};
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
 
 use anyhow::bail;
-use lofty::{Accessor, AudioFile as LoftyAudioFile, Probe, TaggedFileExt};
+use lofty::{Accessor, AudioFile as LoftyAudioFile, Probe, Tag, TaggedFileExt};
+use rodio::Source;
+
+use super::{sidecar, stream::is_url, tracks::decode};
+
+// The names of the cover art files looked for next to the audio files, in order
+// of preference, without their extensions.
+const COVER_NAMES: [&str; 3] = ["cover", "folder", "front"];
+
+// The extensions of the cover art files.
+const COVER_EXTS: [&str; 3] = ["jpg", "jpeg", "png"];
 
 // The set of valid audio file extensions.
 lazy_static::lazy_static! {
     pub static ref AUDIO_FORMATS: HashSet<&'static str> = create_set();
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Ord)]
+/// An audio file and the metadata read from its tags.
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct AudioFile {
     pub path: PathBuf,
     pub title: String,
     pub artist: String,
     pub album: String,
     pub year: Option<u32>,
+    pub disc: Option<u32>,
     pub track: u32,
+    // The length of the track in seconds. Zero if the length is unknown.
     pub duration: usize,
+    // Where the track starts in the file, in seconds. Non-zero for the tracks
+    // after the first of a file split with a `.splits` file.
+    pub start: usize,
 }
 
 impl AudioFile {
+    /// Reads the tags and properties of the audio file at `path`.
     pub fn new(path: PathBuf) -> Result<Self, anyhow::Error> {
         let file = match Probe::open(&path) {
             Ok(f) => f,
@@ -32,52 +53,140 @@ impl AudioFile {
             Err(e) => bail!("failed to read '{}'\n- `{}`", path.display(), e),
         };
 
-        let tag = match tagged_file.primary_tag() {
-            Some(primary_tag) => primary_tag,
-            None => match tagged_file.first_tag().ok_or(()) {
-                Ok(t) => t,
-                Err(_) => bail!("no tags found for '{}'", path.display()),
-            },
+        // Embedded tags take priority. The sidecar file is only read for the
+        // tags that are missing or empty.
+        let tag = tagged_file
+            .primary_tag()
+            .or_else(|| tagged_file.first_tag());
+        let sidecar = match tag {
+            Some(tag) if !is_missing(tag) => None,
+            _ => sidecar::read(&path),
         };
+        if tag.is_none() && sidecar.is_none() {
+            bail!("no tags found for '{}'", path.display())
+        }
+        let sidecar = sidecar.unwrap_or_default();
 
         let properties = tagged_file.properties();
-        let artist = tag.artist().as_deref().unwrap_or("None").trim().to_string();
-        let duration = properties.duration().as_secs() as usize;
+        let duration = match properties.duration().as_secs() as usize {
+            // The tagged duration is missing, so we ask the decoder instead.
+            0 => decoded_duration(&path).unwrap_or(0),
+            secs => secs,
+        };
 
         let audio_file = Self {
-            album: tag.album().as_deref().unwrap_or("None").trim().to_string(),
-            title: tag.title().as_deref().unwrap_or("None").trim().to_string(),
-            year: tag.year(),
-            track: tag.track().unwrap_or(0),
-            artist,
+            album: text(tag.and_then(|t| t.album()), sidecar.album),
+            title: text(tag.and_then(|t| t.title()), sidecar.title),
+            artist: text(tag.and_then(|t| t.artist()), sidecar.artist),
+            year: tag.and_then(|t| t.year()),
+            disc: tag.and_then(|t| t.disk()),
+            track: tag.and_then(|t| t.track()).or(sidecar.track).unwrap_or(0),
             path,
             duration,
+            start: 0,
         };
 
         Ok(audio_file)
     }
+
+    /// Returns the cover art file in the directory of the audio file, if any.
+    /// Network streams have no cover.
+    pub fn cover(&self) -> Option<PathBuf> {
+        if is_url(&self.path) {
+            return None;
+        }
+        find_cover(self.path.parent()?)
+    }
+}
+
+// Order by Album -> Disc -> Track / Title
+impl Ord for AudioFile {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.album
+            .cmp(&other.album)
+            .then(self.disc.cmp(&other.disc))
+            .then(match self.track == other.track {
+                true => self.title.cmp(&other.title),
+                false => self.track.cmp(&other.track),
+            })
+            .then(self.path.cmp(&other.path))
+    }
 }
 
-// Order by Album -> Track / Title
 impl PartialOrd for AudioFile {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(
-            self.album
-                .cmp(&other.album)
-                .then(match self.track == other.track {
-                    true => self.title.cmp(&other.title),
-                    false => self.track.cmp(&other.track),
-                }),
-        )
+        Some(self.cmp(other))
     }
 }
 
-// Returns true if the file extension is a valid format.
+// Whether or not any of the tags shown for a track are missing or empty.
+fn is_missing(tag: &Tag) -> bool {
+    let is_empty = |s: Option<Cow<str>>| s.map_or(true, |s| s.trim().is_empty());
+    is_empty(tag.title())
+        || is_empty(tag.artist())
+        || is_empty(tag.album())
+        || tag.track().is_none()
+}
+
+// The trimmed text of a tag, or the value from the sidecar file if the tag is
+// missing or empty.
+fn text(tagged: Option<Cow<str>>, sidecar: Option<String>) -> String {
+    match tagged.map(|s| s.trim().to_string()) {
+        Some(s) if !s.is_empty() => s,
+        tagged => sidecar.or(tagged).unwrap_or_else(|| String::from("None")),
+    }
+}
+
+// The total duration of the audio file at `path`, as reported by the decoder.
+fn decoded_duration(path: &PathBuf) -> Option<usize> {
+    let source = decode(path).ok()?;
+    source.total_duration().map(|d| d.as_secs() as usize)
+}
+
+/// Returns true if the file extension is a valid format.
 pub fn valid_audio_ext(p: &PathBuf) -> bool {
     let ext = p.extension().unwrap_or_default().to_str().unwrap();
     AUDIO_FORMATS.contains(&ext)
 }
 
+/// Returns the name of the format if `p` is an audio file that can't be
+/// decoded. The decoder has no support for these formats, so they are never
+/// listed, but they are named in errors so that it's clear why.
+pub fn unsupported_format(p: &PathBuf) -> Option<&'static str> {
+    let ext = p.extension()?.to_str()?.to_ascii_lowercase();
+    match ext.as_str() {
+        "wv" => Some("WavPack (.wv)"),
+        "ape" => Some("Monkey's Audio (.ape)"),
+        "mpc" => Some("Musepack (.mpc)"),
+        _ => None,
+    }
+}
+
+/// Returns the cover art file in `dir`, such as `cover.jpg`, `folder.jpg` or
+/// `front.png`. Names are matched case-insensitively.
+pub fn find_cover(dir: &Path) -> Option<PathBuf> {
+    let covers = dir
+        .read_dir()
+        .ok()?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_file())
+        .filter_map(|p| cover_rank(&p).map(|rank| (rank, p)))
+        .collect::<Vec<_>>();
+
+    covers.into_iter().min().map(|(_, p)| p)
+}
+
+// The preference of `p` as a cover, lower is better. `None` if `p` isn't a cover.
+fn cover_rank(p: &Path) -> Option<usize> {
+    let stem = p.file_stem()?.to_str()?.to_ascii_lowercase();
+    let ext = p.extension()?.to_str()?.to_ascii_lowercase();
+
+    if !COVER_EXTS.contains(&ext.as_str()) {
+        return None;
+    }
+    COVER_NAMES.iter().position(|name| *name == stem)
+}
+
 fn create_set() -> HashSet<&'static str> {
     let mut m = HashSet::new();
     m.insert("aac");
@@ -89,3 +198,93 @@ fn create_set() -> HashSet<&'static str> {
     m.insert("wma");
     m
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::create_working_dir;
+
+    fn audio_file(disc: u32, track: u32) -> AudioFile {
+        AudioFile {
+            path: PathBuf::from(format!("{}-{}.flac", disc, track)),
+            title: format!("title {}", track),
+            artist: String::from("artist"),
+            album: String::from("album"),
+            year: None,
+            disc: Some(disc),
+            track,
+            duration: 60,
+            start: 0,
+        }
+    }
+
+    #[test]
+    fn test_sort_multi_disc_album() {
+        let mut list = vec![
+            audio_file(2, 1),
+            audio_file(1, 2),
+            audio_file(2, 2),
+            audio_file(1, 1),
+        ];
+        list.sort();
+
+        let order = list
+            .iter()
+            .map(|f| (f.disc.unwrap(), f.track))
+            .collect::<Vec<_>>();
+
+        assert_eq!(order, vec![(1, 1), (1, 2), (2, 1), (2, 2)]);
+    }
+
+    #[test]
+    fn test_find_cover() {
+        let root = create_working_dir(&[], &[], &["a.mp3", "Front.PNG", "FOLDER.jpg"])
+            .expect("create temp dir");
+        assert_eq!(
+            find_cover(root.path()),
+            Some(root.path().join("FOLDER.jpg"))
+        );
+
+        let root = create_working_dir(&[], &[], &["a.mp3", "back.jpg", "cover.txt"])
+            .expect("create temp dir");
+        assert_eq!(find_cover(root.path()), None);
+    }
+
+    #[test]
+    fn test_sidecar_fills_in_missing_tags() {
+        let root = create_working_dir(
+            &[],
+            &[
+                ("01.mp3", "test_audio_no_tags.mp3"),
+                ("02.mp3", "test_mp3_audio.mp3"),
+            ],
+            &[],
+        )
+        .expect("create temp dir");
+        let untagged = root.path().join("01.mp3");
+        let tagged = root.path().join("02.mp3");
+        assert!(AudioFile::new(untagged.to_owned()).is_err());
+
+        let embedded = AudioFile::new(tagged.to_owned()).expect("should read tags");
+        std::fs::write(
+            root.path().join(sidecar::SIDECAR_FILE),
+            r#"{
+                "artist": "Miles Davis",
+                "tracks": [
+                    { "file": "01.mp3", "title": "So What", "track": 1 },
+                    { "file": "02.mp3", "title": "Freddie Freeloader", "track": 2 }
+                ]
+            }"#,
+        )
+        .expect("write sidecar");
+
+        let file = AudioFile::new(untagged).expect("should read the sidecar");
+        assert_eq!(file.title, "So What");
+        assert_eq!(file.artist, "Miles Davis");
+        assert_eq!(file.album, "None");
+        assert_eq!(file.track, 1);
+
+        // Embedded tags are kept.
+        assert_eq!(AudioFile::new(tagged).expect("should read tags"), embedded);
+    }
+}
@@ -0,0 +1,84 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use cursive::Cursive;
+
+use crate::data::bookmarks;
+use crate::fuzzy::ErrorView;
+
+use super::{is_url, player_view, PlayerStatus, PlayerView};
+
+// How often the position is saved while playing, in seconds.
+const SAVE_INTERVAL: u64 = 10;
+
+lazy_static::lazy_static! {
+    // The track, position and status that were last saved.
+    static ref LAST: Mutex<Option<(PathBuf, u64, PlayerStatus)>> = Mutex::new(None);
+}
+
+// Whether or not a failed save has been reported. Only the first is.
+static REPORTED: AtomicBool = AtomicBool::new(false);
+
+// Saves the track and position of the current book with `--audiobook`, every
+// `SAVE_INTERVAL` seconds while playing and whenever the track or status changes.
+pub fn update(siv: &mut Cursive) {
+    save_if(siv, false);
+}
+
+// Saves the track and position of the current book. Called on quit.
+pub fn save(siv: &mut Cursive) {
+    save_if(siv, true);
+}
+
+fn save_if(siv: &mut Cursive, force: bool) {
+    let current = siv
+        .call_on_name(player_view::NAME, |v: &mut PlayerView| {
+            let player = v.player();
+            (!is_url(player.path())).then(|| {
+                (
+                    player.dir(),
+                    player.path().to_owned(),
+                    player.elapsed().as_secs(),
+                    player.status.to_owned(),
+                )
+            })
+        })
+        .flatten();
+
+    let (dir, track, secs, status) = match current {
+        Some(current) => current,
+        None => return,
+    };
+
+    let mut last = match LAST.lock() {
+        Ok(last) => last,
+        Err(_) => return,
+    };
+
+    let is_due = match last.as_ref() {
+        Some((t, s, st)) => {
+            t != &track
+                || st != &status
+                || (status == PlayerStatus::Playing && secs.abs_diff(*s) >= SAVE_INTERVAL)
+        }
+        None => true,
+    };
+
+    if !is_due && !force {
+        return;
+    }
+
+    *last = Some((track.to_owned(), secs, status));
+    drop(last);
+
+    if let Err(e) = bookmarks::set(dir, track, secs) {
+        if !REPORTED.swap(true, Ordering::Relaxed) {
+            ErrorView::load(siv, e);
+        }
+    }
+}
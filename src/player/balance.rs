@@ -0,0 +1,158 @@
+use std::{
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use rodio::{source::SeekError, Sample, Source};
+
+// The change in balance with `<` and `>`.
+pub const BALANCE_STEP: f32 = 0.1;
+
+lazy_static::lazy_static! {
+    // The balance of the output, shared by every source so that a change applies
+    // to the track that is playing and carries over to the next album.
+    static ref BALANCE: Arc<AtomicU32> = Arc::new(AtomicU32::new(0f32.to_bits()));
+}
+
+// The balance of the output, in range -1.0..=1.0, shared with the sources.
+#[derive(Clone)]
+pub struct Balance(Arc<AtomicU32>);
+
+impl Balance {
+    // The balance shared by the players of the session.
+    pub fn shared() -> Self {
+        Self(BALANCE.clone())
+    }
+
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    // Sets the balance, where -1.0 plays only the left channel and 1.0 only the
+    // right channel. Returns the balance that was set.
+    pub fn set(&self, balance: f32) -> f32 {
+        // Rounded so that stepping back and forth returns to the center.
+        let balance = (balance.clamp(-1.0, 1.0) * 10.0).round() / 10.0;
+        self.0.store(balance.to_bits(), Ordering::Relaxed);
+        balance
+    }
+}
+
+impl Default for Balance {
+    fn default() -> Self {
+        Self(Arc::new(AtomicU32::new(0f32.to_bits())))
+    }
+}
+
+// The gains of the left and right channels for `balance`. The channel the
+// balance is shifted towards stays at full volume while the other fades.
+pub fn gains(balance: f32) -> (f32, f32) {
+    ((1.0 - balance).min(1.0), (1.0 + balance).min(1.0))
+}
+
+// A source that scales the left and right channels of a stereo source by the
+// gains for the current balance. Other sources are played as they are.
+pub struct Pan<S> {
+    source: S,
+    balance: Balance,
+    // The channel of the next sample.
+    channel: u16,
+}
+
+impl<S> Pan<S> {
+    pub fn new(source: S, balance: Balance) -> Self {
+        Self {
+            source,
+            balance,
+            channel: 0,
+        }
+    }
+}
+
+impl<S> Iterator for Pan<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sample = self.source.next()?;
+        let channels = self.source.channels().max(1);
+        let channel = self.channel;
+        self.channel = (self.channel + 1) % channels;
+
+        if channels != 2 {
+            return Some(sample);
+        }
+
+        let (left, right) = gains(self.balance.get());
+        match channel {
+            0 => Some(sample.amplify(left)),
+            _ => Some(sample.amplify(right)),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.source.size_hint()
+    }
+}
+
+impl<S> Source for Pan<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.source.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.source.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.source.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        // Seeking lands on the start of a frame, which is the left channel.
+        self.channel = 0;
+        self.source.try_seek(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rodio::buffer::SamplesBuffer;
+
+    use super::*;
+
+    #[test]
+    fn test_pan_gains() {
+        let balance = Balance::default();
+        let samples = || SamplesBuffer::new(2, 44100, vec![1.0f32; 8]);
+
+        let centered = Pan::new(samples(), balance.clone()).collect::<Vec<_>>();
+        assert!(centered.iter().all(|&s| s == 1.0));
+
+        assert_eq!(balance.set(0.5), 0.5);
+        let right = Pan::new(samples(), balance.clone()).collect::<Vec<_>>();
+        assert_eq!(right, vec![0.5, 1.0, 0.5, 1.0, 0.5, 1.0, 0.5, 1.0]);
+
+        assert_eq!(balance.set(-3.0), -1.0);
+        let left = Pan::new(samples(), balance.clone()).collect::<Vec<_>>();
+        assert_eq!(left, vec![1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0]);
+
+        // Mono sources are left as they are.
+        let mono = Pan::new(SamplesBuffer::new(1, 44100, vec![1.0f32; 4]), balance);
+        assert!(mono.into_iter().all(|s| s == 1.0));
+    }
+}
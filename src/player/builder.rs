@@ -3,7 +3,7 @@ use std::path::PathBuf;
 use anyhow::bail;
 use cursive::Cursive;
 
-use crate::session_data::SessionData;
+use crate::data::SessionData;
 use crate::utils::{self, InnerType};
 
 use super::{
@@ -18,13 +18,22 @@ pub enum PlayerBuilder {
     PreviousTrack,
     RandomAlbum,
     RandomTrack,
+    SwapAlbum,
 }
 
 impl PlayerBuilder {
     pub fn from(&self, path: Option<PathBuf>, siv: &mut Cursive) -> PlayerResult {
+        // The queue is empty until something is played when the search root
+        // has no audio.
+        if path.is_none() && Self::is_queue_empty(siv) {
+            bail!("nothing has been played")
+        }
+
         match self {
             Self::FuzzyFinder => Self::fuzzy(path, siv),
-            Self::PreviousAlbum | Self::PreviousTrack => Self::previous(&self, siv),
+            Self::PreviousAlbum | Self::PreviousTrack | Self::SwapAlbum => {
+                Self::previous(&self, siv)
+            }
             Self::RandomAlbum | Self::RandomTrack => Self::random(&self, siv),
         }
     }
@@ -34,6 +43,11 @@ impl PlayerBuilder {
         Player::new(path, 0, opts, false)
     }
 
+    fn is_queue_empty(siv: &mut Cursive) -> bool {
+        siv.with_user_data(|(_, _, queue): &mut InnerType<SessionData>| queue.is_empty())
+            .unwrap_or(true)
+    }
+
     fn previous(&self, siv: &mut Cursive) -> PlayerResult {
         let ((path, mut index), opts) = siv
             .with_user_data(|(opts, _, queue): &mut InnerType<SessionData>| {
@@ -104,6 +118,10 @@ impl PlayerBuilder {
             .with_user_data(|(opts, _, queue): &mut InnerType<SessionData>| {
                 let opts: PlayerOpts = (*opts).into();
 
+                if queue.is_empty() {
+                    queue.push_back((path.clone(), 0));
+                }
+
                 if queue.len() == 1 {
                     queue.push_front((path.clone(), 0));
                     queue.push_front((path.clone(), 0));
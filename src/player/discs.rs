@@ -0,0 +1,150 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+
+use crate::error::TapError;
+use crate::fuzzy::sort_key;
+
+use super::{audio_files, valid_audio_ext, AudioFile, AudioOpts};
+
+// The subdirectories of `path` that are the discs of one album, such as `CD1`
+// and `CD2`, in order of their names. `None` if `path` has audio files of its
+// own, or a subdirectory with audio that isn't named as a disc, so that a
+// folder of albums isn't played as one.
+pub fn dirs(path: &Path) -> Option<Vec<PathBuf>> {
+    let mut discs = vec![];
+
+    for entry in path.read_dir().ok()?.filter_map(Result::ok) {
+        let path = entry.path();
+
+        if !path.is_dir() {
+            match valid_audio_ext(&path) {
+                true => return None,
+                false => continue,
+            }
+        }
+        // Subdirectories without audio, such as `Scans`, are left out.
+        if !has_audio(&path) {
+            continue;
+        }
+
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default();
+        match is_disc_name(name) {
+            true => discs.push(path),
+            false => return None,
+        }
+    }
+
+    discs.sort_by_key(|p| sort_key(&p.file_name().unwrap_or_default().to_string_lossy()));
+    (!discs.is_empty()).then_some(discs)
+}
+
+// The tracks of each disc in turn. Discs that can't be played are skipped.
+pub fn merged_files(discs: &[PathBuf], opts: &AudioOpts) -> Result<Vec<AudioFile>, anyhow::Error> {
+    let list = discs
+        .iter()
+        .filter_map(|disc| audio_files(disc, opts).ok())
+        .flatten()
+        .collect::<Vec<_>>();
+
+    if list.is_empty() {
+        let dir = discs
+            .first()
+            .and_then(|d| d.parent())
+            .unwrap_or(Path::new(""));
+        bail!(TapError::NoAudio(format!(
+            "no audio files detected in the discs of '{}'",
+            dir.display()
+        )))
+    }
+    Ok(list)
+}
+
+// Whether `name` is the name of a disc, such as "CD1", "CD 2", "Disc 03" or
+// "disk_4 - Live", ignoring case.
+fn is_disc_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    let rest = match ["cd", "disc", "disk"]
+        .iter()
+        .find_map(|p| name.strip_prefix(p))
+    {
+        Some(rest) => rest.trim_start_matches([' ', '_', '-', '.']),
+        None => return false,
+    };
+
+    let digits = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    digits > 0
+        && rest[digits..]
+            .chars()
+            .next()
+            .map_or(true, |c| !c.is_alphanumeric())
+}
+
+// Whether `dir` contains an audio file.
+fn has_audio(dir: &Path) -> bool {
+    match dir.read_dir() {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .any(|entry| valid_audio_ext(&entry.path())),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::create_working_dir;
+
+    #[test]
+    fn test_is_disc_name() {
+        for name in ["CD1", "cd 2", "Disc 03", "disk_4 - Live", "CD10"] {
+            assert!(is_disc_name(name), "{name}");
+        }
+        for name in ["CDs", "Discography", "cd", "Disc One", "CD1a", "Scans"] {
+            assert!(!is_disc_name(name), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_discs_are_merged_in_order() {
+        let root = create_working_dir(
+            &[
+                "album/CD 10",
+                "album/CD 2",
+                "album/Scans",
+                "artist/one",
+                "artist/two",
+            ],
+            &[
+                ("album/CD 2/a.mp3", "test_mp3_audio.mp3"),
+                ("album/CD 2/b.flac", "test_flac_audio.flac"),
+                ("album/CD 10/c.ogg", "test_ogg_audio.ogg"),
+                ("artist/one/d.mp3", "test_mp3_audio.mp3"),
+                ("artist/two/e.mp3", "test_mp3_audio.mp3"),
+            ],
+            &["album/Scans/front.jpg"],
+        )
+        .expect("create temp dir")
+        .into_path();
+
+        let album = root.join("album");
+        let discs = dirs(&album).expect("should find the discs");
+        assert_eq!(discs, vec![album.join("CD 2"), album.join("CD 10")]);
+
+        let list = merged_files(&discs, &AudioOpts::default()).expect("should merge the discs");
+        assert_eq!(list.len(), 3);
+        assert!(list[..2]
+            .iter()
+            .all(|f| f.path.starts_with(album.join("CD 2"))));
+        assert!(list[2].path.starts_with(album.join("CD 10")));
+
+        // A folder of albums isn't a multi-disc album.
+        assert_eq!(dirs(&root.join("artist")), None);
+        assert_eq!(dirs(&album.join("CD 2")), None);
+    }
+}
@@ -0,0 +1,197 @@
+use super::AudioFile;
+
+// The placeholders that can be used in `--header-format`.
+const FIELDS: [&str; 4] = ["artist", "album", "year", "title"];
+
+// The characters dropped from the end of the text before a missing field.
+const OPENING: &[char] = &[' ', '(', '[', '<', '"', '\''];
+
+// The characters dropped from the start of the text after a missing field.
+const CLOSING: &[char] = &[')', ']', '>', '"', '\''];
+
+// The style that a part of the header is drawn in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HeaderStyle {
+    // The artist, in the `header1` color.
+    Artist,
+    // The album and year, in the `header2` color and italic.
+    Album,
+    // The title, in the `hl` color.
+    Title,
+}
+
+enum Token<'a> {
+    Text(&'a str),
+    Field(&'a str),
+}
+
+// Renders the header `template` for `f` into the parts drawn in each style.
+// Text takes the style of the field before it. A field without a value, such
+// as an untagged year, is left out along with the brackets and spaces around it.
+pub fn render(template: &str, f: &AudioFile) -> Vec<(String, HeaderStyle)> {
+    // The text, style and whether or not the text is from the template.
+    let mut parts: Vec<(String, HeaderStyle, bool)> = vec![];
+    let mut style = HeaderStyle::Artist;
+    let mut after_missing = false;
+    let mut has_value = false;
+
+    for token in tokenize(template) {
+        match token {
+            Token::Text(text) => {
+                let text = match (after_missing, has_value) {
+                    // Nothing to separate from.
+                    (true, false) => "",
+                    (true, true) => text.trim_start_matches(CLOSING),
+                    _ => text,
+                };
+                parts.push((text.to_string(), style, true));
+                after_missing = false;
+            }
+            Token::Field(name) => {
+                style = field_style(name);
+                match value(name, f) {
+                    Some(value) => {
+                        parts.push((value, style, false));
+                        has_value = true;
+                        after_missing = false;
+                    }
+                    None => {
+                        if let Some((text, _, true)) = parts.last_mut() {
+                            text.truncate(text.trim_end_matches(OPENING).len());
+                        }
+                        after_missing = true;
+                    }
+                }
+            }
+        }
+    }
+
+    // Join the neighboring parts that are drawn in the same style.
+    let mut joined: Vec<(String, HeaderStyle)> = vec![];
+    for (text, style, _) in parts.into_iter().filter(|(text, _, _)| !text.is_empty()) {
+        match joined.last_mut() {
+            Some((last, last_style)) if *last_style == style => last.push_str(&text),
+            _ => joined.push((text, style)),
+        }
+    }
+    joined
+}
+
+// Splits `template` into text and placeholders. Braces that don't enclose a
+// known placeholder are kept as text.
+fn tokenize(template: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        let field = rest[start + 1..]
+            .find('}')
+            .map(|end| &rest[start + 1..start + 1 + end])
+            .filter(|name| FIELDS.contains(name));
+
+        match field {
+            Some(name) => {
+                if start > 0 {
+                    tokens.push(Token::Text(&rest[..start]));
+                }
+                tokens.push(Token::Field(name));
+                rest = &rest[start + name.len() + 2..];
+            }
+            None => {
+                tokens.push(Token::Text(&rest[..start + 1]));
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+
+    if !rest.is_empty() {
+        tokens.push(Token::Text(rest));
+    }
+    tokens
+}
+
+fn field_style(name: &str) -> HeaderStyle {
+    match name {
+        "artist" => HeaderStyle::Artist,
+        "title" => HeaderStyle::Title,
+        _ => HeaderStyle::Album,
+    }
+}
+
+fn value(name: &str, f: &AudioFile) -> Option<String> {
+    let value = match name {
+        "artist" => f.artist.to_owned(),
+        "album" => f.album.to_owned(),
+        "year" => f.year?.to_string(),
+        "title" => f.title.to_owned(),
+        _ => return None,
+    };
+    (!value.is_empty()).then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn file(year: Option<u32>) -> AudioFile {
+        AudioFile {
+            path: PathBuf::from("/music/a.mp3"),
+            title: String::from("So What"),
+            artist: String::from("Miles Davis"),
+            album: String::from("Kind of Blue"),
+            year,
+            disc: None,
+            track: 1,
+            duration: 60,
+            start: 0,
+        }
+    }
+
+    #[test]
+    fn test_render_header() {
+        use HeaderStyle::*;
+        let own = |parts: &[(&str, HeaderStyle)]| {
+            parts
+                .iter()
+                .map(|(text, style)| (text.to_string(), *style))
+                .collect::<Vec<_>>()
+        };
+
+        // The default matches the original header.
+        let template = "{artist}  {album} ({year})";
+        assert_eq!(
+            render(template, &file(Some(1959))),
+            own(&[("Miles Davis  ", Artist), ("Kind of Blue (1959)", Album)])
+        );
+        assert_eq!(
+            render(template, &file(None)),
+            own(&[("Miles Davis  ", Artist), ("Kind of Blue", Album)])
+        );
+
+        let template = "{album} [{year}] — {artist}: {title} {unknown}";
+        assert_eq!(
+            render(template, &file(Some(1959))),
+            own(&[
+                ("Kind of Blue [1959] — ", Album),
+                ("Miles Davis: ", Artist),
+                ("So What {unknown}", Title),
+            ])
+        );
+        assert_eq!(
+            render(template, &file(None)),
+            own(&[
+                ("Kind of Blue — ", Album),
+                ("Miles Davis: ", Artist),
+                ("So What {unknown}", Title),
+            ])
+        );
+
+        // Nothing is left to separate a missing first field from.
+        assert_eq!(
+            render("({year}) {album}", &file(None)),
+            own(&[("Kind of Blue", Album)])
+        );
+    }
+}
@@ -0,0 +1,214 @@
+use std::{
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use cursive::Cursive;
+
+use crate::config::args;
+use crate::fuzzy::ErrorView;
+
+use super::{player_view, AudioFile, PlayerStatus, PlayerView};
+
+lazy_static::lazy_static! {
+    // The track and status the hooks were last run for.
+    static ref LAST: Mutex<Option<(PathBuf, usize, PlayerStatus)>> = Mutex::new(None);
+}
+
+// Whether or not a failed command has been reported. Only the first is.
+static REPORTED: AtomicBool = AtomicBool::new(false);
+
+// Runs the `--on-track-change`, `--on-pause` and `--on-stop` commands when the
+// player starts a new track, is paused or is stopped.
+pub fn update(siv: &mut Cursive) {
+    let (on_track_change, on_pause, on_stop) = args::hooks();
+    if on_track_change.is_none() && on_pause.is_none() && on_stop.is_none() {
+        return;
+    }
+
+    let current = siv.call_on_name(player_view::NAME, |v: &mut PlayerView| {
+        let player = v.player();
+        (
+            player.file().to_owned(),
+            player.index,
+            player.status.to_owned(),
+        )
+    });
+
+    let (file, index, status) = match current {
+        Some(current) => current,
+        None => return,
+    };
+
+    let mut last = match LAST.lock() {
+        Ok(last) => last,
+        Err(_) => return,
+    };
+
+    let (is_new_track, last_status) = match last.as_ref() {
+        Some((path, i, s)) => (path != &file.path || *i != index, Some(s.to_owned())),
+        None => (true, None),
+    };
+
+    // Resuming a paused track isn't a track change, but playing a stopped
+    // track again is.
+    let command = match (&status, last_status) {
+        (PlayerStatus::Playing, _) if is_new_track => on_track_change,
+        (PlayerStatus::Playing, Some(PlayerStatus::Stopped)) => on_track_change,
+        (PlayerStatus::Paused, Some(s)) if s != PlayerStatus::Paused => on_pause,
+        (PlayerStatus::Stopped, Some(s)) if s != PlayerStatus::Stopped => on_stop,
+        _ => None,
+    };
+
+    *last = Some((file.path.to_owned(), index, status));
+    drop(last);
+
+    if let Some(command) = command {
+        run(siv, command, &file);
+    }
+}
+
+// Runs `template` in the shell, without waiting for it to finish. The output
+// is discarded.
+pub fn run(siv: &mut Cursive, template: &str, file: &AudioFile) {
+    let command = expand(template, file);
+
+    let child = shell(&command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+
+    let cb_sink = siv.cb_sink().clone();
+
+    thread::spawn(move || {
+        let error = match child.and_then(|mut child| child.wait()) {
+            Ok(status) if status.success() => return,
+            Ok(status) => format!("'{}' failed with {}", command, status),
+            Err(e) => format!("could not run '{}'\n- `{}`", command, e),
+        };
+
+        if !REPORTED.swap(true, Ordering::Relaxed) {
+            cb_sink
+                .send(Box::new(move |siv| {
+                    ErrorView::load(siv, anyhow::Error::msg(error))
+                }))
+                .unwrap_or_default();
+        }
+    });
+}
+
+// Replaces the `{path}`, `{title}`, `{artist}`, `{album}` and `{cover}`
+// placeholders in `template` with the quoted values of `file`. `{cover}` is
+// empty if the album has no cover art. The template is scanned once, so a tag
+// that contains a placeholder isn't expanded again.
+fn expand(template: &str, file: &AudioFile) -> String {
+    let cover = file.cover().unwrap_or_default();
+    let values = [
+        ("{path}", quote(&file.path.to_string_lossy())),
+        ("{cover}", quote(&cover.to_string_lossy())),
+        ("{title}", quote(&file.title)),
+        ("{artist}", quote(&file.artist)),
+        ("{album}", quote(&file.album)),
+    ];
+
+    let mut command = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        command.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        match values.iter().find(|(key, _)| rest.starts_with(key)) {
+            Some((key, value)) => {
+                command.push_str(value);
+                rest = &rest[key.len()..];
+            }
+            None => {
+                command.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    command.push_str(rest);
+    command
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(not(unix))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+// Quotes `s` so that the shell reads it as a single word, whatever the tags
+// contain.
+#[cfg(unix)]
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+#[cfg(not(unix))]
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', ""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_expand_quotes_placeholders() {
+        let file = AudioFile {
+            path: PathBuf::from("/music/a.mp3"),
+            title: String::from("It's; rm -rf ~"),
+            artist: String::from("foo"),
+            album: String::from("bar"),
+            year: None,
+            disc: None,
+            track: 1,
+            duration: 60,
+            start: 0,
+        };
+
+        assert_eq!(
+            expand("notify-send {artist} {title} # {path}", &file),
+            "notify-send 'foo' 'It'\\''s; rm -rf ~' # '/music/a.mp3'"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_expand_ignores_placeholders_in_tags() {
+        let file = AudioFile {
+            path: PathBuf::from("/music/{album}.mp3"),
+            title: String::from("{artist}"),
+            artist: String::from("; rm -rf ~"),
+            album: String::from("{title}"),
+            year: None,
+            disc: None,
+            track: 1,
+            duration: 60,
+            start: 0,
+        };
+
+        assert_eq!(
+            expand("echo {title} {artist} {path} {unknown}", &file),
+            "echo '{artist}' '; rm -rf ~' '/music/{album}.mp3' {unknown}"
+        );
+    }
+}
@@ -7,10 +7,14 @@ use cursive::{
     Cursive,
 };
 
+use crate::config::glyphs;
+
 pub struct KeysView {}
 
 impl KeysView {
     pub fn new() -> ScrollView<PaddedView<LinearLayout>> {
+        let glyphs = glyphs::get();
+
         ScrollView::new(PaddedView::lrtb(
             2,
             2,
@@ -21,14 +25,21 @@ impl KeysView {
                     Dialog::new().title("Global").content(
                         ListView::new()
                             .child("fuzzy search:", TextView::new("Tab"))
+                            .child("last search:", TextView::new("Shift + Tab"))
                             .child("depth search:", TextView::new("F1...F4"))
                             .child("filtered search:", TextView::new("A...Z"))
                             .child("artist search:", TextView::new("Ctrl + a"))
                             .child("album search:", TextView::new("Ctrl + s"))
+                            .child("favorites search:", TextView::new("Ctrl + f"))
+                            .child("recently added:", TextView::new("Ctrl + w"))
+                            .child("most played:", TextView::new("Ctrl + b"))
                             .child("parent search:", TextView::new("Ctrl + p"))
                             .child("previous album:", TextView::new("-"))
                             .child("random album:", TextView::new("="))
-                            .child("open file manager:", TextView::new("Ctrl + o")),
+                            .child("open file manager:", TextView::new("Ctrl + o"))
+                            .child("open shell:", TextView::new("Ctrl + e"))
+                            .child("reload:", TextView::new("Ctrl + r"))
+                            .child("next theme:", TextView::new("F5")),
                     ),
                 )
                 .child(DummyView.fixed_height(1))
@@ -36,21 +47,41 @@ impl KeysView {
                     Dialog::new().title("Player").content(
                         ListView::new()
                             .child("play:", TextView::new("h or ← or Space"))
-                            .child("next:", TextView::new("j or ↓"))
-                            .child("previous:", TextView::new("k or ↑"))
+                            .child("next:", TextView::new(format!("j or {}", glyphs.down)))
+                            .child("previous:", TextView::new(format!("k or {}", glyphs.up)))
                             .child("stop:", TextView::new("l or → or Enter"))
                             .child("step forward:", TextView::new("."))
                             .child("step backward:", TextView::new(","))
+                            .child("restart track:", TextView::new("Home"))
+                            .child("show file path:", TextView::new("i"))
+                            .child("show percentage:", TextView::new("%"))
+                            .child("swap album (A/B):", TextView::new("b"))
+                            .child("copy timestamp:", TextView::new("c"))
                             .child("seek to sec", TextView::new("0..9 + \""))
                             .child("seek to min", TextView::new("0..9 + \'"))
+                            .child("seek from end", TextView::new("0..9 + ;"))
                             .child("random:", TextView::new("r or *"))
+                            .child("random track:", TextView::new("x"))
+                            .child("shuffle albums:", TextView::new("a"))
+                            .child("move track up:", TextView::new(format!("{{ or Ctrl + {}", glyphs.up)))
+                            .child("move track down:", TextView::new(format!("}} or Ctrl + {}", glyphs.down)))
                             .child("volume up:", TextView::new("]"))
                             .child("volume down:", TextView::new("["))
+                            .child("volume up 1%:", TextView::new("Alt + ]"))
+                            .child("volume down 1%:", TextView::new("Alt + ["))
                             .child("show volume:", TextView::new("v"))
+                            .child("balance left:", TextView::new("<"))
+                            .child("balance right:", TextView::new(">"))
+                            .child("center balance:", TextView::new("|"))
+                            .child("show/hide playlist:", TextView::new("p"))
+                            .child("favorite album:", TextView::new("f"))
+                            .child("don't play track:", TextView::new("d"))
                             .child("mute:", TextView::new("m"))
                             .child("go to first track:", TextView::new("gg"))
                             .child("go to last track:", TextView::new("Ctrl + g"))
                             .child("go to track number:", TextView::new("0...9 + g"))
+                            .child("go to percent:", TextView::new("0...9 + G"))
+                            .child("show lyrics:", TextView::new("y"))
                             .child("help:", TextView::new("?"))
                             .child("quit:", TextView::new("q")),
                     ),
@@ -63,7 +94,13 @@ impl KeysView {
                             .child("cancel search:", TextView::new("Esc"))
                             .child("page up:", TextView::new("Ctrl + h or PgUp"))
                             .child("page down:", TextView::new("Ctrl + l or PgDn"))
-                            .child("random page:", TextView::new("Ctrl + z")),
+                            .child("random page:", TextView::new("Ctrl + z"))
+                            .child("jump to letter:", TextView::new("Alt + a..z"))
+                            .child("favorite folder:", TextView::new("Ctrl + t"))
+                            .child("search subfolders:", TextView::new("Ctrl + n"))
+                            .child("show tracks:", TextView::new("Ctrl + k"))
+                            .child("follow playing:", TextView::new("Ctrl + y"))
+                            .child("move to trash:", TextView::new("Ctrl + d, Enter")),
                     ),
                 ),
         ))
@@ -0,0 +1,271 @@
+use std::{path::PathBuf, time::Duration};
+
+use cursive::{
+    event::{Event, Key},
+    theme::Effect,
+    traits::View,
+    view::{Nameable, Resizable},
+    views::{Dialog, OnEventView},
+    Cursive, Printer,
+};
+use lofty::{ItemKey, Probe, TaggedFileExt};
+use unicode_width::UnicodeWidthStr;
+
+use crate::config::theme;
+
+use super::{player_view, stream::is_url, PlayerView};
+
+// The name used to find the lyrics view in the view stack.
+const NAME: &str = "lyrics";
+
+// The lyrics of a track, either with the time each line is sung at, from an
+// `.lrc` file, or as plain text.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Lyrics {
+    Synced(Vec<(Duration, String)>),
+    Plain(Vec<String>),
+}
+
+impl Lyrics {
+    // Parses `text` in the LRC format, where each line is preceded by one or
+    // more `[mm:ss.xx]` timestamps. Text without timestamps is plain lyrics.
+    pub fn parse(text: &str) -> Self {
+        let mut offset = 0;
+        let mut synced = vec![];
+
+        for line in text.lines() {
+            let mut rest = line.trim();
+            let mut times = vec![];
+
+            while let Some((tag, after)) =
+                rest.strip_prefix('[').and_then(|r| r.split_once(']'))
+            {
+                match parse_time(tag) {
+                    Some(time) => times.push(time),
+                    None => {
+                        if let Some(ms) = tag.strip_prefix("offset:") {
+                            offset = ms.trim().parse().unwrap_or(0);
+                        }
+                    }
+                }
+                rest = after;
+            }
+
+            for time in times {
+                synced.push((time, rest.trim().to_string()));
+            }
+        }
+
+        if synced.is_empty() {
+            return Lyrics::Plain(text.lines().map(|l| l.trim_end().to_string()).collect());
+        }
+
+        // A positive offset shows the lines earlier.
+        for (time, _) in synced.iter_mut() {
+            *time = match offset >= 0 {
+                true => time.saturating_sub(Duration::from_millis(offset as u64)),
+                false => *time + Duration::from_millis(offset.unsigned_abs()),
+            };
+        }
+        synced.sort_by_key(|(time, _)| *time);
+
+        Lyrics::Synced(synced)
+    }
+
+    // The index of the line being sung at `elapsed`, if any.
+    pub fn line_at(&self, elapsed: Duration) -> Option<usize> {
+        match self {
+            Lyrics::Synced(lines) => lines.iter().rposition(|(time, _)| *time <= elapsed),
+            Lyrics::Plain(_) => None,
+        }
+    }
+
+    fn lines(&self) -> Vec<&str> {
+        match self {
+            Lyrics::Synced(lines) => lines.iter().map(|(_, l)| l.as_str()).collect(),
+            Lyrics::Plain(lines) => lines.iter().map(|l| l.as_str()).collect(),
+        }
+    }
+}
+
+// The lyrics of the track at `path`, read from an `.lrc` file with the same
+// name, or else from the tags. Network streams have no lyrics.
+pub fn read(path: &PathBuf) -> Option<Lyrics> {
+    if is_url(path) {
+        return None;
+    }
+
+    if let Ok(text) = std::fs::read_to_string(path.with_extension("lrc")) {
+        return Some(Lyrics::parse(&text));
+    }
+
+    let tagged_file = Probe::open(path).ok()?.read().ok()?;
+    let tag = tagged_file.primary_tag().or(tagged_file.first_tag())?;
+    let text = tag.get_string(&ItemKey::Lyrics)?;
+
+    match text.trim().is_empty() {
+        true => None,
+        false => Some(Lyrics::parse(text)),
+    }
+}
+
+// Parses an `mm:ss`, `mm:ss.xx` or `mm:ss.xxx` timestamp.
+fn parse_time(tag: &str) -> Option<Duration> {
+    let (mins, secs) = tag.split_once(':')?;
+    let mins: u64 = mins.parse().ok()?;
+    let (secs, fraction) = secs.split_once('.').unwrap_or((secs, "0"));
+    let secs: u64 = secs.parse().ok()?;
+
+    if fraction.is_empty() || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    // Hundredths are most common, but some files use thousandths.
+    let millis = format!("{:0<3}", fraction)[..3].parse::<u64>().ok()?;
+
+    Some(Duration::from_millis((mins * 60 + secs) * 1000 + millis))
+}
+
+// Shows the lyrics of the current track over the player. Synced lyrics scroll
+// with playback and the current line is highlighted.
+pub struct LyricsView {
+    // The track the lyrics are for.
+    path: PathBuf,
+    // The lyrics of the track, if any.
+    lyrics: Option<Lyrics>,
+    // The elapsed time of the track.
+    elapsed: Duration,
+}
+
+impl LyricsView {
+    fn new(path: PathBuf, elapsed: Duration) -> Self {
+        Self {
+            lyrics: read(&path),
+            path,
+            elapsed,
+        }
+    }
+
+    // Follows the playback of the player, reading the lyrics of the new track
+    // when the track changes.
+    fn update(&mut self, path: PathBuf, elapsed: Duration) {
+        if path != self.path {
+            self.lyrics = read(&path);
+            self.path = path;
+        }
+        self.elapsed = elapsed;
+    }
+
+    // Loads the lyrics of the current track over the player. Playback isn't
+    // interrupted and the lyrics are closed with Esc.
+    pub fn load(siv: &mut Cursive) {
+        let current = siv.call_on_name(player_view::NAME, |v: &mut PlayerView| {
+            (v.player().path().to_owned(), v.player().elapsed())
+        });
+
+        if let Some((path, elapsed)) = current {
+            siv.add_layer(
+                OnEventView::new(
+                    Dialog::around(LyricsView::new(path, elapsed).with_name(NAME))
+                        .title("lyrics")
+                        .full_screen(),
+                )
+                .on_event(Event::Key(Key::Esc), |siv| {
+                    siv.pop_layer();
+                })
+                .on_event('y', |siv| {
+                    siv.pop_layer();
+                }),
+            )
+        }
+    }
+}
+
+impl View for LyricsView {
+    fn draw(&self, p: &Printer) {
+        let (w, h) = (p.size.x, p.size.y);
+
+        let lyrics = match &self.lyrics {
+            Some(lyrics) => lyrics,
+            None => {
+                let msg = "no lyrics";
+                p.with_color(theme::prompt(), |p| {
+                    p.print((w.saturating_sub(msg.width()) / 2, h / 2), msg)
+                });
+                return;
+            }
+        };
+
+        let lines = lyrics.lines();
+        let current = lyrics.line_at(self.elapsed);
+
+        // Synced lyrics keep the current line in the middle of the view.
+        let first = match (lyrics, current) {
+            (Lyrics::Synced(_), Some(i)) => i.saturating_sub(h / 2),
+            _ => 0,
+        };
+
+        for (row, (i, line)) in lines.iter().enumerate().skip(first).take(h).enumerate() {
+            let x = w.saturating_sub(line.width()) / 2;
+            match Some(i) == current {
+                true => p.with_color(theme::hl(), |p| {
+                    p.with_effect(Effect::Bold, |p| p.print((x, row), line))
+                }),
+                false => p.with_color(theme::fg(), |p| p.print((x, row), line)),
+            }
+        }
+    }
+}
+
+// Scrolls the lyrics, if shown, to the elapsed time of the player.
+pub fn update(siv: &mut Cursive) {
+    if siv.find_name::<LyricsView>(NAME).is_none() {
+        return;
+    }
+
+    let current = siv.call_on_name(player_view::NAME, |v: &mut PlayerView| {
+        (v.player().path().to_owned(), v.player().elapsed())
+    });
+
+    if let Some((path, elapsed)) = current {
+        siv.call_on_name(NAME, |v: &mut LyricsView| v.update(path, elapsed));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::find_assets_dir;
+
+    #[test]
+    fn test_line_at() {
+        let path = find_assets_dir().join("lyrics").join("test_lyrics.wav");
+        let lyrics = read(&path).expect("should read the .lrc file");
+
+        assert_eq!(lyrics.line_at(Duration::from_millis(500)), None);
+        assert_eq!(lyrics.line_at(Duration::from_secs(1)), Some(0));
+        assert_eq!(lyrics.line_at(Duration::from_millis(3499)), Some(1));
+        assert_eq!(lyrics.line_at(Duration::from_millis(3500)), Some(2));
+        assert_eq!(lyrics.line_at(Duration::from_secs(60)), Some(3));
+        assert_eq!(lyrics.lines()[2], "the second line");
+    }
+
+    #[test]
+    fn test_parse() {
+        let lyrics = Lyrics::parse("[ar:Someone]\n[00:05.00][00:01.5]chorus\n[00:03]verse\n");
+        assert_eq!(
+            lyrics,
+            Lyrics::Synced(vec![
+                (Duration::from_millis(1500), String::from("chorus")),
+                (Duration::from_secs(3), String::from("verse")),
+                (Duration::from_secs(5), String::from("chorus")),
+            ])
+        );
+
+        let lyrics = Lyrics::parse("[offset:500]\n[00:01.00]early\n");
+        assert_eq!(lyrics.line_at(Duration::from_millis(500)), Some(0));
+
+        let lyrics = Lyrics::parse("no timestamps\nhere");
+        assert_eq!(lyrics.line_at(Duration::from_secs(1)), None);
+        assert_eq!(lyrics.lines(), vec!["no timestamps", "here"]);
+    }
+}
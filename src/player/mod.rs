@@ -1,17 +1,62 @@
+#[cfg(feature = "tui")]
+pub mod album_settings;
 pub mod audio_file;
+#[cfg(feature = "tui")]
+pub mod audiobook;
+#[cfg(feature = "tui")]
+pub mod balance;
+#[cfg(feature = "tui")]
 pub mod builder;
+pub mod discs;
+#[cfg(feature = "tui")]
+pub mod header;
+#[cfg(feature = "tui")]
+pub mod hooks;
+#[cfg(feature = "tui")]
 pub mod keys_view;
+#[cfg(feature = "tui")]
+pub mod lyrics;
+#[cfg(feature = "tui")]
+pub mod now_playing;
+#[cfg(feature = "tui")]
 pub mod opts;
+#[cfg(all(target_os = "linux", feature = "output-monitor", feature = "tui"))]
+pub mod output_monitor;
+#[cfg(feature = "tui")]
 pub mod player;
+#[cfg(feature = "tui")]
 pub mod player_view;
+pub mod pls;
+#[cfg(feature = "tui")]
+pub mod prefetch;
+#[cfg(feature = "tui")]
+pub mod record;
+pub mod sidecar;
+#[cfg(feature = "tui")]
+pub mod sleep_inhibitor;
+pub mod splits;
+#[cfg(feature = "tui")]
 pub mod status;
+pub mod stream;
+#[cfg(feature = "tui")]
+pub mod terminal;
+pub mod tracks;
 
 pub use self::{
     audio_file::{valid_audio_ext, AudioFile},
+    stream::is_url,
+    tracks::{audio_files, AudioOpts},
+};
+
+#[cfg(feature = "tui")]
+pub use self::{
     builder::PlayerBuilder,
     keys_view::KeysView,
     opts::PlayerOpts,
     player::{run_automated, Player},
-    player_view::{previous_album, random_album, PlayerView},
+    player_view::{
+        input_trigger, on_playlist_end, previous_album, quit_if_idle, random_album, record_input,
+        update_fps, PlayerView,
+    },
     status::{BytesToStatus, PlayerStatus, StatusToBytes},
 };
@@ -0,0 +1,151 @@
+use std::{
+    fs,
+    io::Write,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use cursive::Cursive;
+
+use crate::config::args;
+use crate::fuzzy::ErrorView;
+
+use super::{player_view, AudioFile, PlayerStatus, PlayerView};
+
+lazy_static::lazy_static! {
+    // The line last written to the status file.
+    static ref LAST: Mutex<Option<String>> = Mutex::new(None);
+}
+
+// Whether or not a failed write has been reported. Only the first is.
+static REPORTED: AtomicBool = AtomicBool::new(false);
+
+// Writes the current track and status to the `--status-file` when either of
+// them changes. The first failure to write is reported.
+pub fn update(siv: &mut Cursive) {
+    let path = match args::status_file() {
+        Some(path) => path,
+        None => return,
+    };
+
+    let line = siv.call_on_name(player_view::NAME, |v: &mut PlayerView| {
+        let player = v.player();
+        let track = (player.index + 1, player.playlist.len());
+        expand(args::status_format(), player.file(), track, &player.status)
+    });
+
+    let line = match line {
+        Some(line) => line,
+        None => return,
+    };
+
+    let mut last = match LAST.lock() {
+        Ok(last) => last,
+        Err(_) => return,
+    };
+    if last.as_ref() == Some(&line) {
+        return;
+    }
+
+    // A line that couldn't be written isn't tried again until it changes.
+    if let Err(e) = write(&path, &line) {
+        if !REPORTED.swap(true, Ordering::Relaxed) {
+            let err = format!("could not write '{}'\n- `{}`", path.display(), e);
+            ErrorView::load(siv, anyhow::Error::msg(err));
+        }
+    }
+    *last = Some(line);
+}
+
+// Empties the status file, so that nothing is shown as playing once tap has
+// quit.
+pub fn clear() {
+    if let Some(path) = args::status_file().filter(|path| path.exists()) {
+        _ = write(&path, "");
+    }
+}
+
+// Replaces the `{status}`, `{artist}`, `{album}`, `{year}`, `{title}`,
+// `{track}`, `{tracks}` and `{path}` placeholders in `template` with the values
+// of `file`, the position of `file` in the playlist and the player status.
+fn expand(
+    template: &str,
+    file: &AudioFile,
+    track: (usize, usize),
+    status: &PlayerStatus,
+) -> String {
+    let status = match status {
+        PlayerStatus::Playing => "playing",
+        PlayerStatus::Paused => "paused",
+        PlayerStatus::Stopped => "stopped",
+    };
+    let year = file.year.map(|y| y.to_string()).unwrap_or_default();
+
+    template
+        .replace("{status}", status)
+        .replace("{artist}", &file.artist)
+        .replace("{album}", &file.album)
+        .replace("{year}", &year)
+        .replace("{title}", &file.title)
+        .replace("{track}", &track.0.to_string())
+        .replace("{tracks}", &track.1.to_string())
+        .replace("{path}", &file.path.to_string_lossy())
+}
+
+// Replaces the contents of `path` with `line`. The line is written to a file
+// next to it that is then renamed, so that readers never see part of a line.
+fn write(path: &Path, line: &str) -> Result<(), std::io::Error> {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(".tmp");
+    let temp = path.with_file_name(name);
+
+    let mut file = fs::File::create(&temp)?;
+    if !line.is_empty() {
+        writeln!(file, "{}", line)?;
+    }
+    file.sync_all()?;
+
+    fs::rename(&temp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_status_file_line() {
+        let file = AudioFile {
+            path: PathBuf::from("/music/a.mp3"),
+            title: String::from("So What"),
+            artist: String::from("Miles Davis"),
+            album: String::from("Kind of Blue"),
+            year: Some(1959),
+            disc: None,
+            track: 1,
+            duration: 60,
+            start: 0,
+        };
+        let dir = tempfile::tempdir().expect("create temp dir");
+        let path = dir.path().join("now-playing");
+
+        let line = expand(
+            "{status}: {artist} - {title} ({year}) {track}/{tracks}",
+            &file,
+            (1, 5),
+            &PlayerStatus::Playing,
+        );
+        write(&path, &line).expect("should write the status file");
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert_eq!(contents, "playing: Miles Davis - So What (1959) 1/5\n");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        write(&path, "").expect("should clear the status file");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+    }
+}
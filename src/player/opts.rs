@@ -9,6 +9,7 @@ pub struct PlayerOpts {
     pub volume: u8,
     pub is_muted: bool,
     pub showing_volume: bool,
+    pub showing_playlist: bool,
 }
 
 impl Default for PlayerOpts {
@@ -18,23 +19,25 @@ impl Default for PlayerOpts {
             volume: 100,
             is_muted: false,
             showing_volume: false,
+            showing_playlist: true,
         }
     }
 }
 
-impl Into<PlayerOpts> for (u8, u8, bool, bool) {
+impl Into<PlayerOpts> for (u8, u8, bool, bool, bool) {
     fn into(self) -> PlayerOpts {
         PlayerOpts {
             status: self.0.from_u8(),
             volume: self.1,
             is_muted: self.2,
             showing_volume: self.3,
+            showing_playlist: self.4,
         }
     }
 }
 
 impl IntoInner for PlayerOpts {
-    type T = (u8, u8, bool, bool);
+    type T = (u8, u8, bool, bool, bool);
 
     fn into_inner(self) -> Self::T {
         (
@@ -42,6 +45,7 @@ impl IntoInner for PlayerOpts {
             self.volume,
             self.is_muted,
             self.showing_volume,
+            self.showing_playlist,
         )
     }
 }
@@ -0,0 +1,96 @@
+use std::{
+    io::{BufRead, BufReader},
+    process::{Command, Stdio},
+    thread,
+};
+
+use cursive::{reexports::crossbeam_channel::Sender, Cursive};
+
+use super::{player_view, PlayerView};
+
+// Watches the default audio output and pauses the player when it changes; for
+// example, when unplugging headphones switches the output to the speakers. Uses
+// `pactl`, which works with both PulseAudio and PipeWire. Does nothing if the
+// audio server can't be reached.
+pub fn spawn(cb: Sender<Box<dyn FnOnce(&mut Cursive) + Send>>) {
+    thread::spawn(move || {
+        let mut output = match current_output() {
+            Some(output) => output,
+            None => return,
+        };
+
+        let mut child = match Command::new("pactl")
+            .arg("subscribe")
+            .env("LC_ALL", "C")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(_) => return,
+        };
+
+        let stdout = match child.stdout.take() {
+            Some(stdout) => stdout,
+            None => return,
+        };
+
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            // Changes to the default sink are reported on the server, and
+            // changes to the active port are reported on the sink.
+            if !line.contains("'change'") || !(line.contains("sink") || line.contains("server")) {
+                continue;
+            }
+
+            match current_output() {
+                Some(next) if next != output => output = next,
+                _ => continue,
+            }
+
+            let paused = cb.send(Box::new(|siv| {
+                if let Some(result) = siv.call_on_name(player_view::NAME, PlayerView::pause) {
+                    result.process(siv);
+                }
+            }));
+
+            // The app has quit.
+            if paused.is_err() {
+                break;
+            }
+        }
+
+        _ = child.kill();
+    });
+}
+
+// The name and active port of the default sink.
+fn current_output() -> Option<(String, String)> {
+    let sink = pactl(&["get-default-sink"])?.trim().to_string();
+    let mut is_default = false;
+
+    for line in pactl(&["list", "sinks"])?.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix("Name: ") {
+            is_default = name == sink;
+        } else if let Some(port) = line.strip_prefix("Active Port: ") {
+            if is_default {
+                return Some((sink, port.to_string()));
+            }
+        }
+    }
+
+    Some((sink, String::new()))
+}
+
+// Runs `pactl` with `args`, returning its output.
+fn pactl(args: &[&str]) -> Option<String> {
+    let output = Command::new("pactl")
+        .args(args)
+        .env("LC_ALL", "C")
+        .output()
+        .ok()?;
+
+    match output.status.success() {
+        true => String::from_utf8(output.stdout).ok(),
+        false => None,
+    }
+}
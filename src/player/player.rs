@@ -1,24 +1,75 @@
 use std::{
     cmp::{max, min},
-    fs::File,
-    io::BufReader,
+    collections::{HashMap, HashSet},
+    io::{Cursor, Write},
     path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Mutex,
+    },
+    thread,
     time::{Duration, Instant},
 };
 
 use anyhow::bail;
 use cursive::XY;
 use expiring_bool::ExpiringBool;
+use rand::{seq::SliceRandom, thread_rng};
 use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink};
 
+use crate::config::args::{self, EndOfPlaylist, Weighting};
+use crate::data::{blocklist, bookmarks, gain_data, history, persistent_data};
+use crate::error::TapError;
 use crate::utils;
 
-use super::{valid_audio_ext, AudioFile, PlayerOpts, PlayerStatus, StatusToBytes};
+use super::{
+    album_settings,
+    balance::{Balance, Pan},
+    prefetch::Prefetch,
+    record::{self, Recorder, Tee},
+    stream::Input,
+    tracks::{audio_files, decode},
+    valid_audio_ext, AudioFile, PlayerOpts, PlayerStatus, StatusToBytes,
+};
 
-pub type PlayerResult = Result<(Player, bool, XY<usize>), anyhow::Error>;
+pub type PlayerResult = Result<(Player, PlayerOpts, XY<usize>), anyhow::Error>;
 
 const SEEK_TIME: Duration = Duration::from_secs(10);
 
+// The step used by `step_forward` and `step_backward` with `--audiobook`.
+const AUDIOBOOK_SEEK_TIME: Duration = Duration::from_secs(30);
+
+// How long a track plays for before the tracks around it are read ahead.
+const PREFETCH_DELAY: Duration = Duration::from_secs(2);
+
+// The most number keys that are combined into one number. Further keys are
+// ignored, so that the number can't overflow.
+const MAX_NUM_KEYS: usize = 6;
+
+// How long number keys are kept for. A number key pressed after this starts a
+// new number rather than adding to the old one.
+const NUM_KEYS_TIMEOUT: Duration = Duration::from_secs(3);
+
+// The percentages the volume is changed by with `]` and `[`, and with
+// `Alt` + `]` and `Alt` + `[`.
+pub const VOLUME_STEP: u8 = 10;
+pub const FINE_VOLUME_STEP: u8 = 1;
+
+// Whether albums are shuffled, kept for the session so that it carries over to
+// the next album.
+static ALBUM_SHUFFLE: AtomicBool = AtomicBool::new(false);
+
+// What plays once the current track has finished.
+#[derive(Debug, PartialEq)]
+pub enum Upcoming {
+    // The track at this index of the playlist.
+    Track(usize),
+    // A random track of the playlist.
+    Random,
+    // A random album.
+    RandomAlbum,
+}
+
 pub struct Player {
     // The list of audio files for the player.
     pub playlist: Vec<AudioFile>,
@@ -26,60 +77,176 @@ pub struct Player {
     pub index: usize,
     // The index of the previous audio file, used with standalone player.
     pub previous: usize,
-    // The current volume as a percentage, in range 0..=120.
+    // The current volume as a percentage, in range 0..=max_volume.
     pub volume: u8,
+    // The highest volume as a percentage, set with `--max-volume`.
+    pub max_volume: u8,
     // Whether the player is muted or not.
     pub is_muted: bool,
     // Whether or not the next track will be selected randomly.
     pub is_randomized: bool,
+    // Whether or not a random album is played, from its first track, once the
+    // last track has finished. Each album plays in order.
+    pub is_album_shuffled: bool,
+    // The order that the tracks are played in when randomized, and the position
+    // of the next track in it. Every track plays once before any is repeated.
+    shuffle_order: Vec<usize>,
+    shuffle_position: usize,
     // Whether or not the next track is queued.
     pub next_track_queued: bool,
     // Whether the player is playing, paused or stopped.
     pub status: PlayerStatus,
     // The list of numbers from last keyboard input.
     pub num_keys: Vec<usize>,
+    // The instant the last number key was pressed.
+    num_key_pressed: Instant,
     // Whether or not a double-tap event was registered.
     pub timer_bool: ExpiringBool,
+    // The paths of the tracks in the playlist that are blocklisted. They are
+    // skipped in sequence and never chosen at random.
+    pub blocked: HashSet<PathBuf>,
+    // The time stepped forward or backward with `.` and `,`.
+    pub seek_step: Duration,
+    // What to do when the last track has finished.
+    pub end_of_playlist: EndOfPlaylist,
+    // Whether or not the last track has finished since this was last taken.
+    is_finished: bool,
+    // Whether or not a track has played to its end since the player was
+    // created, which counts the album as played.
+    pub has_played: bool,
+    // How long before the end of the current track the next track is queued.
+    // The next track is queued as soon as the current track starts if `None`.
+    pub prebuffer: Option<Duration>,
+    // The gain of each track that has been analyzed with `--analyze`, in dB,
+    // from the `.tap_gain` file of its album.
+    gains: HashMap<PathBuf, f64>,
+    // The silence between tracks, from `--gap` or the `.tap` file of the album.
+    // Tracks follow each other gaplessly if zero.
+    pub gap: Duration,
+    // The instant the gap after the last track started, while it lasts.
+    gap_started: Option<Instant>,
+    // The tracks before and after the current track, read ahead with `--prefetch`.
+    prefetch: Option<Prefetch>,
     // The instant that playback started or resumed.
     last_started: Instant,
     // The instant that the player was paused. Reset when player is stopped.
     last_elapsed: Duration,
+    // The balance of the output, which applies to every source appended to the sink.
+    balance: Balance,
+    // The recording made with `--record`, which every source appended to the
+    // sink is written to.
+    recorder: Option<Recorder>,
+    // The file of the source playing in the sink, if any. Tracks split from
+    // this file are played by seeking within the source.
+    loaded: Option<PathBuf>,
+    // The tracks that couldn't be decoded, and were skipped, since this was
+    // last taken.
+    skipped: Vec<PathBuf>,
     // Handle to audio sink.
     sink: Sink,
-    // The open flow of audio data.
-    _stream: OutputStream,
-    // Handle to stream.
-    _stream_handle: OutputStreamHandle,
+    // The open flow of audio data and its handle. `None` when the sink
+    // plays into the null output used by tests.
+    _output: Option<(OutputStream, OutputStreamHandle)>,
 }
 
 impl Player {
     pub fn new(path: PathBuf, index: usize, opts: PlayerOpts, is_randomized: bool) -> PlayerResult {
-        let (playlist, size) = playlist(&path)?;
-        let (_stream, _stream_handle) = OutputStream::try_default()?;
+        let (_stream, _stream_handle) = match OutputStream::try_default() {
+            Ok(output) => output,
+            Err(e) => bail!(TapError::AudioDevice(format!(
+                "could not open the audio device\n- `{}`",
+                e
+            ))),
+        };
         let sink = Sink::try_new(&_stream_handle)?;
+        let output = Some((_stream, _stream_handle));
+
+        Self::with_sink(path, index, opts, is_randomized, sink, output)
+    }
+
+    fn with_sink(
+        path: PathBuf,
+        index: usize,
+        opts: PlayerOpts,
+        is_randomized: bool,
+        sink: Sink,
+        _output: Option<(OutputStream, OutputStreamHandle)>,
+    ) -> PlayerResult {
+        let (playlist, size) = playlist(&path)?;
+        // The playlist may have changed since `index` was recorded.
+        let index = if index < playlist.len() { index } else { 0 };
+        // The settings of the album take precedence over the arguments.
+        let album = match path.is_dir() {
+            true => album_settings::read(&path),
+            false => album_settings::read(path.parent().unwrap_or(&path)),
+        };
 
         let mut player = Self {
             last_started: Instant::now(),
             last_elapsed: Duration::ZERO,
             previous: 0,
             num_keys: vec![],
+            num_key_pressed: Instant::now(),
             next_track_queued: false,
             timer_bool: ExpiringBool::new(false, Duration::from_millis(500)),
-            status: opts.status,
-            volume: opts.volume,
+            prebuffer: args::prebuffer(),
+            gains: gain_data::track_gains(&playlist),
+            gap: album.gap.or(args::gap()).unwrap_or_default(),
+            gap_started: None,
+            prefetch: args::prefetch().then(Prefetch::default),
+            seek_step: match args::audiobook() {
+                true => AUDIOBOOK_SEEK_TIME,
+                false => SEEK_TIME,
+            },
+            end_of_playlist: args::end_of_playlist(),
+            is_finished: false,
+            has_played: false,
+            blocked: blocklist::blocked(&playlist),
+            status: opts.status.to_owned(),
+            volume: opts.volume.min(args::max_volume()),
+            max_volume: args::max_volume(),
             is_muted: opts.is_muted,
             index,
             playlist,
             is_randomized,
+            is_album_shuffled: ALBUM_SHUFFLE.load(Ordering::Relaxed),
+            shuffle_order: vec![],
+            shuffle_position: 0,
+            balance: Balance::shared(),
+            recorder: record::shared(),
+            loaded: None,
+            skipped: vec![],
             sink,
-            _stream,
-            _stream_handle,
+            _output,
+        };
+
+        // Start from the first track that isn't blocklisted.
+        if player.is_blocked(player.index) {
+            if let Some(index) = player.next_index(player.index) {
+                player.index = index;
+            }
+        }
+
+        // Audiobooks continue from the track and position they were left at,
+        // unless another track was asked for.
+        let bookmark = match args::audiobook() && index == 0 {
+            true => player.bookmark(),
+            false => None,
         };
+        if let Some((index, _)) = bookmark {
+            player.index = index;
+        }
 
         player.set_volume();
         player.set_playback();
 
-        Ok((player, opts.showing_volume, size))
+        if let Some((_, elapsed)) = bookmark {
+            if player.status != PlayerStatus::Stopped && !elapsed.is_zero() {
+                player.seek_to_time(elapsed);
+            }
+        }
+
+        Ok((player, opts, size))
     }
 
     // The current audio file.
@@ -92,6 +259,26 @@ impl Player {
         &self.file().path
     }
 
+    // The directory of the first track, which names the book with `--audiobook`.
+    pub fn dir(&self) -> PathBuf {
+        let first = &self.playlist[0].path;
+        let dir = first.parent().unwrap_or(first);
+
+        // The tracks of merged discs are in the subdirectories of the album.
+        match self.playlist.last() {
+            Some(last) if !last.path.starts_with(dir) => dir.parent().unwrap_or(dir),
+            _ => dir,
+        }
+        .to_path_buf()
+    }
+
+    // The index and position of the track that the book was left at, if any.
+    fn bookmark(&self) -> Option<(usize, Duration)> {
+        let (track, secs) = bookmarks::get(&self.dir())?;
+        let index = self.playlist.iter().position(|f| f.path == track)?;
+        Some((index, Duration::from_secs(secs)))
+    }
+
     // Resumes a paused sink and records the start time.
     pub fn resume(&mut self) {
         self.sink.play();
@@ -101,7 +288,7 @@ impl Player {
 
     // Pauses the sink and records the elapsed time.
     pub fn pause(&mut self) {
-        self.last_elapsed = self.elapsed();
+        self.last_elapsed = self.position();
         self.sink.pause();
         self.status = PlayerStatus::Paused;
     }
@@ -111,6 +298,10 @@ impl Player {
         self.clear();
         if self.status != PlayerStatus::Stopped {
             self.sink.stop();
+            if let Some(recorder) = &self.recorder {
+                recorder.flush();
+            }
+            self.loaded = None;
             self.status = PlayerStatus::Stopped;
             self.last_elapsed = Duration::ZERO;
         }
@@ -119,9 +310,8 @@ impl Player {
 
     // Decodes and appends `file` to the sink, starts playback and records start time.
     pub fn play(&mut self) {
-        if let Ok(source) = decode(self.path()) {
-            self.sink.append(source);
-            self.sink.play();
+        self.sink.play();
+        if self.append_current() {
             self.status = PlayerStatus::Playing;
             self.last_started = Instant::now();
         } else {
@@ -139,6 +329,18 @@ impl Player {
         self.status.to_u8()
     }
 
+    // Adds `digit` to the number being entered with the number keys. Digits
+    // left from a number entered a while ago are dropped first.
+    pub fn push_num_key(&mut self, digit: usize) {
+        if self.num_key_pressed.elapsed() > NUM_KEYS_TIMEOUT {
+            self.num_keys.clear();
+        }
+        if self.num_keys.len() < MAX_NUM_KEYS {
+            self.num_keys.push(digit);
+        }
+        self.num_key_pressed = Instant::now();
+    }
+
     // Play the track selected from keyboard input.
     pub fn play_key_selection(&mut self) {
         // Play first track when called in quick succession.
@@ -151,62 +353,137 @@ impl Player {
         // Play the track from number key inputs.
         } else {
             let track_number = utils::concatenate(&self.num_keys) as u32;
-            if let Some(index) = self.playlist.iter().position(|f| f.track == track_number) {
-                self.play_index(index.clone());
+            if let Some(index) = self.track_index(track_number) {
+                self.play_index(index);
             } else {
                 self.clear();
             }
         }
     }
 
+    // Plays the track at the position in the playlist given by the number key
+    // inputs as a percentage, so that 50 plays the track halfway through.
+    pub fn play_percent_selection(&mut self) {
+        if self.num_keys.is_empty() {
+            return;
+        }
+        let percent = utils::concatenate(&self.num_keys);
+        self.play_index(percent_index(percent, self.playlist.len()));
+    }
+
+    // The track number displayed for the track at `index`.
+    pub fn track_number(&self, index: usize) -> u32 {
+        match args::sequential() {
+            true => index as u32 + 1,
+            false => self.playlist[index].track,
+        }
+    }
+
+    // The index of the track displayed with `track_number`, if any.
+    fn track_index(&self, track_number: u32) -> Option<usize> {
+        (0..self.playlist.len()).find(|&i| self.track_number(i) == track_number)
+    }
+
     // Play the track selected from mouse input.
     pub fn play_mouse_selected(&mut self, selected: usize) {
         self.play_index(selected);
     }
 
+    // Play the first track in the current playlist.
+    pub fn play_first_track(&mut self) {
+        self.play_index(0);
+    }
+
     // Play the last track in the current playlist.
     pub fn play_last_track(&mut self) {
         self.play_index(self.last_index());
     }
 
+    // Play a random track from the current playlist, once. Unlike `is_randomized`,
+    // playback continues in order from the new track.
+    pub fn play_random_track(&mut self) {
+        // Choose any track other than the current one.
+        let indices = self
+            .playable()
+            .into_iter()
+            .filter(|&i| i != self.index)
+            .collect::<Vec<_>>();
+
+        let index = match indices.is_empty() {
+            true => self.index,
+            false => indices[utils::random(0..indices.len())],
+        };
+        self.play_index(index);
+    }
+
     // Skip to next track in the playlist.
     pub fn next(&mut self) {
         self.clear();
-        if self.index < self.last_index() {
-            self.index += 1;
-            self.set_playback();
-        } else {
-            self.stop();
+        match self.next_index(self.index) {
+            Some(index) => {
+                self.index = index;
+                self.set_playback();
+            }
+            None => {
+                self.stop();
+            }
         }
     }
 
     // Skip to previous track in the playlist.
     pub fn previous(&mut self) {
         self.clear();
-        if self.index > 0 {
-            self.index -= 1;
+        if let Some(index) = self.previous_index(self.index) {
+            self.index = index;
         }
         self.set_playback();
     }
 
-    // Increase volume by 10%, to maximum of 120%.
-    pub fn increase_volume(&mut self) -> u8 {
-        if self.volume < 120 {
-            self.volume += 10;
-            if !self.is_muted {
-                self.sink.set_volume(self.volume as f32 / 100.0);
+    // Adds the current track to the blocklist, or removes it. The current track
+    // keeps playing. Returns whether the track is now blocklisted.
+    pub fn toggle_blocked(&mut self) -> Result<bool, anyhow::Error> {
+        let path = self.path().to_owned();
+        let is_blocked = blocklist::toggle(&path)?;
+
+        match is_blocked {
+            true => self.blocked.insert(path),
+            false => self.blocked.remove(&path),
+        };
+        Ok(is_blocked)
+    }
+
+    // Whether or not the track at `index` is blocklisted.
+    pub fn is_blocked(&self, index: usize) -> bool {
+        self.blocked.contains(&self.playlist[index].path)
+    }
+
+    // Restarts the current track from the beginning. A paused track stays paused
+    // and a stopped track starts playing.
+    pub fn restart(&mut self) {
+        match self.status {
+            PlayerStatus::Stopped => self.play_index(self.index),
+            _ => {
+                self.clear();
+                self.set_playback();
             }
         }
+    }
+
+    // Increase volume by `step`%, to maximum of `max_volume`. Volumes above 100%
+    // amplify the audio, which can clip.
+    pub fn increase_volume(&mut self, step: u8) -> u8 {
+        if self.volume < self.max_volume {
+            self.volume = self.volume.saturating_add(step).min(self.max_volume);
+            self.set_volume();
+        }
         self.volume
     }
 
-    // Decrease volume by 10%, to minimum of 0%.
-    pub fn decrease_volume(&mut self) -> u8 {
+    // Decrease volume by `step`%, to minimum of 0%.
+    pub fn decrease_volume(&mut self, step: u8) -> u8 {
         if self.volume > 0 {
-            self.volume -= 10;
-            if !self.is_muted {
-                self.sink.set_volume(self.volume as f32 / 100.0);
-            }
+            self.volume = self.volume.saturating_sub(step);
+            self.set_volume();
         }
         self.volume
     }
@@ -215,14 +492,28 @@ impl Player {
     // this change. Returns the updated `is_muted`.
     pub fn toggle_mute(&mut self) -> bool {
         self.is_muted ^= true;
-        self.sink.set_volume(if self.is_muted {
-            0.0
-        } else {
-            self.volume as f32 / 100.0
-        });
+        self.set_volume();
         self.is_muted
     }
 
+    // The balance of the output, from -1.0 for only the left channel to 1.0 for
+    // only the right channel.
+    pub fn balance(&self) -> f32 {
+        self.balance.get()
+    }
+
+    // Shifts the balance by `step`, towards the right channel if positive. The
+    // balance applies to the track that is playing and carries over to the
+    // next album. Returns the new balance.
+    pub fn shift_balance(&mut self, step: f32) -> f32 {
+        self.balance.set(self.balance.get() + step)
+    }
+
+    // Centers the balance.
+    pub fn reset_balance(&mut self) -> f32 {
+        self.balance.set(0.0)
+    }
+
     // Toggles `is_randomized` and removes the current next
     // track from the sink when `is_randomized` is true.
     pub fn toggle_randomization(&mut self) -> bool {
@@ -231,20 +522,84 @@ impl Player {
         if self.is_randomized && self.sink.len() > 1 {
             self.sink.pop();
         }
+        // The current track counts as played in the new order.
+        self.shuffle();
         self.is_randomized
     }
 
+    // Toggles `is_album_shuffled` for this and later players.
+    pub fn toggle_album_shuffle(&mut self) -> bool {
+        self.is_album_shuffled ^= true;
+        ALBUM_SHUFFLE.store(self.is_album_shuffled, Ordering::Relaxed);
+        self.is_album_shuffled
+    }
+
+    // What to do once the last track has finished. Shuffling albums takes
+    // precedence over `--end-of-playlist`.
+    pub fn end_action(&self) -> EndOfPlaylist {
+        match self.is_album_shuffled {
+            true => EndOfPlaylist::RandomAlbum,
+            false => self.end_of_playlist,
+        }
+    }
+
+    // What plays once the current track has finished, if anything. This follows
+    // the same order as `poll` and `finish`, so a queued track is reported.
+    pub fn upcoming(&self) -> Option<Upcoming> {
+        if self.is_randomized {
+            return Some(Upcoming::Random);
+        }
+        if let Some(index) = self.next_index(self.index) {
+            return Some(Upcoming::Track(index));
+        }
+
+        match self.end_action() {
+            EndOfPlaylist::Repeat => self.playable().first().map(|&i| Upcoming::Track(i)),
+            EndOfPlaylist::RandomAlbum => Some(Upcoming::RandomAlbum),
+            _ => None,
+        }
+    }
+
+    // Moves the current track one place up or down the playlist, if possible.
+    // The track after the current track changes, so any queued next track
+    // is removed from the sink.
+    pub fn move_track(&mut self, up: bool) {
+        let index = match up {
+            true if self.index > 0 => self.index - 1,
+            false if self.index < self.last_index() => self.index + 1,
+            _ => return,
+        };
+
+        self.playlist.swap(self.index, index);
+        self.index = index;
+
+        if self.next_track_queued && self.sink.len() > 1 {
+            self.sink.pop();
+        }
+        self.next_track_queued = false;
+    }
+
     // Tries to get the path of a random player and a random index for that player.
     pub fn randomized(paths: &Vec<PathBuf>) -> Option<(PathBuf, usize)> {
         if paths.len() == 0 {
             return None;
         }
+        let weights = weights(paths, args::random_weighting());
         let mut count = 0;
         while count < 10 {
-            let target = utils::random(0..paths.len());
+            let target = utils::weighted_random(&weights);
             let path = paths[target].to_owned();
             if let Ok((playlist, _)) = playlist(&path) {
-                let index = utils::random(0..playlist.len());
+                let blocked = blocklist::blocked(&playlist);
+                let indices = (0..playlist.len())
+                    .filter(|&i| !blocked.contains(&playlist[i].path))
+                    .collect::<Vec<_>>();
+
+                if indices.is_empty() {
+                    count += 1;
+                    continue;
+                }
+                let index = indices[utils::random(0..indices.len())];
                 return Some((path, index));
             } else {
                 count += 1;
@@ -265,14 +620,10 @@ impl Player {
         }
     }
 
-    // Sets the current track in a playlist randomly.
+    // Sets the current track in a playlist to the next in the shuffled order.
     pub fn next_random(&mut self) {
-        if self.playlist.len() > 1 {
-            let mut index = utils::random(0..self.playlist.len());
-            if index == self.index {
-                // A second chance to find a new index.
-                index = utils::random(0..self.playlist.len());
-            }
+        if self.playlist.len() > 1 && !self.playable().is_empty() {
+            let index = self.next_shuffled();
             self.previous = self.index;
             self.index = index;
             self.next_track_queued = false;
@@ -280,6 +631,40 @@ impl Player {
         }
     }
 
+    // The next track in the shuffled order. The tracks are shuffled again once
+    // they have all played, with the current track moved to the end so that it
+    // doesn't play twice in a row.
+    fn next_shuffled(&mut self) -> usize {
+        // Tracks blocklisted since the order was shuffled are skipped.
+        while let Some(&index) = self.shuffle_order.get(self.shuffle_position) {
+            self.shuffle_position += 1;
+            if !self.is_blocked(index) {
+                return index;
+            }
+        }
+
+        let mut order = self.playable();
+        order.shuffle(&mut thread_rng());
+        if let Some(i) = order.iter().position(|&i| i == self.index) {
+            let last = order.len() - 1;
+            order.swap(i, last);
+        }
+
+        self.shuffle_order = order;
+        self.shuffle_position = 1;
+        self.shuffle_order[0]
+    }
+
+    // Shuffles the tracks other than the current track, which is treated as
+    // having played.
+    fn shuffle(&mut self) {
+        let current = self.index;
+        self.shuffle_order = self.playable();
+        self.shuffle_order.retain(|&i| i != current);
+        self.shuffle_order.shuffle(&mut thread_rng());
+        self.shuffle_position = 0;
+    }
+
     // Seeks the playback to the input time in seconds.
     pub fn seek_to_sec(&mut self) {
         if !self.num_keys.is_empty() {
@@ -298,16 +683,56 @@ impl Player {
         }
     }
 
-    // Increments the playback position by SEEK_TIME.
+    // Seeks the playback to the input time in seconds before the end of the
+    // track, or to the start if the input is longer than the track.
+    pub fn seek_from_end(&mut self) {
+        let duration = self.file().duration as u64;
+        if !self.num_keys.is_empty() && duration > 0 {
+            let secs = utils::concatenate(&self.num_keys) as u64;
+            let seek_time = Duration::new(duration.saturating_sub(secs), 0);
+            self.seek_to_time(seek_time)
+        } else {
+            self.num_keys.clear();
+        }
+    }
+
+    // Increments the playback position by `seek_step`.
     pub fn step_forward(&mut self) {
         let elapsed = self.elapsed();
-        self.seek_forward(SEEK_TIME, elapsed);
+        self.seek_forward(self.seek_step, elapsed);
     }
 
-    // Decrements the playback position by SEEK_TIME.
+    // Decrements the playback position by `seek_step`.
     pub fn step_backward(&mut self) {
         let elapsed = self.elapsed();
-        self.seek_backward(SEEK_TIME, elapsed);
+        self.seek_backward(self.seek_step, elapsed);
+    }
+
+    // Pauses the player while seeking with the mouse. A stopped player loads the
+    // current track paused, so that there is a source to seek within. Returns
+    // whether or not playback should resume once the seek is done.
+    pub fn pause_for_seek(&mut self) -> bool {
+        let is_playing = self.is_playing();
+
+        if self.status == PlayerStatus::Stopped {
+            if self.append_current() {
+                self.sink.pause();
+                self.status = PlayerStatus::Paused;
+            }
+        } else {
+            self.pause();
+        }
+
+        is_playing
+    }
+
+    // Seeks to `seek_time` once seeking with the mouse is done, then stays
+    // paused unless `resume` is set.
+    pub fn finish_seek(&mut self, seek_time: Duration, resume: bool) {
+        self.seek_to_time(seek_time);
+        if !resume && self.is_playing() {
+            self.pause();
+        }
     }
 
     // Seeks the playback to the provided seek_time, in seconds.
@@ -331,11 +756,12 @@ impl Player {
             self.play_or_pause();
         }
         let duration = Duration::new(self.file().duration as u64, 0);
-        if duration - elapsed < time + Duration::new(0, 500) {
+        // Tracks of unknown length advance when the sink empties.
+        if !duration.is_zero() && duration.saturating_sub(elapsed) < time + Duration::new(0, 500) {
             self.next()
         } else {
             let future = elapsed + time;
-            if let Ok(_) = self.sink.try_seek(future) {
+            if let Ok(_) = self.sink.try_seek(self.start() + future) {
                 self.last_started -= time;
             }
         }
@@ -348,11 +774,10 @@ impl Player {
             self.play_or_pause();
         }
         if elapsed < time + Duration::new(0, 500) {
-            self.stop();
-            self.play();
+            self.play_index(self.index);
         } else {
             let past = elapsed - time;
-            if let Ok(_) = self.sink.try_seek(past) {
+            if let Ok(_) = self.sink.try_seek(self.start() + past) {
                 if self.last_elapsed == Duration::ZERO {
                     self.last_started += time;
                 } else if self.last_elapsed >= time {
@@ -366,9 +791,16 @@ impl Player {
         }
     }
 
-    // The time elapsed during playback.
+    // The time elapsed during playback of the current track.
     #[inline]
     pub fn elapsed(&self) -> Duration {
+        self.position().saturating_sub(self.start())
+    }
+
+    // The playback position within the file of the current track, which is
+    // ahead of `elapsed` for the tracks split from a file.
+    #[inline]
+    fn position(&self) -> Duration {
         self.last_elapsed
             + if self.is_playing() {
                 Instant::now() - self.last_started
@@ -383,6 +815,8 @@ impl Player {
     // If playback is not randomized and there is a succeeding
     // track in the playlist, the next track is queued before the
     // current track completes. This is to ensure gapless playback.
+    // The track is queued immediately, or `prebuffer` seconds before
+    // the current track completes, if set.
     //
     // If playback is randomized, the next track is queued when
     // the current track completes.
@@ -395,9 +829,33 @@ impl Player {
     // 2 => the player is unchanged.
     #[inline]
     pub fn poll(&mut self) -> usize {
+        let polled = self.poll_sink();
+        // The player only moves on by itself once a track has finished.
+        if polled == 1 || self.is_finished || (self.is_randomized && self.next_track_queued) {
+            self.has_played = true;
+        }
+        // A queued track plays at the gain of the track before it until now.
+        if polled == 1 {
+            self.set_volume();
+        }
+        polled
+    }
+
+    fn poll_sink(&mut self) -> usize {
         if !self.is_playing() {
             return 0;
         }
+        if self.prefetch.is_some() && self.elapsed() > PREFETCH_DELAY {
+            self.update_prefetch();
+        }
+        // A track split from a file ends before the source does, so the next
+        // track is found here instead of being queued.
+        if let Some(end) = self.split_end() {
+            return match self.position() < end {
+                true => 2,
+                false => self.finish_split(end),
+            };
+        }
         if self.is_randomized {
             if self.sink.empty() {
                 self.next_track_queued = true;
@@ -406,23 +864,102 @@ impl Player {
             if self.next_track_queued {
                 self.last_started = Instant::now();
                 self.last_elapsed = Duration::ZERO;
-                self.index += 1;
+                self.index = self.next_index(self.index).unwrap_or(self.index);
+                self.loaded = Some(self.path().to_owned());
                 self.next_track_queued = false;
                 return 1;
-            } else if let Some(next) = self.playlist.get(self.index + 1) {
+            } else if !self.should_prebuffer() {
+                return 2;
+            } else if let Some(next) = self.next_index(self.index).map(|i| &self.playlist[i]) {
+                // A track split from the middle of a file can't follow gaplessly,
+                // so it is played once the current source has finished.
+                if next.start > 0 {
+                    return 2;
+                }
                 if let Ok(source) = decode(&next.path) {
-                    self.sink.append(source);
+                    self.sink.append(self.output(source));
                     self.next_track_queued = true;
                 } else {
                     self.next();
                 }
             }
         } else if self.sink.empty() {
-            self.stop();
+            if self.in_gap() {
+                return 2;
+            }
+            return match self.next_index(self.index) {
+                Some(_) => {
+                    self.next();
+                    1
+                }
+                None => self.finish(),
+            };
         }
         2
     }
 
+    // The end of the current track within its file, in seconds, if it is split
+    // from a file and another track of the file follows it.
+    fn split_end(&self) -> Option<Duration> {
+        let file = self.file();
+        self.playlist
+            .iter()
+            .any(|f| f.path == file.path && f.start > file.start)
+            .then(|| Duration::from_secs((file.start + file.duration) as u64))
+    }
+
+    // Moves on from a track split from a file once it has reached `end`. The
+    // track that follows it in the file keeps playing from the same source.
+    fn finish_split(&mut self, end: Duration) -> usize {
+        if self.is_randomized {
+            self.next_track_queued = true;
+            return 2;
+        }
+
+        match self.next_index(self.index) {
+            Some(next) if self.playlist[next].path == *self.path() => {
+                let follows = self.playlist[next].start as u64 == end.as_secs();
+                self.index = next;
+                if !follows {
+                    self.seek_to_start();
+                }
+                1
+            }
+            Some(_) => {
+                self.next();
+                1
+            }
+            None => self.finish(),
+        }
+    }
+
+    // Stops the player once the last track has finished, or plays the playlist
+    // again with `EndOfPlaylist::Repeat`. The other actions need the app and
+    // are taken by `player_view::on_playlist_end`.
+    fn finish(&mut self) -> usize {
+        if self.end_action() == EndOfPlaylist::Repeat {
+            if let Some(&index) = self.playable().first() {
+                self.play_index(index);
+                return 1;
+            }
+        }
+
+        self.stop();
+        self.is_finished = true;
+        2
+    }
+
+    // Whether or not the last track has finished since this was last called.
+    pub fn take_finished(&mut self) -> bool {
+        std::mem::take(&mut self.is_finished)
+    }
+
+    // The tracks that were skipped since this was last called, as they
+    // couldn't be decoded.
+    pub fn take_skipped(&mut self) -> Vec<PathBuf> {
+        std::mem::take(&mut self.skipped)
+    }
+
     // Stdout for the automated player.
     pub fn stdout(&self) -> (String, usize) {
         let file = self.file();
@@ -437,11 +974,54 @@ impl Player {
         (line, length)
     }
 
+    // Whether or not the next track should be queued; that is, when fewer
+    // than `prebuffer` seconds of the current track remain. With a gap between
+    // tracks the next track is only played once the current track has ended.
+    fn should_prebuffer(&self) -> bool {
+        if !self.gap.is_zero() {
+            return false;
+        }
+        match self.prebuffer {
+            Some(lead) => {
+                let duration = Duration::from_secs(self.file().duration as u64);
+                duration.saturating_sub(self.elapsed()) <= lead
+            }
+            None => true,
+        }
+    }
+
+    // Whether the gap after the track that has ended is still to be waited out.
+    // The gap starts when this is first called after the track ends.
+    fn in_gap(&mut self) -> bool {
+        if self.gap.is_zero() {
+            return false;
+        }
+        let started = *self.gap_started.get_or_insert_with(Instant::now);
+        started.elapsed() < self.gap
+    }
+
     // Whether the player is playing or not.
     fn is_playing(&self) -> bool {
         self.status == PlayerStatus::Playing
     }
 
+    // The index of the first track after `index` that isn't blocklisted, if any.
+    fn next_index(&self, index: usize) -> Option<usize> {
+        (index + 1..self.playlist.len()).find(|&i| !self.is_blocked(i))
+    }
+
+    // The index of the last track before `index` that isn't blocklisted, if any.
+    fn previous_index(&self, index: usize) -> Option<usize> {
+        (0..index).rev().find(|&i| !self.is_blocked(i))
+    }
+
+    // The indices of the tracks that aren't blocklisted.
+    fn playable(&self) -> Vec<usize> {
+        (0..self.playlist.len())
+            .filter(|&i| !self.is_blocked(i))
+            .collect()
+    }
+
     // The index of the last track in the playlist.
     fn last_index(&self) -> usize {
         self.playlist.len() - 1
@@ -450,134 +1030,245 @@ impl Player {
     // Removes the stored keyboard inputs.
     fn clear(&mut self) {
         self.next_track_queued = false;
+        self.gap_started = None;
         self.num_keys.clear();
         self.timer_bool.set_false();
     }
 
     // Play the track at `index` in playlist.
     fn play_index(&mut self, index: usize) {
-        self.stop();
-        self.index = index;
         self.clear();
+        self.index = index;
+        if self.status == PlayerStatus::Paused {
+            self.resume();
+        }
+        if self.is_playing() && self.seek_within_source() {
+            return;
+        }
+        self.stop();
         self.play();
     }
 
     // Convenience method to maintain `status` in new player instances.
     fn set_playback(&mut self) {
+        if self.is_playing() && self.seek_within_source() {
+            return;
+        }
+
         self.sink.stop();
+        self.loaded = None;
         self.last_elapsed = Duration::ZERO;
 
         if self.status != PlayerStatus::Stopped {
-            if let Ok(source) = decode(self.path()) {
-                self.sink.append(source);
-                self.last_started = Instant::now();
-            }
+            self.append_current();
             if self.status == PlayerStatus::Paused {
                 self.sink.pause()
             }
         }
     }
 
-    // Apply volume setting to the audio sink.
+    // Wraps `source` to be played with the balance, and recorded with `--record`.
+    fn output(&self, source: Decoder<Input>) -> Tee<Pan<Decoder<Input>>> {
+        Tee::new(Pan::new(source, self.balance.clone()), self.recorder.clone())
+    }
+
+    // Decodes the current track and appends it to the sink, seeking to where
+    // the track starts in its file. Returns whether the track was decoded.
+    fn append_current(&mut self) -> bool {
+        let source = match self.source() {
+            Ok(source) => source,
+            Err(_) => {
+                self.skipped.push(self.path().to_owned());
+                return false;
+            }
+        };
+
+        self.sink.append(self.output(source));
+        self.set_volume();
+        self.loaded = Some(self.path().to_owned());
+        self.last_elapsed = Duration::ZERO;
+        self.last_started = Instant::now();
+        if self.file().start > 0 {
+            self.seek_to_start();
+        }
+        true
+    }
+
+    // Plays the current track from its start by seeking within the source in
+    // the sink, if the source is the file of the track. Returns false if the
+    // track needs to be decoded instead.
+    fn seek_within_source(&mut self) -> bool {
+        if self.loaded.as_ref() != Some(self.path()) || self.sink.empty() {
+            return false;
+        }
+        // A queued next track follows the end of the file, not this track.
+        if self.sink.len() > 1 {
+            self.sink.pop();
+        }
+        self.seek_to_start()
+    }
+
+    // Seeks to where the current track starts in its file.
+    fn seek_to_start(&mut self) -> bool {
+        let start = self.start();
+        match self.sink.try_seek(start) {
+            Ok(_) => {
+                self.last_elapsed = start;
+                self.last_started = Instant::now();
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    // Where the current track starts in its file.
+    fn start(&self) -> Duration {
+        Duration::from_secs(self.file().start as u64)
+    }
+
+    // Reads the tracks before and after the current track ahead. The next track
+    // of a randomized player isn't known, so nothing is read ahead.
+    fn update_prefetch(&mut self) {
+        let paths: Vec<&PathBuf> = match self.is_randomized {
+            true => vec![],
+            false => [self.next_index(self.index), self.previous_index(self.index)]
+                .into_iter()
+                .flatten()
+                .map(|i| &self.playlist[i].path)
+                .filter(|&path| path != self.path())
+                .collect(),
+        };
+
+        if let Some(prefetch) = self.prefetch.as_mut() {
+            prefetch.update(&paths);
+        }
+    }
+
+    // Decodes the current track, from memory if it was read ahead.
+    fn source(&mut self) -> Result<Decoder<Input>, anyhow::Error> {
+        let path = self.path().to_owned();
+        match self.prefetch.as_mut().and_then(|p| p.take(&path)) {
+            Some(bytes) => match Decoder::new(Input::Memory(Cursor::new(bytes))) {
+                Ok(s) => Ok(s),
+                Err(_) => bail!("could not decode '{}'", path.display()),
+            },
+            None => decode(&path),
+        }
+    }
+
+    // Apply volume setting to the audio sink, with the gain of the current track.
     fn set_volume(&mut self) {
         if self.is_muted {
             self.sink.set_volume(0.0)
         } else {
-            self.sink.set_volume(self.volume as f32 / 100.0);
+            self.sink.set_volume(gain(self.volume) * self.track_gain());
+        }
+    }
+
+    // The factor that the current track is amplified by to normalize its
+    // loudness. One if the track hasn't been analyzed. Quiet tracks aren't
+    // boosted, since the peaks of a boosted track could clip.
+    fn track_gain(&self) -> f32 {
+        match self.gains.get(self.path()) {
+            Some(db) => 10f32.powf(db.min(0.0) as f32 / 20.0),
+            None => 1.0,
         }
     }
 }
 
 // Run an automated player in the command line without the TUI.
 pub fn run_automated(path: PathBuf) -> Result<(), anyhow::Error> {
-    use std::io::{stdin, stdout, Write};
-    use std::thread::sleep;
+    use std::io::{stdin, stdout};
 
-    let (mut player, _, _) = super::PlayerBuilder::new(path)?;
-    let (mut line, mut length) = player.stdout();
+    // Resume from the last track played in `path`, if requested.
+    let index = match args::resume() {
+        true => persistent_data::automated_progress(&path).unwrap_or(0),
+        false => 0,
+    };
 
-    print!("{}", line);
-    stdout().flush()?;
+    let (mut player, _, _) = Player::new(path.to_owned(), index, PlayerOpts::default(), false)?;
+
+    // Quit on `enter` key press, unless `--no-quit-on-enter` is set. The end
+    // of the input, such as when it is piped from a command that has finished,
+    // doesn't quit.
+    let (quit, on_quit) = mpsc::channel();
+    if args::quit_on_enter() {
+        thread::spawn(move || {
+            let mut input = String::new();
+            if stdin().read_line(&mut input).map_or(false, |n| n > 0) {
+                _ = quit.send(());
+            }
+        });
+    }
+
+    // Record the track to resume from with `--continue`.
+    let index = automate(&mut player, &on_quit, &mut stdout())?;
+    _ = persistent_data::set_automated_progress(&path, index);
+
+    Ok(())
+}
+
+// Plays the playlist of `player` to the end, or until `quit` receives, writing
+// the current track to `out`. Tracks that can't be decoded are skipped with a
+// notice. Returns the index of the track that was playing on quit, or the first
+// track if the playlist has completed.
+fn automate(
+    player: &mut Player,
+    quit: &mpsc::Receiver<()>,
+    out: &mut impl Write,
+) -> Result<usize, anyhow::Error> {
+    // The track that is shown, and the length of its line.
+    let mut shown = None;
+    let mut length = 0;
 
     loop {
-        // Exit on `enter` key press.
-        let mut input = String::new();
-        if let Ok(_) = stdin().read_line(&mut input) {
-            return Ok(());
+        if quit.try_recv().is_ok() {
+            return Ok(player.index);
         }
 
-        match player.poll() {
-            0 => {
-                println!();
-                return Ok(());
-            }
-            1 => {
-                // Print the number of spaces required to clear the previous line.
-                print!("\r{: <1$}", "", length);
-                (line, length) = player.stdout();
-                print!("\r{}", line);
-                stdout().flush()?;
-            }
-            _ => sleep(Duration::from_millis(60)),
-        }
-    }
-}
+        let polled = player.poll();
 
-// Returns the playlist and required size for the player on success.
-pub fn playlist(path: &PathBuf) -> Result<(Vec<AudioFile>, XY<usize>), anyhow::Error> {
-    // A value used to set an appropriate width for the player view.
-    let mut width = 0;
-    // The error we get if we can't create an audio file.
-    let mut error: Option<anyhow::Error> = None;
-
-    // Collect the potential audio file paths.
-    let paths = match path.read_dir() {
-        Ok(path) => path
-            .filter_map(|entry| entry.ok())
-            .map(|entry| entry.path())
-            .filter(|entry| entry.is_file())
-            .collect::<Vec<_>>(),
-        Err(_) => {
-            vec![path.to_owned()]
+        for path in player.take_skipped() {
+            // Print the number of spaces required to clear the previous line.
+            write!(out, "\r{: <1$}\r", "", length)?;
+            writeln!(out, "[tap player]: skipping '{}'", path.display())?;
+            (shown, length) = (None, 0);
         }
-    };
 
-    if paths.is_empty() {
-        bail!("'{}' is empty", path.display())
-    }
+        if polled == 0 {
+            writeln!(out)?;
+            return Ok(0);
+        }
 
-    // The audio files comprising our playlist.
-    let mut list = {
-        paths
-            .into_iter()
-            .filter(|path| valid_audio_ext(path))
-            .filter_map(|path| match AudioFile::new(path) {
-                Ok(file) => {
-                    width = max(width, file.title.len());
-                    Some(file)
-                }
-                Err(e) => {
-                    if error.is_none() {
-                        error = Some(e)
-                    }
-                    None
-                }
-            })
-    }
-    .collect::<Vec<AudioFile>>();
+        if shown != Some(player.index) {
+            write!(out, "\r{: <1$}", "", length)?;
+            let (line, len) = player.stdout();
+            write!(out, "\r{}", line)?;
+            length = len;
+            out.flush()?;
+            shown = Some(player.index);
+        }
 
-    // Check the first track can be decoded and calculate the required width.
-    if let Some(first) = list.first() {
-        width = max(width, first.album.len() + first.artist.len() + 1);
-        _ = decode(&first.path)?;
-    } else {
-        match error {
-            Some(e) => bail!(e),
-            None => bail!("no audio files detected in '{}'", path.display()),
+        if polled != 1 {
+            thread::sleep(Duration::from_millis(60));
         }
     }
+}
 
-    list.sort();
+// The gain applied to the sink for `volume`, a percentage.
+fn gain(volume: u8) -> f32 {
+    volume as f32 / 100.0
+}
+
+// Returns the playlist and required size for the player on success.
+pub fn playlist(path: &PathBuf) -> Result<(Vec<AudioFile>, XY<usize>), anyhow::Error> {
+    let list = audio_files(path, &args::audio_opts())?;
+
+    // A value used to set an appropriate width for the player view.
+    let width = list.iter().fold(
+        list[0].album.len() + list[0].artist.len() + 1,
+        |width, file| max(width, file.title.len()),
+    );
 
     let size = XY {
         x: max(width + 19, 53),
@@ -587,22 +1278,633 @@ pub fn playlist(path: &PathBuf) -> Result<(Vec<AudioFile>, XY<usize>), anyhow::E
     Ok((list, size))
 }
 
-pub fn decode(path: &PathBuf) -> Result<Decoder<BufReader<File>>, anyhow::Error> {
-    let source = match File::open(path.as_path()) {
-        Ok(inner) => match Decoder::new(BufReader::new(inner)) {
-            Ok(s) => s,
-            Err(_) => bail!("could not decode '{}'", path.display()),
-        },
-        Err(_) => bail!("could not open '{}'", path.display()),
-    };
-    Ok(source)
+// The index of the track `percent` of the way through a playlist of `len`
+// tracks. Percentages over 100 play the last track.
+fn percent_index(percent: usize, len: usize) -> usize {
+    (min(percent, 100) * len / 100).min(len.saturating_sub(1))
+}
+
+// The weight of each album in `paths` when choosing an album at random.
+fn weights(paths: &[PathBuf], weighting: Weighting) -> Vec<u64> {
+    match weighting {
+        Weighting::Uniform => vec![1; paths.len()],
+        Weighting::TrackCount => paths.iter().map(|p| track_count(p).max(1)).collect(),
+        Weighting::LeastRecent => {
+            let now = history::now();
+            paths
+                .iter()
+                .map(|p| match history::last_played(p) {
+                    Some(secs) => (now.saturating_sub(secs) / 3600 + 1).min(MAX_AGE_HOURS),
+                    None => MAX_AGE_HOURS,
+                })
+                .collect()
+        }
+    }
+}
+
+// The number of hours since an album was played after which its weight with
+// `--random-weighting least-recent` stops growing.
+const MAX_AGE_HOURS: u64 = 30 * 24;
+
+lazy_static::lazy_static! {
+    // The number of audio files in each directory, counted on first use.
+    static ref TRACK_COUNTS: Mutex<HashMap<PathBuf, u64>> = Mutex::new(HashMap::new());
+}
+
+// The number of audio files in `dir`, without reading the files.
+fn track_count(dir: &PathBuf) -> u64 {
+    let mut counts = TRACK_COUNTS.lock().expect("should not be poisoned");
+    *counts.entry(dir.to_owned()).or_insert_with(|| match dir.read_dir() {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.is_file() && valid_audio_ext(p))
+            .count() as u64,
+        Err(_) => 0,
+    })
 }
 
 #[cfg(test)]
 mod tests {
+    use std::thread::sleep;
+
+    use rodio::{queue::SourcesQueueOutput, Source};
+
     use super::*;
+    use crate::player::tracks;
     use crate::utils::{create_working_dir, find_assets_dir};
 
+    // How often the null output consumes samples.
+    const TICK: Duration = Duration::from_millis(10);
+
+    // Creates a player for `path` that plays into a null output, so that
+    // playback can be tested without an audio device.
+    fn headless(path: PathBuf) -> Player {
+        let (sink, output) = Sink::new_idle();
+        std::thread::spawn(move || null_output(output));
+
+        let (player, _, _) = Player::with_sink(path, 0, PlayerOpts::default(), false, sink, None)
+            .expect("should create a headless player");
+        player
+    }
+
+    // Consumes the samples from the sink in real time, like an audio device
+    // would. The thread runs until the test process exits.
+    fn null_output(mut output: SourcesQueueOutput<f32>) {
+        loop {
+            let samples = output.sample_rate() as usize * output.channels() as usize / 100;
+            for _ in 0..samples {
+                if output.next().is_none() {
+                    return;
+                }
+            }
+            sleep(TICK);
+        }
+    }
+
+    // Polls the player until `done` returns true, failing after a few seconds.
+    fn poll_until(player: &mut Player, done: impl Fn(&Player) -> bool) {
+        let start = Instant::now();
+        while !done(player) {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "timed out while polling the player"
+            );
+            player.poll();
+            sleep(TICK);
+        }
+    }
+
+    fn assets_player() -> Player {
+        headless(find_assets_dir())
+    }
+
+    fn long_player() -> Player {
+        headless(find_assets_dir().join("long").join("test_wav_long.wav"))
+    }
+
+    #[test]
+    fn test_headless_player_starts_playing() {
+        let player = assets_player();
+
+        assert_eq!(player.status, PlayerStatus::Playing);
+        assert_eq!(player.index, 0);
+        assert_eq!(player.volume, 100);
+        assert!(!player.is_muted);
+        assert!(!player.next_track_queued);
+    }
+
+    #[test]
+    fn test_play_or_pause_transitions() {
+        let mut player = long_player();
+
+        assert_eq!(player.play_or_pause(), PlayerStatus::Paused.to_u8());
+        assert_eq!(player.status, PlayerStatus::Paused);
+        assert_eq!(player.play_or_pause(), PlayerStatus::Playing.to_u8());
+        assert_eq!(player.status, PlayerStatus::Playing);
+
+        assert_eq!(player.stop(), PlayerStatus::Stopped.to_u8());
+        assert_eq!(player.elapsed(), Duration::ZERO);
+        assert_eq!(player.play_or_pause(), PlayerStatus::Playing.to_u8());
+        assert_eq!(player.index, 0);
+    }
+
+    #[test]
+    fn test_elapsed_is_frozen_while_paused() {
+        let mut player = long_player();
+        sleep(Duration::from_millis(50));
+        player.pause();
+
+        let paused_at = player.elapsed();
+        assert!(paused_at >= Duration::from_millis(50));
+        sleep(Duration::from_millis(50));
+        assert_eq!(player.elapsed(), paused_at);
+
+        player.resume();
+        sleep(Duration::from_millis(20));
+        assert!(player.elapsed() > paused_at);
+    }
+
+    #[test]
+    fn test_volume_is_clamped() {
+        let mut player = assets_player();
+
+        assert_eq!(player.increase_volume(VOLUME_STEP), 110);
+        assert_eq!(player.increase_volume(VOLUME_STEP), 120);
+        assert_eq!(player.increase_volume(VOLUME_STEP), 120);
+
+        for _ in 0..15 {
+            player.decrease_volume(VOLUME_STEP);
+        }
+        assert_eq!(player.volume, 0);
+        assert_eq!(player.increase_volume(VOLUME_STEP), 10);
+    }
+
+    #[test]
+    fn test_volume_boost_is_clamped() {
+        let mut player = assets_player();
+        player.max_volume = 125;
+
+        assert_eq!(player.increase_volume(VOLUME_STEP), 110);
+        assert_eq!(player.increase_volume(VOLUME_STEP), 120);
+        assert_eq!(player.increase_volume(VOLUME_STEP), 125);
+        assert_eq!(player.increase_volume(VOLUME_STEP), 125);
+        assert_eq!(player.decrease_volume(VOLUME_STEP), 115);
+    }
+
+    #[test]
+    fn test_fine_volume_step() {
+        let mut player = assets_player();
+
+        assert_eq!(player.increase_volume(FINE_VOLUME_STEP), 101);
+        assert_eq!(player.sink.volume(), 1.01);
+        assert_eq!(player.decrease_volume(FINE_VOLUME_STEP), 100);
+        assert_eq!(player.decrease_volume(VOLUME_STEP), 90);
+        assert_eq!(player.sink.volume(), 0.9);
+    }
+
+    #[test]
+    fn test_toggle_mute_restores_gain() {
+        let mut player = assets_player();
+        player.increase_volume(VOLUME_STEP);
+        player.increase_volume(FINE_VOLUME_STEP);
+
+        assert!(player.toggle_mute());
+        assert_eq!(player.sink.volume(), 0.0);
+        assert!(!player.toggle_mute());
+        assert_eq!(player.volume, 111);
+        assert_eq!(player.sink.volume(), gain(111));
+    }
+
+    #[test]
+    fn test_toggle_mute_keeps_volume() {
+        let mut player = assets_player();
+        player.decrease_volume(VOLUME_STEP);
+
+        assert!(player.toggle_mute());
+        assert_eq!(player.volume, 90);
+        assert_eq!(player.increase_volume(VOLUME_STEP), 100);
+        assert!(!player.toggle_mute());
+        assert_eq!(player.volume, 100);
+    }
+
+    #[test]
+    fn test_next_and_previous_track() {
+        let mut player = assets_player();
+        player.pause();
+
+        player.previous();
+        assert_eq!(player.index, 0);
+
+        player.next();
+        player.next();
+        assert_eq!(player.index, 2);
+        assert_eq!(player.status, PlayerStatus::Paused);
+
+        player.previous();
+        assert_eq!(player.index, 1);
+
+        // Skipping past the last track stops the player.
+        player.play_last_track();
+        player.next();
+        assert_eq!(player.index, player.playlist.len() - 1);
+        assert_eq!(player.status, PlayerStatus::Stopped);
+    }
+
+    #[test]
+    fn test_play_key_selection() {
+        let mut player = assets_player();
+        let track = player.playlist[3].track as usize;
+        player.num_keys = track.to_string().chars().map(|c| c as usize - 48).collect();
+
+        player.play_key_selection();
+        assert_eq!(player.track_number(player.index), track as u32);
+        assert!(player.num_keys.is_empty());
+        assert_eq!(player.status, PlayerStatus::Playing);
+    }
+
+    #[test]
+    fn test_upcoming() {
+        let mut player = assets_player();
+        assert_eq!(player.upcoming(), Some(Upcoming::Track(1)));
+
+        player.move_track(false);
+        assert_eq!(player.index, 1);
+        assert_eq!(player.upcoming(), Some(Upcoming::Track(2)));
+
+        player.play_last_track();
+        assert_eq!(player.upcoming(), None);
+        player.end_of_playlist = EndOfPlaylist::Repeat;
+        assert_eq!(player.upcoming(), Some(Upcoming::Track(0)));
+        player.end_of_playlist = EndOfPlaylist::RandomAlbum;
+        assert_eq!(player.upcoming(), Some(Upcoming::RandomAlbum));
+
+        player.toggle_randomization();
+        assert_eq!(player.upcoming(), Some(Upcoming::Random));
+    }
+
+    #[test]
+    fn test_percent_index() {
+        assert_eq!(percent_index(0, 200), 0);
+        assert_eq!(percent_index(50, 200), 100);
+        assert_eq!(percent_index(99, 200), 198);
+        assert_eq!(percent_index(100, 200), 199);
+        assert_eq!(percent_index(250, 200), 199);
+        assert_eq!(percent_index(50, 3), 1);
+        assert_eq!(percent_index(100, 1), 0);
+
+        let mut player = assets_player();
+        player.num_keys = vec![1, 0, 0];
+        player.play_percent_selection();
+        assert_eq!(player.index, player.playlist.len() - 1);
+        assert!(player.num_keys.is_empty());
+    }
+
+    #[test]
+    fn test_long_number_input_is_capped() {
+        let mut player = long_player();
+        for _ in 0..40 {
+            player.push_num_key(9);
+        }
+        assert_eq!(player.num_keys, vec![9; MAX_NUM_KEYS]);
+
+        // Seeking past the end of the track moves on from it.
+        player.seek_to_min();
+        assert!(player.num_keys.is_empty());
+
+        for _ in 0..40 {
+            player.push_num_key(9);
+        }
+        player.play_percent_selection();
+        assert_eq!(player.index, player.playlist.len() - 1);
+
+        // Digits entered a while ago don't add to a new number.
+        player.push_num_key(4);
+        player.num_key_pressed -= NUM_KEYS_TIMEOUT * 2;
+        player.push_num_key(2);
+        assert_eq!(player.num_keys, vec![2]);
+    }
+
+    #[test]
+    fn test_play_first_track_restarts_album() {
+        let mut player = assets_player();
+        player.play_last_track();
+        player.pause();
+
+        player.play_first_track();
+        assert_eq!(player.index, 0);
+        assert_eq!(player.status, PlayerStatus::Playing);
+        assert!(player.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_play_random_track_changes_track() {
+        let mut player = assets_player();
+
+        for _ in 0..10 {
+            let previous = player.index;
+            player.play_random_track();
+            assert_ne!(player.index, previous);
+        }
+    }
+
+    #[test]
+    fn test_move_track() {
+        let mut player = assets_player();
+        let first = player.file().path.to_owned();
+
+        player.move_track(true);
+        assert_eq!(player.index, 0);
+
+        player.move_track(false);
+        assert_eq!(player.index, 1);
+        assert_eq!(player.playlist[1].path, first);
+        assert!(!player.next_track_queued);
+
+        player.play_last_track();
+        player.move_track(false);
+        assert_eq!(player.index, player.playlist.len() - 1);
+    }
+
+    #[test]
+    fn test_step_uses_seek_step() {
+        let mut player = long_player();
+        player.seek_to_time(Duration::from_secs(11));
+        player.step_backward();
+        assert!(player.elapsed() >= Duration::from_secs(1));
+
+        // The longer audiobook step goes back past the start, which restarts
+        // the track.
+        player.seek_step = AUDIOBOOK_SEEK_TIME;
+        player.seek_to_time(Duration::from_secs(11));
+        player.step_backward();
+        assert!(player.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_mouse_seek_while_stopped_stays_paused() {
+        let mut player = long_player();
+        player.stop();
+
+        assert!(!player.pause_for_seek());
+        assert_eq!(player.status, PlayerStatus::Paused);
+
+        player.finish_seek(Duration::from_secs(5), false);
+        assert_eq!(player.status, PlayerStatus::Paused);
+        let elapsed = player.elapsed();
+        assert!(elapsed >= Duration::from_secs(5) && elapsed < Duration::from_secs(6));
+
+        // Seeking while playing resumes once done.
+        player.resume();
+        assert!(player.pause_for_seek());
+        player.finish_seek(Duration::from_secs(2), true);
+        assert_eq!(player.status, PlayerStatus::Playing);
+    }
+
+    #[test]
+    fn test_seek_to_time() {
+        let mut player = long_player();
+
+        player.seek_to_time(Duration::from_secs(5));
+        let elapsed = player.elapsed();
+        assert!(elapsed >= Duration::from_secs(5) && elapsed < Duration::from_secs(6));
+        assert_eq!(player.status, PlayerStatus::Playing);
+
+        player.seek_to_time(Duration::from_secs(2));
+        let elapsed = player.elapsed();
+        assert!(elapsed >= Duration::from_secs(2) && elapsed < Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_seek_resumes_paused_player() {
+        let mut player = long_player();
+        player.pause();
+
+        player.step_forward();
+        assert_eq!(player.status, PlayerStatus::Playing);
+        assert!(player.elapsed() >= SEEK_TIME);
+
+        // Stepping back past the start restarts the track.
+        player.seek_to_time(Duration::from_secs(3));
+        player.step_backward();
+        assert!(player.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_restart_keeps_status() {
+        let mut player = long_player();
+        player.seek_to_time(Duration::from_secs(3));
+
+        player.restart();
+        assert!(player.elapsed() < Duration::from_millis(500));
+        assert_eq!(player.status, PlayerStatus::Playing);
+
+        player.seek_to_time(Duration::from_secs(3));
+        player.pause();
+        player.restart();
+        assert_eq!(player.elapsed(), Duration::ZERO);
+        assert_eq!(player.status, PlayerStatus::Paused);
+
+        player.stop();
+        player.restart();
+        assert!(player.elapsed() < Duration::from_millis(500));
+        assert_eq!(player.status, PlayerStatus::Playing);
+        assert_eq!(player.index, 0);
+    }
+
+    #[test]
+    fn test_seek_past_the_end_stops_single_track() {
+        let mut player = long_player();
+
+        player.seek_to_time(Duration::from_secs(60));
+        assert_eq!(player.status, PlayerStatus::Stopped);
+    }
+
+    #[test]
+    fn test_seek_from_end() {
+        let mut player = long_player();
+        player.num_keys = vec![4];
+
+        player.seek_from_end();
+        let elapsed = player.elapsed();
+        assert!(elapsed >= Duration::from_secs(8) && elapsed < Duration::from_secs(9));
+        assert!(player.num_keys.is_empty());
+    }
+
+    #[test]
+    fn test_poll_queues_and_advances_to_next_track() {
+        let root = create_working_dir(
+            &[],
+            &[
+                ("long.wav", "long/test_wav_long.wav"),
+                ("short.wav", "test_wav_audio.wav"),
+            ],
+            &[],
+        )
+        .expect("create temp dir");
+        let mut player = headless(root.path().to_path_buf());
+
+        assert_eq!(player.poll(), 2);
+        assert!(player.next_track_queued);
+        assert_eq!(player.index, 0);
+
+        // Skip to the last second of the first track.
+        player.seek_to_time(Duration::from_secs(11));
+        poll_until(&mut player, |p| p.index == 1);
+        assert_eq!(player.status, PlayerStatus::Playing);
+        assert!(!player.next_track_queued);
+        assert!(player.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_poll_skips_blocked_track() {
+        let root = create_working_dir(
+            &[],
+            &[
+                ("long.wav", "long/test_wav_long.wav"),
+                ("short_a.wav", "test_wav_audio.wav"),
+                ("short_b.wav", "test_wav_audio.wav"),
+            ],
+            &[],
+        )
+        .expect("create temp dir");
+        let mut player = headless(root.path().to_path_buf());
+        let middle = player.playlist[1].path.to_owned();
+        player.blocked.insert(middle);
+
+        // Skip to the last second of the first track.
+        player.seek_to_time(Duration::from_secs(11));
+        poll_until(&mut player, |p| {
+            assert_ne!(p.index, 1, "the blocked track should be skipped");
+            p.index == 2
+        });
+        assert_eq!(player.status, PlayerStatus::Playing);
+
+        player.previous();
+        assert_eq!(player.index, 0);
+        player.next();
+        assert_eq!(player.index, 2);
+    }
+
+    #[test]
+    fn test_poll_stops_at_end_of_playlist() {
+        let mut player = headless(find_assets_dir().join("test_wav_audio.wav"));
+
+        poll_until(&mut player, |p| p.status == PlayerStatus::Stopped);
+        assert_eq!(player.index, 0);
+        assert_eq!(player.poll(), 0);
+        assert!(player.take_finished());
+        assert!(!player.take_finished());
+    }
+
+    #[test]
+    fn test_poll_repeats_playlist() {
+        let mut player = headless(find_assets_dir().join("test_wav_audio.wav"));
+        player.end_of_playlist = EndOfPlaylist::Repeat;
+
+        let start = Instant::now();
+        while player.poll() != 1 {
+            assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+            assert_eq!(player.status, PlayerStatus::Playing);
+            sleep(TICK);
+        }
+
+        assert_eq!(player.index, 0);
+        assert_eq!(player.status, PlayerStatus::Playing);
+        assert!(player.elapsed() < Duration::from_secs(1));
+        assert!(!player.take_finished());
+    }
+
+    #[test]
+    fn test_album_shuffle_finishes_instead_of_repeating() {
+        let mut player = headless(find_assets_dir().join("test_wav_audio.wav"));
+        player.end_of_playlist = EndOfPlaylist::Repeat;
+        player.is_album_shuffled = true;
+
+        poll_until(&mut player, |p| p.status == PlayerStatus::Stopped);
+        assert!(player.take_finished());
+        assert_eq!(player.end_action(), EndOfPlaylist::RandomAlbum);
+    }
+
+    // Reads the tracks around the current track ahead and waits for the next
+    // track to be read.
+    fn prefetched_player() -> (Player, PathBuf) {
+        let mut player = assets_player();
+        player.prefetch = Some(Prefetch::default());
+        player.update_prefetch();
+
+        let next = player.playlist[player.next_index(0).unwrap()].path.to_owned();
+        let start = Instant::now();
+        while !player.prefetch.as_ref().unwrap().is_ready(&next) {
+            assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+            sleep(TICK);
+        }
+        (player, next)
+    }
+
+    #[test]
+    fn test_prefetched_track_is_played() {
+        let (mut player, next) = prefetched_player();
+
+        player.next();
+        assert_eq!(player.path(), &next);
+        assert_eq!(player.status, PlayerStatus::Playing);
+        assert!(!player.prefetch.as_ref().unwrap().is_ready(&next));
+        assert!(!player.sink.empty());
+    }
+
+    #[test]
+    fn test_prefetch_is_dropped_when_randomized() {
+        let (mut player, next) = prefetched_player();
+
+        player.toggle_randomization();
+        player.update_prefetch();
+        assert!(!player.prefetch.as_ref().unwrap().is_ready(&next));
+    }
+
+    #[test]
+    fn test_poll_ignores_paused_player() {
+        let mut player = assets_player();
+        player.pause();
+
+        assert_eq!(player.poll(), 0);
+        assert!(!player.next_track_queued);
+    }
+
+    #[test]
+    fn test_shuffle_plays_each_track_once() {
+        let mut player = assets_player();
+        let len = player.playable().len();
+        assert!(len > 2);
+
+        player.toggle_randomization();
+
+        for _ in 0..2 {
+            let mut played = vec![player.index];
+            for _ in 1..len {
+                player.next_random();
+                played.push(player.index);
+            }
+
+            played.sort();
+            assert_eq!(played, player.playable());
+
+            // The next round doesn't start with the track that ended the last.
+            let last = player.index;
+            player.next_random();
+            assert_ne!(player.index, last);
+        }
+    }
+
+    #[test]
+    fn test_randomized_player_waits_for_end_of_track() {
+        let mut player = assets_player();
+        player.toggle_randomization();
+
+        assert_eq!(player.poll(), 2);
+        poll_until(&mut player, |p| p.next_track_queued);
+        assert_eq!(player.index, 0);
+    }
+
     #[test]
     fn test_playlist_mp3_success() {
         let root = find_assets_dir().join("test_mp3_audio.mp3");
@@ -643,6 +1945,17 @@ mod tests {
         assert_eq!(playlist[0].title, "test_audio_ogg");
     }
 
+    #[test]
+    fn test_audio_file_unknown_duration() {
+        let path = find_assets_dir()
+            .join("no_duration")
+            .join("test_wav_no_duration.wav");
+        let file = AudioFile::new(path).expect("should read a file without a duration");
+
+        assert_eq!(file.title, "test_audio_wav");
+        assert_eq!(file.duration, 0);
+    }
+
     #[test]
     fn test_playlist_assets_length() {
         let root = find_assets_dir();
@@ -678,4 +1991,199 @@ mod tests {
             "Providing the path to an empty directory should yield an error"
         );
     }
+
+    #[test]
+    fn test_track_count_weighting_prefers_larger_albums() {
+        let root = create_working_dir(
+            &["small", "large"],
+            &[],
+            &[
+                "small/1.mp3",
+                "large/1.mp3",
+                "large/2.mp3",
+                "large/3.mp3",
+                "large/4.mp3",
+                "large/5.mp3",
+                "large/6.mp3",
+                "large/7.mp3",
+                "large/8.mp3",
+                "large/9.mp3",
+                "large/cover.jpg",
+            ],
+        )
+        .unwrap();
+        let paths = vec![root.path().join("small"), root.path().join("large")];
+
+        assert_eq!(weights(&paths, Weighting::Uniform), vec![1, 1]);
+        let weights = weights(&paths, Weighting::TrackCount);
+        assert_eq!(weights, vec![1, 9]);
+
+        let draws = 2000;
+        let large = (0..draws)
+            .filter(|_| utils::weighted_random(&weights) == 1)
+            .count();
+        // Expected to be 90%, with a wide margin so the test isn't flaky.
+        assert!(large > draws * 3 / 4, "large album drawn {large} times");
+    }
+
+    // A player for a 12 second file split into three 4 second tracks.
+    fn split_player() -> (tempfile::TempDir, Player) {
+        let root = create_working_dir(&[], &[("mix.wav", "long/test_wav_long.wav")], &[]).unwrap();
+        std::fs::write(root.path().join("mix.splits"), "0:04 Second\n0:08 Third\n").unwrap();
+        let player = headless(root.path().to_path_buf());
+        (root, player)
+    }
+
+    #[test]
+    fn test_split_file_is_played_as_tracks() {
+        let (root, player) = split_player();
+        let file = AudioFile::new(root.path().join("mix.wav")).unwrap();
+
+        let tracks = player
+            .playlist
+            .iter()
+            .map(|f| (f.track, f.start, f.duration))
+            .collect::<Vec<_>>();
+        assert_eq!(tracks, vec![(1, 0, 4), (2, 4, 4), (3, 8, 4)]);
+        // The part before the first split point keeps the title of the file.
+        assert_eq!(player.playlist[0].title, file.title);
+        assert_eq!(player.playlist[1].title, "Second");
+    }
+
+    #[test]
+    fn test_split_tracks_are_played_without_decoding() {
+        let (_root, mut player) = split_player();
+        poll_until(&mut player, |p| p.elapsed() > Duration::from_millis(500));
+        let before = tracks::decoded();
+
+        player.next();
+        assert_eq!(player.index, 1);
+        assert!(player.elapsed() < Duration::from_millis(500));
+
+        player.play_last_track();
+        assert_eq!(player.index, 2);
+        player.previous();
+        player.previous();
+        assert_eq!(player.index, 0);
+        assert_eq!(player.status, PlayerStatus::Playing);
+
+        assert_eq!(
+            tracks::decoded(),
+            before,
+            "the file should not be decoded again"
+        );
+    }
+
+    #[test]
+    fn test_split_track_advances_at_its_end() {
+        let (_root, mut player) = split_player();
+        player.seek_to_time(Duration::from_secs(3));
+        let before = tracks::decoded();
+
+        poll_until(&mut player, |p| p.index == 1);
+        assert!(player.elapsed() < Duration::from_secs(1));
+        assert_eq!(tracks::decoded(), before);
+    }
+
+    #[test]
+    fn test_automated_player_skips_undecodable_tracks() {
+        let root = create_working_dir(
+            &["album"],
+            &[
+                ("album/a.mp3", "test_mp3_audio.mp3"),
+                ("album/b.ogg", "test_ogg_audio.ogg"),
+                ("album/c.wav", "test_wav_audio.wav"),
+            ],
+            &[],
+        )
+        .expect("create temp dir")
+        .into_path();
+
+        let mut player = headless(root.join("album"));
+        // A track that can't be decoded once the playlist has been read.
+        let bad = player.playlist[1].path.to_owned();
+        std::fs::write(&bad, b"not audio").expect("should overwrite the track");
+
+        let (_quit, on_quit) = mpsc::channel();
+        let mut out = vec![];
+        let index = automate(&mut player, &on_quit, &mut out).expect("should complete");
+        let out = String::from_utf8(out).unwrap();
+
+        assert_eq!(index, 0);
+        assert!(out.contains(&format!("[tap player]: skipping '{}'", bad.display())));
+        assert!(out.contains("(3/3)"), "the last track should be played");
+    }
+
+    #[test]
+    fn test_album_settings_set_the_gap() {
+        let root = create_working_dir(
+            &["live", "studio"],
+            &[
+                ("live/a.mp3", "test_mp3_audio.mp3"),
+                ("live/b.ogg", "test_ogg_audio.ogg"),
+                ("studio/c.mp3", "test_mp3_audio.mp3"),
+            ],
+            &[],
+        )
+        .expect("create temp dir")
+        .into_path();
+        std::fs::write(root.join("live").join(".tap"), "gap_seconds = 2\n").unwrap();
+
+        let mut player = headless(root.join("live"));
+        assert_eq!(player.gap, Duration::from_secs(2));
+        assert_eq!(headless(root.join("studio")).gap, Duration::ZERO);
+
+        // The next track waits out the gap once the first has ended.
+        poll_until(&mut player, |p| p.gap_started.is_some());
+        assert_eq!(player.index, 0);
+        poll_until(&mut player, |p| p.index == 1);
+        assert!(player.gap_started.is_none());
+    }
+
+    #[test]
+    fn test_analyzed_gain_sets_the_volume() {
+        let root = create_working_dir(
+            &["album"],
+            &[
+                ("album/a.mp3", "test_mp3_audio.mp3"),
+                ("album/b.ogg", "test_ogg_audio.ogg"),
+                ("album/c.flac", "test_flac_audio.flac"),
+            ],
+            &[],
+        )
+        .expect("create temp dir")
+        .into_path()
+        .join("album");
+        std::fs::write(
+            root.join(gain_data::GAIN_FILE),
+            "a.mp3\t-6.00\nc.flac\t4.50\n",
+        )
+        .unwrap();
+
+        let mut player = headless(root.to_owned());
+        let index = |player: &Player, name: &str| {
+            player
+                .playlist
+                .iter()
+                .position(|f| f.path == root.join(name))
+                .expect("should be in the playlist")
+        };
+        let analyzed = 10f32.powf(-6.0 / 20.0);
+
+        player.play_index(index(&player, "a.mp3"));
+        assert!((player.sink.volume() - analyzed).abs() < 1e-6);
+
+        // The gain applies on top of the volume.
+        player.volume = 50;
+        player.set_volume();
+        assert!((player.sink.volume() - analyzed * 0.5).abs() < 1e-6);
+
+        // Tracks that haven't been analyzed play at the volume alone.
+        player.play_index(index(&player, "b.ogg"));
+        assert!((player.sink.volume() - 0.5).abs() < 1e-6);
+
+        // Tracks that need a boost aren't boosted, so that they can't clip.
+        player.play_index(index(&player, "c.flac"));
+        assert!((player.sink.volume() - 0.5).abs() < 1e-6);
+    }
 }
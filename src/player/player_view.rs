@@ -1,69 +1,171 @@
-use std::time::Duration;
+use std::{
+    cmp::min,
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
 
 use cursive::{
-    event::{Event, EventResult, Key, MouseButton, MouseEvent},
+    event::{Event, EventResult, EventTrigger, Key, MouseButton, MouseEvent},
     reexports::crossbeam_channel::Sender,
     theme::{ColorStyle, Effect},
     traits::View,
-    view::Resizable,
+    view::{Nameable, Resizable},
+    views::{Dialog, OnEventView},
     Cursive, Printer, XY,
 };
 use expiring_bool::ExpiringBool;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-use crate::config::{args, theme};
-use crate::fuzzy::{self, FuzzyView};
-use crate::session_data::SessionData;
+use crate::config::{
+    args::{self, EndOfPlaylist},
+    glyphs, theme,
+};
+use crate::data::{favorites, history, play_counts, SessionData};
+use crate::fuzzy::{self, ErrorView, FuzzyView};
 use crate::utils::{self, InnerType};
 
-use super::{AudioFile, KeysView, Player, PlayerBuilder, PlayerStatus};
+use super::{
+    header::{self, HeaderStyle},
+    hooks, is_url,
+    lyrics::LyricsView,
+    balance::BALANCE_STEP,
+    player::{Upcoming, FINE_VOLUME_STEP, VOLUME_STEP},
+    sleep_inhibitor, terminal, AudioFile, KeysView, Player, PlayerBuilder, PlayerOpts, PlayerStatus,
+};
+
+// The name used to find the player view in the view stack.
+pub const NAME: &str = "player";
+
+// The length of the progress bar in the mini player.
+const MINI_BAR: usize = 10;
+
+// The frame rate used while the player is paused or stopped.
+const IDLE_FPS: u32 = 1;
+
+// The width of the progress bar drawn for tracks of unknown length.
+const PULSE: usize = 3;
+
+lazy_static::lazy_static! {
+    // The instant of the last user input, used for the idle timeout.
+    static ref LAST_INPUT: Mutex<Instant> = Mutex::new(Instant::now());
+    // The state of swapping between the current and previous albums.
+    static ref SWAP: Mutex<Swap> = Mutex::new(Swap::default());
+}
+
+#[derive(Default)]
+struct Swap {
+    // The album that was swapped away from and its elapsed time, so that
+    // swapping back resumes it.
+    album: Option<(PathBuf, Duration)>,
+    // Whether the album that was swapped to is shown as "B", or else "A".
+    is_b_side: bool,
+}
 
 pub struct PlayerView {
     // The currently loaded player.
     player: Player,
     // The time to seek to, in seconds. `Some` when seeking has been initiated.
     mouse_seek_time: Option<usize>,
+    // Whether or not playback resumes once seeking with the mouse is done.
+    resume_after_seek: bool,
     // The vertical offset required to show relevant playlist rows.
     offset: usize,
     // Whether or not the current volume is displayed.
     showing_volume: ExpiringBool,
+    // Whether or not the playlist is displayed between the header and the progress bar.
+    showing_playlist: bool,
+    // Whether or not the path of the current track is displayed in place of
+    // the progress bar.
+    showing_path: bool,
+    // Whether the position is shown as a percentage, in place of the remaining time.
+    showing_percent: bool,
+    // Whether or not the balance is displayed.
+    showing_balance: ExpiringBool,
+    // Whether or not the side swapped to is displayed, as "A" or "B".
+    showing_side: ExpiringBool,
+    // The side swapped to.
+    side: char,
+    // Whether or not the confirmation of a copied timestamp is displayed.
+    showing_copied: ExpiringBool,
+    // The height of the view when the playlist is displayed.
+    height: usize,
     // Callback to access the cursive root. `None` if standalone player.
     cb: Option<Sender<Box<dyn FnOnce(&mut Cursive) + Send>>>,
     // The size of the view.
     size: XY<usize>,
+    // Whether or not the album has been counted as played.
+    counted: bool,
 }
 
 impl PlayerView {
     pub fn new(
         player: Player,
-        showing_volume: bool,
+        opts: PlayerOpts,
+        height: usize,
         cb: Option<Sender<Box<dyn FnOnce(&mut Cursive) + Send>>>,
     ) -> Self {
         Self {
             player,
             cb,
+            height,
             mouse_seek_time: None,
+            resume_after_seek: false,
             offset: 0,
-            showing_volume: ExpiringBool::new(showing_volume, Duration::from_millis(1500)),
+            showing_volume: ExpiringBool::new(opts.showing_volume, Duration::from_millis(1500)),
+            showing_playlist: opts.showing_playlist,
+            showing_path: false,
+            showing_percent: args::show_percent(),
+            showing_balance: ExpiringBool::new(false, Duration::from_millis(1500)),
+            showing_side: ExpiringBool::new(false, Duration::from_millis(1500)),
+            side: 'A',
+            showing_copied: ExpiringBool::new(false, Duration::from_millis(1500)),
             size: XY { x: 0, y: 0 },
+            counted: false,
         }
     }
 
-    pub fn load((player, showing_volume, size): (Player, bool, XY<usize>), siv: &mut Cursive) {
+    pub fn load((player, opts, size): (Player, PlayerOpts, XY<usize>), siv: &mut Cursive) {
         let cb = match siv.user_data::<InnerType<SessionData>>() {
             Some(_) => Some(siv.cb_sink().clone()),
             None => None,
         };
 
+        // The history is only used to choose random albums, so failing to save
+        // it isn't worth interrupting playback for.
+        if !is_url(player.path()) {
+            let _ = history::set_played(player.dir());
+        }
+
         siv.add_layer(
-            PlayerView::new(player, showing_volume, cb)
+            PlayerView::new(player, opts, size.y, cb)
+                .with_name(NAME)
                 .full_width()
-                .max_width(size.x)
-                .fixed_height(size.y),
+                .max_width(size.x),
         );
 
         remove_layers_to_top(siv);
     }
 
+    // Counts the album as played once, when its first track has finished. As
+    // with the history, failing to save the count doesn't interrupt playback.
+    fn count_play(&mut self) {
+        self.counted = true;
+        if !is_url(self.player.path()) {
+            let _ = play_counts::add_play(&self.player.dir());
+        }
+    }
+
+    // The currently loaded player.
+    pub fn player(&self) -> &Player {
+        &self.player
+    }
+
+    // Plays the album again from the first track.
+    pub fn restart_album(&mut self) {
+        self.player.play_first_track();
+    }
+
     // Draw methods
 
     // Formats the display for the current playback status.
@@ -75,30 +177,104 @@ impl PlayerView {
         }
     }
 
-    // Formats the display showing whether the player is muted or randomized.
-    fn player_info(&self) -> &'static str {
-        match (self.player.is_randomized, self.player.is_muted) {
-            (true, true) => " *m",
-            (true, false) => "  *",
-            (false, true) => "  m",
-            (false, false) => unreachable!(),
+    // Formats the display of what plays next, with `--show-next`.
+    fn next_up(&self) -> Option<String> {
+        if !args::show_next() {
+            return None;
         }
+
+        let next = match self.player.upcoming()? {
+            Upcoming::Track(index) => self.player.playlist[index].title.to_owned(),
+            Upcoming::Random => String::from("random"),
+            Upcoming::RandomAlbum => String::from("random album"),
+        };
+        Some(format!("next: {}", next))
     }
 
-    // Formats the player header.
-    fn album_and_year(&self, f: &AudioFile) -> String {
-        if let Some(year) = f.year {
-            return format!("{} ({})", f.album, year);
-        } else {
-            return format!("{}", f.album);
+    // Formats the display showing whether the player is randomized, shuffling
+    // albums or muted.
+    fn player_info(&self) -> String {
+        let info: String = [
+            (self.player.is_randomized, '*'),
+            (self.player.is_album_shuffled, 'a'),
+            (self.player.is_muted, 'm'),
+        ]
+        .iter()
+        .filter_map(|&(on, c)| on.then_some(c))
+        .collect();
+
+        format!("{:>3}", info)
+    }
+
+    // Whether or not any of the options in `player_info` are set.
+    fn has_player_info(&self) -> bool {
+        self.player.is_randomized || self.player.is_album_shuffled || self.player.is_muted
+    }
+
+    // Formats the player header from `--header-format`, followed by the disc
+    // number and a star for favorite albums.
+    fn header(&self, f: &AudioFile) -> Vec<(String, HeaderStyle)> {
+        let mut header = header::render(args::header_format(), f);
+
+        let mut suffix = match self.disc(f) {
+            Some(disc) => format!("  disc {}", disc),
+            None => String::new(),
+        };
+
+        if f.path
+            .parent()
+            .map_or(false, |dir| favorites::is_favorite(&dir.to_path_buf()))
+        {
+            suffix.push_str("  ");
+            suffix.push_str(glyphs::get().star);
+        }
+
+        if !suffix.is_empty() {
+            header.push((suffix, HeaderStyle::Album));
+        }
+        header
+    }
+
+    // The disc number of `f`, if the playlist spans more than one disc.
+    fn disc(&self, f: &AudioFile) -> Option<u32> {
+        let playlist = &self.player.playlist;
+        let first = playlist.first()?.disc;
+        match playlist.iter().any(|e| e.disc != first) {
+            true => f.disc,
+            false => None,
         }
     }
 
-    // Formats the volume display.
+    // Formats the volume display. The volume that unmuting restores is shown
+    // while muted.
     fn volume(&self, w: usize) -> String {
-        match w > 14 {
-            true => format!("  vol: {:>3} %  ", self.player.volume),
-            false => format!("  {:>3} %  ", self.player.volume),
+        match (w > 14, self.player.is_muted) {
+            (true, false) => format!("  vol: {:>3} %  ", self.player.volume),
+            (true, true) => format!("  mute {:>3} %  ", self.player.volume),
+            (false, false) => format!("  {:>3} %  ", self.player.volume),
+            (false, true) => format!("  {:>3} m  ", self.player.volume),
+        }
+    }
+
+    // Formats the display for the balance, as the percentage the other channel
+    // is turned down by on the side it is shifted to.
+    fn balance(&self) -> String {
+        let balance = self.player.balance();
+        let percent = (balance.abs() * 100.0).round();
+        let text = match balance {
+            b if b < 0.0 => format!("L {:>3}", percent),
+            b if b > 0.0 => format!("R {:>3}", percent),
+            _ => String::from("C"),
+        };
+        format!("  bal: {:>5}  ", text)
+    }
+
+    // The color of the volume display. Volumes above 100% are shown as errors,
+    // since the amplified audio can clip, unless muted.
+    fn volume_color(&self) -> ColorStyle {
+        match self.player.volume > 100 && !self.player.is_muted {
+            true => theme::err(),
+            false => theme::prompt(),
         }
     }
 
@@ -112,6 +288,79 @@ impl PlayerView {
         }
     }
 
+    // The remaining playback time, or the position as a percentage with `%`.
+    fn time_column(&self, elapsed: usize, duration: usize) -> String {
+        match self.showing_percent {
+            true => percent(elapsed, duration),
+            false => remaining_time(elapsed, duration),
+        }
+    }
+
+    // The start column and length of the progress bar.
+    fn progress_bar(&self) -> (usize, usize) {
+        if args::mini() {
+            let column = if self.size.x > 9 { self.size.x - 9 } else { 0 };
+            let length = if column > MINI_BAR + 16 { MINI_BAR } else { 0 };
+            (column - length, length)
+        } else {
+            let length = if self.size.x > 16 {
+                self.size.x - 16
+            } else {
+                0
+            };
+            (8, length)
+        }
+    }
+
+    // Draws the player on a single row: the status, the current track and
+    // a short progress bar with the remaining playback time.
+    fn draw_mini(&self, p: &Printer) {
+        // The file currently loaded in the player.
+        let f = self.player.file();
+        // The time elapsed since playback started.
+        let elapsed = self.elapsed();
+        // The start and length of the progress bar.
+        let (start, length) = self.progress_bar();
+        // The start of the remaining time column.
+        let column = start + length;
+
+        // Draw the player status.
+        let (symbol, color, effect) = self.player_status();
+        p.with_color(color, |p| {
+            p.with_effect(effect, |p| p.print((1, 0), symbol))
+        });
+
+        // Draw the current track, cropped to leave room for the progress bar.
+        p.cropped((start.saturating_sub(1), 1))
+            .with_color(theme::hl(), |p| {
+                let track = self.player.track_number(self.player.index);
+                p.print((3, 0), format!("{:02}  {}", track, f.title).as_str())
+            });
+
+        if length > 0 && f.duration == 0 {
+            p.with_color(theme::progress(), |p| {
+                p.print_hline(
+                    (start + pulse(elapsed, length), 0),
+                    min(PULSE, length),
+                    glyphs::get().block,
+                );
+            });
+        } else if length > 0 {
+            let (solid, extra) = ratio(elapsed, f.duration, length);
+            p.with_color(theme::progress(), |p| {
+                p.print_hline((start, 0), solid, glyphs::get().block);
+                if solid < length {
+                    p.print((start + solid, 0), glyphs::get().sub_block(extra));
+                }
+            });
+        }
+
+        // Draw the remaining playback time.
+        p.with_color(theme::hl(), |p| {
+            p.print((column, 0), self.time_column(elapsed, f.duration).as_str())
+        });
+    }
+
     // Computes the y offset needed to show the results of the fuzzy match.
     #[inline]
     fn update_offset(&self) -> usize {
@@ -182,15 +431,16 @@ impl PlayerView {
 
     // Updates user data with the current status.
     fn set_status(&mut self, status: u8) -> EventResult {
-        if self.cb.is_some() {
-            EventResult::with_cb(move |siv| {
+        let has_session = self.cb.is_some();
+        EventResult::with_cb(move |siv| {
+            if has_session {
                 siv.with_user_data(|(opts, _, _): &mut InnerType<SessionData>| {
                     opts.0 = status;
                 });
-            })
-        } else {
-            EventResult::Consumed(None)
-        }
+            }
+            update_fps(siv);
+            sleep_inhibitor::update(siv);
+        })
     }
 
     // Toggles the track order between in-order and random.
@@ -212,27 +462,40 @@ impl PlayerView {
         EventResult::Consumed(None)
     }
 
-    // Loads a fuzzy view for the parent of the current audio file.
+    // Loads a fuzzy view for the parent of the current audio file, with the
+    // directory that is playing selected.
     fn parent(&self) -> EventResult {
         let mut parent = self.player.path().to_owned();
-        let root = args::search_root();
+        let root = args::root_of(&parent);
 
         if parent != root {
             parent.pop();
             if parent != root {
+                let child = parent.to_owned();
                 parent.pop();
                 return EventResult::with_cb(move |siv| {
-                    let items = fuzzy::create_items(&parent).expect("should always exist");
-                    FuzzyView::load(items, None, siv)
+                    FuzzyView::load_parent(parent.to_owned(), &child, siv)
                 });
             }
         }
         EventResult::Consumed(None)
     }
 
+    // Swaps between the current and previous albums.
+    fn swap_album(&self) -> EventResult {
+        if self.cb.is_none() {
+            return EventResult::Consumed(None);
+        }
+
+        let index = self.player.index;
+        let elapsed = self.player.elapsed();
+        EventResult::with_cb(move |siv| swap_album(siv, index, elapsed))
+    }
+
     // Toggles whether the player is muted and updates user data.
     fn toggle_mute(&mut self) -> EventResult {
         let is_muted = self.player.toggle_mute();
+        self.showing_volume.set();
         if self.cb.is_some() {
             EventResult::with_cb(move |siv| {
                 siv.with_user_data(|(opts, _, _): &mut InnerType<SessionData>| {
@@ -258,6 +521,21 @@ impl PlayerView {
         }
     }
 
+    // Toggles whether or not the playlist is displayed and updates user data.
+    fn toggle_playlist_display(&mut self) -> EventResult {
+        self.showing_playlist ^= true;
+        let showing_playlist = self.showing_playlist;
+        if self.cb.is_some() {
+            EventResult::with_cb(move |siv| {
+                siv.with_user_data(|(opts, _, _): &mut InnerType<SessionData>| {
+                    opts.4 = showing_playlist;
+                });
+            })
+        } else {
+            EventResult::Consumed(None)
+        }
+    }
+
     // Loads the next track in the queue.
     fn next(&mut self) {
         if self.player.is_randomized {
@@ -276,6 +554,41 @@ impl PlayerView {
         }
     }
 
+    // Adds the directory of the current audio file to the favorites, or removes it.
+    fn toggle_favorite(&self) -> EventResult {
+        let dir = match self.player.path().parent() {
+            Some(dir) if !is_url(self.player.path()) => dir.to_path_buf(),
+            _ => return EventResult::Consumed(None),
+        };
+
+        match favorites::toggle(&dir) {
+            Ok(_) => EventResult::Consumed(None),
+            Err(e) => {
+                let msg = e.to_string();
+                EventResult::with_cb(move |siv| {
+                    ErrorView::load(siv, anyhow::Error::msg(msg.clone()))
+                })
+            }
+        }
+    }
+
+    // Adds the current track to the blocklist, or removes it.
+    fn toggle_blocked(&mut self) -> EventResult {
+        if is_url(self.player.path()) {
+            return EventResult::Consumed(None);
+        }
+
+        match self.player.toggle_blocked() {
+            Ok(_) => EventResult::Consumed(None),
+            Err(e) => {
+                let msg = e.to_string();
+                EventResult::with_cb(move |siv| {
+                    ErrorView::load(siv, anyhow::Error::msg(msg.clone()))
+                })
+            }
+        }
+    }
+
     // Opens the parent of the current audio file in the
     // preferred file manager.
     fn open_file_manager(&self) {
@@ -283,18 +596,67 @@ impl PlayerView {
         _ = utils::open_file_manager(path);
     }
 
-    // Increments the volume and updates user data.
-    fn increase_volume(&mut self) -> EventResult {
-        let volume = self.player.increase_volume();
+    // Opens a shell in the directory of the current audio file.
+    fn open_shell(&self) -> EventResult {
+        if is_url(self.player.path()) {
+            return EventResult::Consumed(None);
+        }
+
+        let path = self.player.path().to_owned();
+        EventResult::with_cb(move |siv| {
+            if let Err(e) = terminal::open_shell(siv, path.to_owned()) {
+                ErrorView::load(siv, e)
+            }
+        })
+    }
+
+    // Copies the current track and elapsed time to the clipboard, formatted with
+    // `--timestamp-format`. The text is shown instead if it can't be copied.
+    fn copy_timestamp(&mut self) -> EventResult {
+        let text = timestamp(args::timestamp_format(), self.player.file(), self.elapsed());
+
+        match utils::copy_to_clipboard(&text) {
+            Ok(()) => {
+                self.showing_copied.set();
+                EventResult::Consumed(None)
+            }
+            Err(_) => EventResult::with_cb(move |siv| {
+                siv.add_layer(
+                    Dialog::text(text.to_owned())
+                        .title("timestamp")
+                        .dismiss_button("Ok"),
+                )
+            }),
+        }
+    }
+
+    // Quits the app. With `--confirm-quit`, asks first unless the player is
+    // stopped, since the playback position would be lost.
+    fn quit(&self) -> EventResult {
+        match args::confirm_quit() && self.player.status != PlayerStatus::Stopped {
+            true => EventResult::with_cb(load_quit_dialog),
+            false => quit(),
+        }
+    }
+
+    // Increments the volume by `step`% and updates user data.
+    fn increase_volume(&mut self, step: u8) -> EventResult {
+        let volume = self.player.increase_volume(step);
         return self.set_volume(volume);
     }
 
-    // Decrements the volume and updates user data.
-    fn decrease_volume(&mut self) -> EventResult {
-        let volume = self.player.decrease_volume();
+    // Decrements the volume by `step`% and updates user data.
+    fn decrease_volume(&mut self, step: u8) -> EventResult {
+        let volume = self.player.decrease_volume(step);
         return self.set_volume(volume);
     }
 
+    // Shifts the balance by `step` and shows it.
+    fn shift_balance(&mut self, step: f32) {
+        self.player.shift_balance(step);
+        self.showing_balance.set();
+    }
+
     // Stops the player and updates user data.
     fn stop(&mut self) -> EventResult {
         let status = self.player.stop();
@@ -307,8 +669,28 @@ impl PlayerView {
         return self.set_status(status);
     }
 
+    // Pauses playback, if playing.
+    pub fn pause(&mut self) -> EventResult {
+        match self.player.status {
+            PlayerStatus::Playing => self.play_or_pause(),
+            _ => EventResult::Ignored,
+        }
+    }
+
     // Handles the mouse left button press actions.
     fn mouse_button_left(&mut self, offset: XY<usize>, position: XY<usize>) {
+        // The mini player seeks from the progress bar and plays or pauses elsewhere.
+        if args::mini() {
+            let (start, length) = self.progress_bar();
+            let x = position.x.saturating_sub(offset.x);
+            if length > 0 && x >= start && x < start + length {
+                self.mouse_hold_seek(offset, position);
+            } else {
+                self.play_or_pause();
+            }
+            return;
+        }
+
         // Whether or not the mouse cursor is outside the area containing
         // the playlist and the progress bar.
         let outside_area = position.y <= offset.y
@@ -345,14 +727,17 @@ impl PlayerView {
 
     // Updates the seek position from mouse input.
     fn mouse_hold_seek(&mut self, offset: XY<usize>, position: XY<usize>) {
-        if self.size.x > 16 && position.x > offset.x {
-            if self.player.status == PlayerStatus::Stopped {
-                self.player.play();
+        let (start, length) = self.progress_bar();
+
+        if length > 0 && position.x > offset.x && self.player.file().duration > 0 {
+            // Clicking the bar while stopped or paused sets the position
+            // without starting playback.
+            if self.mouse_seek_time.is_none() {
+                self.resume_after_seek = self.player.pause_for_seek();
             }
-            self.player.pause();
             let duration = self.player.file().duration;
-            let mouse_seek_pos = utils::clamp(position.x - offset.x, 8, self.size.x - 8) - 8;
-            self.mouse_seek_time = Some(mouse_seek_pos * duration / (self.size.x - 16));
+            let x = position.x - offset.x;
+            self.mouse_seek_time = Some(seek_time(x, start, length, duration));
         }
     }
 
@@ -360,7 +745,7 @@ impl PlayerView {
     fn mouse_release_seek(&mut self) {
         if let Some(secs) = self.mouse_seek_time {
             let seek_time = Duration::new(secs as u64, 0);
-            self.player.seek_to_time(seek_time);
+            self.player.finish_seek(seek_time, self.resume_after_seek);
         }
         self.mouse_seek_time = None;
     }
@@ -376,13 +761,13 @@ impl PlayerView {
 
         if event == MouseEvent::WheelUp {
             if outside_playlist {
-                self.increase_volume();
+                self.increase_volume(VOLUME_STEP);
             } else {
                 self.previous();
             }
         } else if event == MouseEvent::WheelDown {
             if outside_playlist {
-                self.decrease_volume();
+                self.decrease_volume(VOLUME_STEP);
             } else {
                 if self.player.index != self.player.playlist.len() - 1 {
                     self.next();
@@ -395,6 +780,9 @@ impl PlayerView {
 impl View for PlayerView {
     fn layout(&mut self, size: cursive::Vec2) {
         self.player.poll();
+        if self.player.has_played && !self.counted {
+            self.count_play();
+        }
         if self.player.is_randomized && self.player.next_track_queued {
             self.random_track();
         }
@@ -402,7 +790,22 @@ impl View for PlayerView {
         self.offset = self.update_offset();
     }
 
+    // The mini player is always drawn on a single row. Without the playlist
+    // only the header and the progress bar are drawn.
+    fn required_size(&mut self, constraint: XY<usize>) -> XY<usize> {
+        let height = match (args::mini(), self.showing_playlist) {
+            (true, _) => 1,
+            (false, true) => self.height,
+            (false, false) => 2,
+        };
+        XY::new(constraint.x, height)
+    }
+
     fn draw(&self, p: &Printer) {
+        if args::mini() {
+            return self.draw_mini(p);
+        }
+
         // The size of the screen we can draw on.
         let (w, h) = (p.size.x, p.size.y);
         // The file currently loaded in the player.
@@ -414,10 +817,10 @@ impl View for PlayerView {
         // The time elapsed since playback started.
         let elapsed = self.elapsed();
         // The values needed to draw the progress bar.
-        let (length, extra) = ratio(elapsed, f.duration, length);
+        let (solid, extra) = ratio(elapsed, f.duration, length);
 
         // Draw the playlist, with rows: 'Track, Title, Duration'.
-        if h > 2 {
+        if h > 2 && self.showing_playlist {
             for (i, f) in self.player.playlist.iter().enumerate() {
                 // Skip rows that are not visible.
                 if i < self.offset {
@@ -434,22 +837,32 @@ impl View for PlayerView {
                     });
                     // Draw the active row.
                     p.with_color(theme::hl(), |p| {
-                        p.print((6, row), format!("{:02}  {}", f.track, f.title).as_str());
-                        if column > 11 && (self.player.is_randomized || self.player.is_muted) {
+                        let track = self.player.track_number(i);
+                        p.print((6, row), format!("{:02}  {}", track, f.title).as_str());
+                        if column > 11 && self.has_player_info() {
                             // Draw the player options.
                             p.with_color(theme::info(), |p| {
                                 p.with_effect(Effect::Italic, |p| {
-                                    p.print((column - 3, row), self.player_info())
+                                    p.print((column - 3, row), &self.player_info())
                                 })
                             })
                         }
-                        p.print((column, row), mins_and_secs(f.duration).as_str());
+                        p.print((column, row), track_length(f.duration).as_str());
+                    })
+                } else if i + 2 - self.offset < h && self.player.is_blocked(i) {
+                    // Draw the blocklisted rows dimmed, with a marker.
+                    p.with_color(theme::prompt(), |p| {
+                        let track = self.player.track_number(i);
+                        p.print((3, row), "x");
+                        p.print((6, row), format!("{:02}  {}", track, f.title).as_str());
+                        p.print((column, row), track_length(f.duration).as_str());
                     })
                 } else if i + 2 - self.offset < h {
                     // Draw the inactive rows.
                     p.with_color(theme::fg(), |p| {
-                        p.print((6, row), format!("{:02}  {}", f.track, f.title).as_str());
-                        p.print((column, row), mins_and_secs(f.duration).as_str());
+                        let track = self.player.track_number(i);
+                        p.print((6, row), format!("{:02}  {}", track, f.title).as_str());
+                        p.print((column, row), track_length(f.duration).as_str());
                     })
                 }
 
@@ -461,22 +874,54 @@ impl View for PlayerView {
         }
 
         if h > 1 {
-            // Draw the header: 'Artist, Album, Year'.
+            // Draw the header, as set with `--header-format`.
+            let mut x = 2;
             p.with_effect(Effect::Bold, |p| {
-                p.with_color(theme::header1(), |p| p.print((2, 0), &f.artist.as_str()));
-                p.with_effect(Effect::Italic, |p| {
-                    p.with_color(theme::header2(), |p| {
-                        p.print((f.artist.len() + 4, 0), &self.album_and_year(f).as_str())
-                    })
-                })
+                for (text, style) in self.header(f) {
+                    match style {
+                        HeaderStyle::Artist => {
+                            p.with_color(theme::header1(), |p| p.print((x, 0), &text))
+                        }
+                        HeaderStyle::Album => p.with_effect(Effect::Italic, |p| {
+                            p.with_color(theme::header2(), |p| p.print((x, 0), &text))
+                        }),
+                        HeaderStyle::Title => p.with_color(theme::hl(), |p| p.print((x, 0), &text)),
+                    }
+                    x += text.width();
+                }
             });
 
-            if self.showing_volume.is_true() {
+            // Draw what plays next at the end of the header, clear of the volume
+            // and the other messages shown after it.
+            if let Some(next) = self.next_up() {
+                let end = column.saturating_sub(6);
+                if end > x + 12 {
+                    let next = truncate_right(&next, end - x - 2);
+                    p.with_color(theme::prompt(), |p| p.print((end - next.width(), 0), &next));
+                }
+            }
+
+            if self.showing_volume.is_true() || self.player.is_muted {
                 let column = if w > 14 { column - 5 } else { column };
-                p.with_color(theme::prompt(), |p| {
+                p.with_color(self.volume_color(), |p| {
                     p.print((column, 0), &self.volume(w).as_str())
                 });
-            };
+            } else if self.showing_balance.is_true() {
+                let column = if w > 14 { column - 5 } else { column };
+                p.with_color(theme::prompt(), |p| p.print((column, 0), &self.balance()));
+            } else if self.showing_side.is_true() {
+                p.with_color(theme::prompt(), |p| {
+                    p.print((column, 0), &format!("  {}  ", self.side))
+                });
+            } else if self.showing_copied.is_true() {
+                p.with_color(theme::prompt(), |p| {
+                    p.print((column.saturating_sub(1), 0), "  copied  ")
+                });
+            } else if args::audiobook() {
+                p.with_color(theme::info(), |p| {
+                    p.with_effect(Effect::Italic, |p| p.print((column, 0), "  book"))
+                });
+            }
         }
 
         if h > 0 {
@@ -485,25 +930,43 @@ impl View for PlayerView {
 
             // Draw the elapsed and remaining playback times.
             p.with_color(theme::hl(), |p| {
-                let remaining = if elapsed > f.duration {
-                    0
-                } else {
-                    f.duration - elapsed
-                };
                 p.print((0, last_row), &mins_and_secs(elapsed));
-                p.print((column, last_row), mins_and_secs(remaining).as_str())
-            });
-
-            // Draw the fractional part of the progress bar.
-            p.with_color(theme::progress(), |p| {
-                p.print((length + 8, last_row), sub_block(extra));
+                p.print(
+                    (column, last_row),
+                    self.time_column(elapsed, f.duration).as_str(),
+                )
             });
 
-            // Draw the solid part of the progress bar (preceding the fractional part).
-            p.cropped((length + 8, h))
-                .with_color(theme::progress(), |p| {
-                    p.print_hline((8, last_row), length, "█");
+            if self.showing_path {
+                // Draw the path of the current track in place of the progress bar.
+                let path = f.path.to_string_lossy();
+                p.with_color(theme::prompt(), |p| {
+                    p.print(
+                        (8, last_row),
+                        &truncate_left(&path, length.saturating_sub(1)),
+                    )
+                });
+            } else if f.duration == 0 {
+                // Draw the indeterminate progress bar for tracks of unknown length.
+                p.with_color(theme::progress(), |p| {
+                    p.print_hline(
+                        (8 + pulse(elapsed, length), last_row),
+                        min(PULSE, length),
+                        glyphs::get().block,
+                    );
                 });
+            } else {
+                // Draw the fractional part of the progress bar.
+                p.with_color(theme::progress(), |p| {
+                    p.print((solid + 8, last_row), glyphs::get().sub_block(extra));
+                });
+
+                // Draw the solid part of the progress bar (preceding the fractional part).
+                p.cropped((solid + 8, h))
+                    .with_color(theme::progress(), |p| {
+                        p.print_hline((8, last_row), solid, glyphs::get().block);
+                    });
+            }
 
             // Draw spaces to maintain consistent padding when resizing.
             p.print((w - 2, 0), "  ");
@@ -519,35 +982,52 @@ impl View for PlayerView {
             Event::Char('k') | Event::Key(Key::Up) => self.previous(),
             Event::Char('l') | Event::Key(Key::Enter | Key::Right) => return self.stop(),
 
-            Event::Char(']') => return self.increase_volume(),
-            Event::Char('[') => return self.decrease_volume(),
+            Event::Char(']') => return self.increase_volume(VOLUME_STEP),
+            Event::Char('[') => return self.decrease_volume(VOLUME_STEP),
+            Event::AltChar(']') => return self.increase_volume(FINE_VOLUME_STEP),
+            Event::AltChar('[') => return self.decrease_volume(FINE_VOLUME_STEP),
+            Event::Char('<') => self.shift_balance(-BALANCE_STEP),
+            Event::Char('>') => self.shift_balance(BALANCE_STEP),
+            Event::Char('|') => {
+                self.player.reset_balance();
+                self.showing_balance.set();
+            }
             Event::Char('v') => return self.toggle_volume_display(),
+            Event::Char('p') => return self.toggle_playlist_display(),
             Event::Char('m') => return self.toggle_mute(),
+            Event::Char('i') => self.showing_path ^= true,
+            Event::Char('%') => self.showing_percent ^= true,
+            Event::Char('b') => return self.swap_album(),
+            Event::Char('c') => return self.copy_timestamp(),
 
             Event::Char('\'') => self.player.seek_to_min(),
             Event::Char('"') => self.player.seek_to_sec(),
+            Event::Char(';') => self.player.seek_from_end(),
             Event::Char('.') => self.player.step_forward(),
             Event::Char(',') => self.player.step_backward(),
+            Event::Key(Key::Home) => self.player.restart(),
 
             Event::Char('*' | 'r') => return self.toggle_randomization(),
+            Event::Char('a') => {
+                self.player.toggle_album_shuffle();
+            }
+            Event::Char('x') => self.player.play_random_track(),
+            Event::Char('{') | Event::Ctrl(Key::Up) => self.player.move_track(true),
+            Event::Char('}') | Event::Ctrl(Key::Down) => self.player.move_track(false),
             Event::Char('g') => self.player.play_key_selection(),
             Event::CtrlChar('g') => self.player.play_last_track(),
+            Event::Char('G') => self.player.play_percent_selection(),
 
-            Event::Char('0') => self.player.num_keys.push(0),
-            Event::Char('1') => self.player.num_keys.push(1),
-            Event::Char('2') => self.player.num_keys.push(2),
-            Event::Char('3') => self.player.num_keys.push(3),
-            Event::Char('4') => self.player.num_keys.push(4),
-            Event::Char('5') => self.player.num_keys.push(5),
-            Event::Char('6') => self.player.num_keys.push(6),
-            Event::Char('7') => self.player.num_keys.push(7),
-            Event::Char('8') => self.player.num_keys.push(8),
-            Event::Char('9') => self.player.num_keys.push(9),
+            Event::Char(c @ '0'..='9') => self.player.push_num_key(c as usize - '0' as usize),
 
             Event::CtrlChar('p') => return self.parent(),
             Event::CtrlChar('o') => self.open_file_manager(),
+            Event::CtrlChar('e') => return self.open_shell(),
+            Event::Char('f') => return self.toggle_favorite(),
+            Event::Char('d') => return self.toggle_blocked(),
+            Event::Char('y') => return load_lyrics_view(),
             Event::Char('?') => return load_keys_view(),
-            Event::Char('q') => return quit(),
+            Event::Char('q') => return self.quit(),
 
             // TODO: scroll to adjust vertical offset, not select track.
             // FIXME: mouse stop, mouse play, mouse select -> playback is
@@ -594,6 +1074,183 @@ pub fn random_album(_: &Event) -> Option<EventResult> {
     }))
 }
 
+// Plays a random album, quits or runs the `--end-command` once the last track
+// of the playlist has finished, as set with `--end-of-playlist` or by shuffling
+// albums.
+pub fn on_playlist_end(siv: &mut Cursive) {
+    let finished = siv
+        .call_on_name(NAME, |v: &mut PlayerView| {
+            v.player
+                .take_finished()
+                .then(|| (v.player.file().to_owned(), v.player.end_action()))
+        })
+        .flatten();
+
+    let (file, action) = match finished {
+        Some(finished) => finished,
+        None => return,
+    };
+
+    match action {
+        EndOfPlaylist::RandomAlbum => {
+            if let Ok(player) = PlayerBuilder::RandomAlbum.from(None, siv) {
+                PlayerView::load(player, siv);
+            }
+        }
+        EndOfPlaylist::Quit => siv.quit(),
+        EndOfPlaylist::Command => {
+            if let Some(command) = args::end_command() {
+                hooks::run(siv, command, &file);
+            }
+        }
+        EndOfPlaylist::Stop | EndOfPlaylist::Repeat => (),
+    }
+}
+
+// Loads the previous album in place of the current one, resuming it at the
+// track and time it was left at, so that two albums can be switched between.
+// The current album is left at `index` and `elapsed`. The swapped album isn't
+// randomized.
+fn swap_album(siv: &mut Cursive, index: usize, elapsed: Duration) {
+    let current = siv
+        .with_user_data(|(_, _, queue): &mut InnerType<SessionData>| {
+            // Swapping back resumes the current album at the same track.
+            let (path, i) = queue.get_mut(1)?;
+            *i = index;
+            Some(path.to_owned())
+        })
+        .flatten();
+
+    // There is no previous album to swap to.
+    let current = match current {
+        Some(current) => current,
+        None => return,
+    };
+
+    let (mut player, opts, size) = match PlayerBuilder::SwapAlbum.from(None, siv) {
+        Ok(player) => player,
+        Err(_) => return,
+    };
+
+    let mut swap = SWAP.lock().expect("should not be poisoned");
+    let resumed = match (swap.album.take(), fuzzy::current_path(siv)) {
+        (Some((path, elapsed)), Some(swapped)) if path == swapped => Some(elapsed),
+        _ => None,
+    };
+
+    if let Some(elapsed) = resumed {
+        match player.status {
+            PlayerStatus::Playing => player.seek_to_time(elapsed),
+            PlayerStatus::Paused => {
+                player.seek_to_time(elapsed);
+                player.pause();
+            }
+            PlayerStatus::Stopped => (),
+        }
+    }
+
+    swap.album = Some((current, elapsed));
+    swap.is_b_side ^= true;
+    let side = if swap.is_b_side { 'B' } else { 'A' };
+    drop(swap);
+
+    PlayerView::load((player, opts, size), siv);
+    siv.call_on_name(NAME, |v: &mut PlayerView| {
+        v.side = side;
+        v.showing_side.set();
+    });
+}
+
+// Trigger for the events that count as user input for the idle timeout: key
+// presses, mouse button presses and releases, and mouse wheel scrolls. Mouse
+// movement, resizing the terminal and refreshing don't count as input.
+pub fn input_trigger() -> EventTrigger {
+    EventTrigger::from_fn(|event| match event {
+        Event::Mouse { event, .. } => matches!(
+            event,
+            MouseEvent::Press(_)
+                | MouseEvent::Release(_)
+                | MouseEvent::WheelUp
+                | MouseEvent::WheelDown
+        ),
+        Event::Char(_)
+        | Event::CtrlChar(_)
+        | Event::AltChar(_)
+        | Event::Key(_)
+        | Event::Shift(_)
+        | Event::Alt(_)
+        | Event::AltShift(_)
+        | Event::Ctrl(_)
+        | Event::CtrlShift(_)
+        | Event::CtrlAlt(_) => true,
+        _ => false,
+    })
+}
+
+// Callback to record user input. The event is still handled by the views.
+pub fn record_input(_: &Event) -> Option<EventResult> {
+    if let Ok(mut last_input) = LAST_INPUT.lock() {
+        *last_input = Instant::now();
+    }
+    None
+}
+
+// Lowers the frame rate while the player is paused or stopped, since nothing
+// on screen moves, and restores it while playing or when there is no player.
+pub fn update_fps(siv: &mut Cursive) {
+    let is_playing = siv.call_on_name(NAME, |v: &mut PlayerView| {
+        v.player.status == PlayerStatus::Playing
+    });
+
+    match is_playing {
+        Some(false) => siv.set_fps(IDLE_FPS),
+        _ => siv.set_fps(args::fps()),
+    }
+}
+
+// Quits the app if there has been no user input for the idle timeout,
+// unless the player is playing.
+pub fn quit_if_idle(siv: &mut Cursive) {
+    let timeout = match args::idle_timeout() {
+        Some(timeout) => timeout,
+        None => return,
+    };
+
+    let is_idle = LAST_INPUT
+        .lock()
+        .map_or(false, |last_input| last_input.elapsed() >= timeout);
+
+    let is_playing = siv
+        .call_on_name(NAME, |v: &mut PlayerView| {
+            v.player.status == PlayerStatus::Playing
+        })
+        .unwrap_or(false);
+
+    if is_idle && !is_playing {
+        siv.quit();
+    }
+}
+
+// Asks to confirm quitting. Pressing `q` again quits, so that tapping it twice
+// skips the dialog.
+fn load_quit_dialog(siv: &mut Cursive) {
+    siv.add_layer(
+        OnEventView::new(
+            Dialog::text("Quit tap? Playback will stop.")
+                .button("Quit", |siv| siv.quit())
+                .dismiss_button("Cancel"),
+        )
+        .on_event('q', |siv| siv.quit())
+        .on_event('y', |siv| siv.quit())
+        .on_event('n', |siv| {
+            siv.pop_layer();
+        })
+        .on_event(Key::Esc, |siv| {
+            siv.pop_layer();
+        }),
+    )
+}
+
 // Quit the app.
 fn quit() -> EventResult {
     return EventResult::with_cb(|siv| {
@@ -608,6 +1265,25 @@ fn load_keys_view() -> EventResult {
     });
 }
 
+fn load_lyrics_view() -> EventResult {
+    return EventResult::with_cb(|siv| {
+        LyricsView::load(siv);
+    });
+}
+
+// The time to seek to when clicking column `x` of a progress bar that starts
+// at column `start` and is `length` columns long. This is the inverse of
+// `ratio`: the time falls in the middle of the clicked cell, so that the bar
+// is drawn up to and including that cell. Clicks outside the bar are clamped
+// to its first and last cells.
+fn seek_time(x: usize, start: usize, length: usize, duration: usize) -> usize {
+    if length == 0 {
+        return 0;
+    }
+    let cell = utils::clamp(x, start, start + length - 1) - start;
+    (2 * cell + 1) * duration / (2 * length)
+}
+
 // Computes the values required to draw the progress bar.
 fn ratio(value: usize, max: usize, length: usize) -> (usize, usize) {
     if max == 0 {
@@ -620,19 +1296,72 @@ fn ratio(value: usize, max: usize, length: usize) -> (usize, usize) {
     (integer, fraction * 8 / max)
 }
 
-// The characters needed to draw the fractional part of the progress bar.
-fn sub_block(extra: usize) -> &'static str {
-    match extra {
-        0 => " ",
-        1 => "▏",
-        2 => "▎",
-        3 => "▍",
-        4 => "▌",
-        5 => "▋",
-        6 => "▊",
-        7 => "▉",
-        _ => "█",
+// Truncates `text` from the left to fit in `width` columns, so that the end of
+// it, such as a file name, stays visible.
+fn truncate_left(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    } else if width == 0 {
+        return String::new();
+    }
+
+    // The ellipsis takes one column.
+    let mut used = 1;
+    let mut start = text.len();
+
+    for (i, c) in text.char_indices().rev() {
+        used += c.width().unwrap_or(0);
+        if used > width {
+            break;
+        }
+        start = i;
+    }
+
+    format!("{}{}", glyphs::get().ellipsis, &text[start..])
+}
+
+// Truncates `text` from the right to fit in `width` columns.
+fn truncate_right(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    } else if width == 0 {
+        return String::new();
+    }
+
+    // The ellipsis takes one column.
+    let mut used = 1;
+    let mut end = 0;
+
+    for (i, c) in text.char_indices() {
+        used += c.width().unwrap_or(0);
+        if used > width {
+            break;
+        }
+        end = i + c.len_utf8();
     }
+
+    format!("{}{}", &text[..end], glyphs::get().ellipsis)
+}
+
+// Replaces the `{artist}`, `{title}`, `{album}` and `{elapsed}` placeholders
+// in `template`. The elapsed time is formatted as `mm:ss`, or `h:mm:ss` from an
+// hour in.
+fn timestamp(template: &str, file: &AudioFile, elapsed: usize) -> String {
+    let elapsed = match elapsed < 3600 {
+        true => format!("{:02}:{:02}", elapsed / 60, elapsed % 60),
+        false => format!(
+            "{}:{:02}:{:02}",
+            elapsed / 3600,
+            elapsed / 60 % 60,
+            elapsed % 60
+        ),
+    };
+
+    template
+        .replace("{artist}", &file.artist)
+        .replace("{title}", &file.title)
+        .replace("{album}", &file.album)
+        .replace("{elapsed}", &elapsed)
 }
 
 // Formats the playback time.
@@ -640,6 +1369,47 @@ fn mins_and_secs(secs: usize) -> String {
     format!("  {:02}:{:02}  ", secs / 60, secs % 60)
 }
 
+// The length of a track, which is unknown if it has no duration.
+pub fn track_length(duration: usize) -> String {
+    match duration {
+        0 => String::from("  --:--  "),
+        _ => mins_and_secs(duration),
+    }
+}
+
+// The remaining playback time, which is unknown if the track has no duration.
+fn remaining_time(elapsed: usize, duration: usize) -> String {
+    match duration {
+        0 => track_length(duration),
+        _ => mins_and_secs(duration.saturating_sub(elapsed)),
+    }
+}
+
+// The playback position as a percentage of `duration`, padded to the width of
+// `mins_and_secs`. Unknown if the track has no duration.
+fn percent(elapsed: usize, duration: usize) -> String {
+    match duration {
+        0 => String::from("   --%   "),
+        _ => format!("{:>6}%  ", min(elapsed * 100 / duration, 100)),
+    }
+}
+
+// The offset of the indeterminate progress bar, which moves back and forth
+// along a bar of `length` once per second.
+fn pulse(elapsed: usize, length: usize) -> usize {
+    let span = length.saturating_sub(PULSE);
+    if span == 0 {
+        return 0;
+    }
+
+    let step = elapsed % (2 * span);
+    if step < span {
+        step
+    } else {
+        2 * span - step
+    }
+}
+
 // Remove all layers from the view stack except the top layer.
 fn remove_layers_to_top(siv: &mut Cursive) {
     while siv.screen().len() > 1 {
@@ -647,3 +1417,89 @@ fn remove_layers_to_top(siv: &mut Cursive) {
             .remove_layer(cursive::views::LayerPosition::FromBack(0));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seek_time_matches_drawn_bar() {
+        for width in [17, 20, 53, 80, 120] {
+            // The bar drawn by the full player.
+            let (start, length) = (8, width - 16);
+
+            for duration in [2 * length, 3 * length + 7, 3600] {
+                for x in start..start + length {
+                    let time = seek_time(x, start, length, duration);
+                    let (solid, _) = ratio(time, duration, length);
+                    assert_eq!(
+                        solid + start,
+                        x,
+                        "width {width}, duration {duration}: clicking column {x} seeks to {time}s"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_percent() {
+        assert_eq!(percent(0, 240), "     0%  ");
+        assert_eq!(percent(103, 240), "    42%  ");
+        assert_eq!(percent(240, 240), "   100%  ");
+        // Elapsed can pass an unreliable duration.
+        assert_eq!(percent(300, 240), "   100%  ");
+        assert_eq!(percent(30, 0), "   --%   ");
+        assert_eq!(percent(103, 240).len(), mins_and_secs(0).len());
+    }
+
+    #[test]
+    fn test_truncate_left_keeps_file_name() {
+        let path = "/music/Miles Davis/Kind of Blue/01 So What.flac";
+
+        assert_eq!(truncate_left(path, 80), path);
+        assert_eq!(truncate_left(path, 18), "…e/01 So What.flac");
+        assert_eq!(truncate_left(path, 18).width(), 18);
+        assert_eq!(truncate_left("/música/日本語.mp3", 9), "…本語.mp3");
+        assert_eq!(truncate_left(path, 0), "");
+    }
+
+    #[test]
+    fn test_truncate_right() {
+        assert_eq!(truncate_right("next: So What", 20), "next: So What");
+        assert_eq!(truncate_right("next: So What", 9), "next: So…");
+        assert_eq!(truncate_right("next: 日本語", 10), "next: 日…");
+        assert_eq!(truncate_right("next: 日本語", 10).width(), 9);
+        assert_eq!(truncate_right("next", 0), "");
+    }
+
+    #[test]
+    fn test_seek_time_clamps_to_bar() {
+        let (start, length, duration) = (8, 24, 240);
+
+        assert_eq!(seek_time(0, start, length, duration), 5);
+        assert_eq!(seek_time(start, start, length, duration), 5);
+        assert_eq!(seek_time(start + length - 1, start, length, duration), 235);
+        assert_eq!(seek_time(200, start, length, duration), 235);
+        assert_eq!(seek_time(10, start, 0, duration), 0);
+    }
+
+    #[test]
+    fn test_timestamp() {
+        let file = AudioFile {
+            path: PathBuf::from("/music/mix.mp3"),
+            title: String::from("Mix"),
+            artist: String::from("DJ"),
+            album: String::from("Live"),
+            year: None,
+            disc: None,
+            track: 1,
+            duration: 7200,
+            start: 0,
+        };
+        let template = "{artist} – {title} @ {elapsed}";
+
+        assert_eq!(timestamp(template, &file, 754), "DJ – Mix @ 12:34");
+        assert_eq!(timestamp(template, &file, 3725), "DJ – Mix @ 1:02:05");
+    }
+}
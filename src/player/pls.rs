@@ -0,0 +1,172 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use anyhow::bail;
+
+use super::{stream, tracks::check, valid_audio_ext, AudioFile};
+
+// An entry in a `.pls` playlist, from the `FileN`, `TitleN` and `LengthN` keys.
+#[derive(Default)]
+struct Entry {
+    file: Option<String>,
+    title: Option<String>,
+    length: Option<i64>,
+}
+
+/// Returns true if `path` is a `.pls` playlist file.
+pub fn is_pls(path: &PathBuf) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .map_or(false, |ext| ext.eq_ignore_ascii_case("pls"))
+}
+
+/// Reads the audio files listed in the `.pls` playlist at `path`, in order.
+///
+/// Local files are resolved relative to the playlist and URLs are kept as
+/// network streams. Entries that are malformed or can't be read are skipped.
+/// Fails if no entries can be played or if the first can't be decoded.
+pub fn audio_files(path: &PathBuf) -> Result<Vec<AudioFile>, anyhow::Error> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => bail!("could not read '{}'\n- `{}`", path.display(), e),
+    };
+
+    let dir = path.parent().map(PathBuf::from).unwrap_or_default();
+    // The error we get if we can't create an audio file.
+    let mut error: Option<anyhow::Error> = None;
+
+    let list = parse(&text)
+        .into_values()
+        .filter_map(|entry| {
+            let file = entry.file?;
+            // A negative or missing length means the length is unknown.
+            let duration = entry.length.filter(|&l| l > 0).unwrap_or(0) as usize;
+            let url = PathBuf::from(&file);
+
+            if stream::is_url(&url) {
+                return Some(stream::remote_file(&url, entry.title, duration));
+            }
+
+            let local = dir.join(&file);
+            if !valid_audio_ext(&local) {
+                return None;
+            }
+
+            match AudioFile::new(local) {
+                Ok(f) => Some(f),
+                Err(e) => {
+                    if error.is_none() {
+                        error = Some(e)
+                    }
+                    None
+                }
+            }
+        })
+        .enumerate()
+        .map(|(i, mut f)| {
+            // Number the tracks in playlist order, rather than by their tags.
+            f.track = i as u32 + 1;
+            f
+        })
+        .collect::<Vec<AudioFile>>();
+
+    match list.first() {
+        Some(first) => {
+            check(&first.path)?;
+        }
+        None => match error {
+            Some(e) => bail!(e),
+            None => bail!("no audio files listed in '{}'", path.display()),
+        },
+    }
+
+    Ok(list)
+}
+
+// Parses the INI-style `.pls` format into entries ordered by their number.
+// Lines that aren't `FileN`, `TitleN` or `LengthN` keys are ignored.
+fn parse(text: &str) -> BTreeMap<usize, Entry> {
+    let mut entries: BTreeMap<usize, Entry> = BTreeMap::new();
+
+    for line in text.lines().map(str::trim) {
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim().to_lowercase(), value.trim()),
+            None => continue,
+        };
+
+        let (name, number) = match key.find(|c: char| c.is_ascii_digit()) {
+            Some(pos) => key.split_at(pos),
+            None => continue,
+        };
+
+        let number = match number.parse::<usize>() {
+            Ok(n) => n,
+            Err(_) => continue,
+        };
+
+        if value.is_empty() {
+            continue;
+        }
+
+        let entry = entries.entry(number).or_default();
+        match name {
+            "file" => entry.file = Some(value.to_string()),
+            "title" => entry.title = Some(value.to_string()),
+            "length" => entry.length = value.parse().ok(),
+            _ => (),
+        }
+    }
+
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::find_assets_dir;
+
+    #[test]
+    fn test_parse_pls() {
+        let text = "[playlist]\n\
+            File2=two.mp3\n\
+            file1 = one.mp3\n\
+            Title1=One\n\
+            Length1=-1\n\
+            Title3=No file\n\
+            Length2=oops\n\
+            NumberOfEntries=3\n\
+            Version=2\n";
+
+        let entries = parse(text);
+        let files = entries
+            .values()
+            .map(|e| e.file.as_deref())
+            .collect::<Vec<_>>();
+
+        assert_eq!(files, [Some("one.mp3"), Some("two.mp3"), None]);
+        assert_eq!(entries[&1].title.as_deref(), Some("One"));
+        assert_eq!(entries[&1].length, Some(-1));
+        assert_eq!(entries[&2].length, None);
+    }
+
+    #[test]
+    fn test_pls_tracks_load_in_order() {
+        let path = find_assets_dir().join("pls").join("test_playlist.pls");
+        assert!(is_pls(&path));
+
+        let list = audio_files(&path).expect("should read the playlist");
+        let titles = list.iter().map(|f| f.title.as_str()).collect::<Vec<_>>();
+
+        // The missing file is skipped and the stream is kept without connecting.
+        assert_eq!(
+            titles,
+            ["test_audio_ogg", "test_audio_mp3", "Example Radio"]
+        );
+        assert_eq!(
+            list[2].path,
+            PathBuf::from("https://example.com/stream.mp3")
+        );
+        assert_eq!(list[2].duration, 0);
+        assert_eq!(list.iter().map(|f| f.track).collect::<Vec<_>>(), [1, 2, 3]);
+    }
+}
@@ -0,0 +1,68 @@
+use std::{
+    fs::File,
+    io::Read,
+    path::PathBuf,
+    thread::{self, JoinHandle},
+};
+
+use super::is_url;
+
+// The largest file that is read ahead, so that long mixes aren't held in memory.
+const MAX_BYTES: u64 = 64 * 1024 * 1024;
+
+// The tracks next to the current track, read into memory in the background so
+// that skipping to them doesn't wait on the disk. Used with `--prefetch`.
+#[derive(Default)]
+pub struct Prefetch {
+    // The tracks being read, or that have been read, by path.
+    entries: Vec<(PathBuf, JoinHandle<Option<Vec<u8>>>)>,
+}
+
+impl Prefetch {
+    // Reads `paths` ahead, if they aren't already, and drops any other tracks.
+    // Tracks that are no longer next to the current track, such as after
+    // moving a track or randomizing, are dropped this way.
+    pub fn update(&mut self, paths: &[&PathBuf]) {
+        self.entries.retain(|(path, _)| paths.contains(&path));
+
+        for &path in paths {
+            if is_url(path) || self.entries.iter().any(|(p, _)| p == path) {
+                continue;
+            }
+            let owned = path.to_owned();
+            let handle = thread::spawn(move || read(owned));
+            self.entries.push((path.to_owned(), handle));
+        }
+    }
+
+    // Whether or not `path` has been read ahead.
+    pub fn is_ready(&self, path: &PathBuf) -> bool {
+        self.entries
+            .iter()
+            .any(|(p, handle)| p == path && handle.is_finished())
+    }
+
+    // Takes the data of `path`, if it has been read ahead. Returns `None`
+    // rather than waiting for a track that is still being read.
+    pub fn take(&mut self, path: &PathBuf) -> Option<Vec<u8>> {
+        let i = self
+            .entries
+            .iter()
+            .position(|(p, handle)| p == path && handle.is_finished())?;
+        let (_, handle) = self.entries.remove(i);
+        handle.join().ok().flatten()
+    }
+}
+
+// The contents of the file at `path`, unless it is too large to read ahead.
+fn read(path: PathBuf) -> Option<Vec<u8>> {
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    if len > MAX_BYTES {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(len as usize);
+    file.read_to_end(&mut bytes).ok()?;
+    Some(bytes)
+}
@@ -0,0 +1,228 @@
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use hound::{SampleFormat, WavSpec, WavWriter};
+use rodio::{source::SeekError, Sample, Source};
+
+use crate::config::args;
+
+// The number of samples a source collects before writing them to the file.
+const CHUNK: usize = 4096;
+
+lazy_static::lazy_static! {
+    // The recording made with `--record`, shared by the players of the session.
+    static ref RECORDER: Option<Recorder> = args::record().map(|path| Recorder::new(path));
+}
+
+// The recording made with `--record`, if any.
+pub fn shared() -> Option<Recorder> {
+    RECORDER.to_owned()
+}
+
+// Closes the recording made with `--record`, if any, so that the file is
+// complete. Called once the players have been dropped on quit.
+pub fn finish() {
+    if let Some(recorder) = RECORDER.as_ref() {
+        recorder.finish();
+    }
+}
+
+struct Recording {
+    path: PathBuf,
+    // The format of the file, taken from the first track that is played.
+    spec: Option<WavSpec>,
+    // `None` until the first track is played, or if the file couldn't be written.
+    writer: Option<WavWriter<BufWriter<File>>>,
+}
+
+// Writes the audio that is played to a WAV file. The file is created with the
+// sample rate and channels of the first track that is played, and tracks in
+// another format are played without being recorded.
+#[derive(Clone)]
+pub struct Recorder(Arc<Mutex<Recording>>);
+
+impl Recorder {
+    pub fn new(path: &Path) -> Self {
+        Self(Arc::new(Mutex::new(Recording {
+            path: path.to_owned(),
+            spec: None,
+            writer: None,
+        })))
+    }
+
+    fn write(&self, samples: &[f32], channels: u16, sample_rate: u32) {
+        let mut recording = self.0.lock().expect("should not be poisoned");
+        let spec = WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample: 32,
+            sample_format: SampleFormat::Float,
+        };
+
+        if recording.spec.is_none() {
+            recording.spec = Some(spec);
+            recording.writer = WavWriter::create(&recording.path, spec).ok();
+        }
+        if recording.spec != Some(spec) {
+            return;
+        }
+
+        if let Some(writer) = recording.writer.as_mut() {
+            if samples.iter().any(|&s| writer.write_sample(s).is_err()) {
+                recording.writer = None;
+            }
+        }
+    }
+
+    // Writes what has been recorded so far, so that the file is valid while
+    // the recording continues.
+    pub fn flush(&self) {
+        let mut recording = self.0.lock().expect("should not be poisoned");
+        if let Some(writer) = recording.writer.as_mut() {
+            let _ = writer.flush();
+        }
+    }
+
+    // Closes the file. Anything played afterwards isn't recorded.
+    pub fn finish(&self) {
+        let mut recording = self.0.lock().expect("should not be poisoned");
+        if let Some(writer) = recording.writer.take() {
+            let _ = writer.finalize();
+        }
+    }
+}
+
+// A source that passes its samples to a `Recorder` as they are played. Without
+// a recorder it plays the source as it is.
+pub struct Tee<S> {
+    source: S,
+    recorder: Option<Recorder>,
+    // The channels and sample rate of the source.
+    format: (u16, u32),
+    // The samples played since they were last written.
+    buffer: Vec<f32>,
+}
+
+impl<S> Tee<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    pub fn new(source: S, recorder: Option<Recorder>) -> Self {
+        Self {
+            format: (source.channels(), source.sample_rate()),
+            source,
+            recorder,
+            buffer: vec![],
+        }
+    }
+}
+
+impl<S> Tee<S> {
+    fn write(&mut self) {
+        if let Some(recorder) = &self.recorder {
+            if !self.buffer.is_empty() {
+                let (channels, sample_rate) = self.format;
+                recorder.write(&self.buffer, channels, sample_rate);
+                self.buffer.clear();
+            }
+        }
+    }
+}
+
+impl<S> Iterator for Tee<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sample = self.source.next();
+
+        if self.recorder.is_some() {
+            match sample {
+                Some(s) => self.buffer.push(s.to_f32()),
+                None => self.write(),
+            }
+            if self.buffer.len() >= CHUNK {
+                self.write();
+            }
+        }
+        sample
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.source.size_hint()
+    }
+}
+
+impl<S> Source for Tee<S>
+where
+    S: Source,
+    S::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.source.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.source.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.source.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.source.try_seek(pos)
+    }
+}
+
+// Sources that are removed from the sink before they finish, such as when
+// skipping a track, write what was played of them.
+impl<S> Drop for Tee<S> {
+    fn drop(&mut self) {
+        self.write();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hound::WavReader;
+
+    use super::*;
+    use crate::player::tracks::decode;
+    use crate::utils::find_assets_dir;
+
+    #[test]
+    fn test_recording_is_valid_wav() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        let path = dir.path().join("mixtape.wav");
+        let recorder = Recorder::new(&path);
+
+        let source = decode(&find_assets_dir().join("test_wav_audio.wav")).unwrap();
+        let (channels, sample_rate) = (source.channels(), source.sample_rate());
+        let played = Tee::new(source, Some(recorder.to_owned())).count();
+
+        // A track in another format isn't recorded.
+        let other = rodio::buffer::SamplesBuffer::new(channels, sample_rate + 1, vec![0.5f32; 64]);
+        Tee::new(other, Some(recorder.to_owned())).for_each(drop);
+        recorder.finish();
+
+        let reader = WavReader::open(&path).expect("should be a valid WAV file");
+        assert_eq!(reader.spec().channels, channels);
+        assert_eq!(reader.spec().sample_rate, sample_rate);
+        assert!(played > 0);
+        assert_eq!(reader.len() as usize, played);
+    }
+}
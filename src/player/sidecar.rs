@@ -0,0 +1,101 @@
+use std::{fs, path::Path};
+
+use serde::Deserialize;
+
+// The file next to the audio files of an album that describes their tracks,
+// for albums that were ripped without tags.
+pub const SIDECAR_FILE: &str = "metadata.json";
+
+// The contents of a `metadata.json` file. The album fields apply to every
+// track that doesn't set its own:
+//
+// {
+//   "artist": "Miles Davis",
+//   "album": "Kind of Blue",
+//   "tracks": [
+//     { "file": "01.mp3", "title": "So What", "track": 1 },
+//     { "file": "02.mp3", "title": "Freddie Freeloader", "track": 2 }
+//   ]
+// }
+#[derive(Debug, Default, Deserialize)]
+struct Sidecar {
+    artist: Option<String>,
+    album: Option<String>,
+    #[serde(default)]
+    tracks: Vec<SidecarTrack>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SidecarTrack {
+    // The file name of the track, without its directory.
+    file: String,
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    track: Option<u32>,
+}
+
+// The metadata of a track read from the sidecar file.
+#[derive(Debug, Default, PartialEq)]
+pub struct TrackMetadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub track: Option<u32>,
+}
+
+// Reads the metadata of the audio file at `path` from the `metadata.json` file
+// in its directory. `None` if there is no sidecar file, it isn't valid JSON or
+// it doesn't list the file.
+pub fn read(path: &Path) -> Option<TrackMetadata> {
+    let name = path.file_name()?.to_str()?;
+    let text = fs::read_to_string(path.with_file_name(SIDECAR_FILE)).ok()?;
+    let sidecar = serde_json::from_str::<Sidecar>(&text).ok()?;
+
+    let track = sidecar.tracks.into_iter().find(|t| t.file == name)?;
+    Some(TrackMetadata {
+        title: track.title,
+        artist: track.artist.or(sidecar.artist),
+        album: track.album.or(sidecar.album),
+        track: track.track,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_sidecar() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        let track = dir.path().join("01.mp3");
+        assert_eq!(read(&track), None);
+
+        let sidecar = |text: &str| {
+            fs::write(dir.path().join(SIDECAR_FILE), text).expect("write sidecar");
+            read(&track)
+        };
+
+        let text = r#"{
+            "artist": "Miles Davis",
+            "album": "Kind of Blue",
+            "tracks": [
+                { "file": "01.mp3", "title": "So What", "track": 1 },
+                { "file": "02.mp3", "title": "Freddie Freeloader", "artist": "Miles" }
+            ]
+        }"#;
+        assert_eq!(
+            sidecar(text),
+            Some(TrackMetadata {
+                title: Some(String::from("So What")),
+                artist: Some(String::from("Miles Davis")),
+                album: Some(String::from("Kind of Blue")),
+                track: Some(1),
+            })
+        );
+
+        assert_eq!(sidecar(r#"{ "tracks": [{ "file": "02.mp3" }] }"#), None);
+        assert_eq!(sidecar(r#"{ "tracks": [{ "title": "So What" }] }"#), None);
+        assert_eq!(sidecar("not json"), None);
+    }
+}
@@ -0,0 +1,120 @@
+use std::{
+    process::{Child, Command, Stdio},
+    sync::Mutex,
+};
+
+use anyhow::bail;
+use cursive::Cursive;
+
+use crate::config::args;
+use crate::fuzzy::ErrorView;
+
+use super::{player_view, PlayerStatus, PlayerView};
+
+lazy_static::lazy_static! {
+    static ref INHIBITOR: Mutex<Inhibitor> = Mutex::new(Inhibitor::default());
+}
+
+#[derive(Default)]
+struct Inhibitor {
+    // The process holding the inhibitor. `Some` while sleep is prevented.
+    child: Option<Child>,
+    // Whether or not the inhibitor couldn't be started, in which case we
+    // stop trying.
+    is_unavailable: bool,
+}
+
+// Prevents the system from sleeping while the player is playing, with
+// `--prevent-sleep`, and allows it again when paused, stopped or closed.
+pub fn update(siv: &mut Cursive) {
+    if !args::prevent_sleep() {
+        return;
+    }
+
+    let is_playing = siv
+        .call_on_name(player_view::NAME, |v: &mut PlayerView| {
+            v.player().status == PlayerStatus::Playing
+        })
+        .unwrap_or(false);
+
+    let mut inhibitor = match INHIBITOR.lock() {
+        Ok(inhibitor) => inhibitor,
+        Err(_) => return,
+    };
+
+    // The inhibitor process exits early if the power manager refuses it.
+    let has_exited = inhibitor
+        .child
+        .as_mut()
+        .map_or(false, |child| matches!(child.try_wait(), Ok(Some(_))));
+
+    let result = if has_exited {
+        inhibitor.child = None;
+        Err(anyhow::anyhow!(
+            "--prevent-sleep: the power manager refused the request"
+        ))
+    } else if !is_playing {
+        inhibitor.release();
+        Ok(())
+    } else if inhibitor.child.is_none() && !inhibitor.is_unavailable {
+        inhibit().map(|child| inhibitor.child = Some(child))
+    } else {
+        Ok(())
+    };
+
+    if let Err(e) = result {
+        // Warn once, then carry on without it.
+        inhibitor.is_unavailable = true;
+        drop(inhibitor);
+        ErrorView::load(siv, e);
+    }
+}
+
+// Allows the system to sleep again. Called on quit.
+pub fn release() {
+    if let Ok(mut inhibitor) = INHIBITOR.lock() {
+        inhibitor.release();
+    }
+}
+
+impl Inhibitor {
+    fn release(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            _ = child.kill();
+            _ = child.wait();
+        }
+    }
+}
+
+// Starts a process that holds a sleep inhibitor for as long as it runs. The
+// process also exits with tap, so the inhibitor is never left behind.
+fn inhibit() -> Result<Child, anyhow::Error> {
+    let pid = std::process::id().to_string();
+
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("caffeinate");
+        command.args(["-i", "-w", &pid]);
+        command
+    } else if cfg!(target_os = "linux") {
+        let mut command = Command::new("systemd-inhibit");
+        command
+            .args(["--what=sleep:idle", "--who=tap", "--why=Playing audio"])
+            .args(["tail", "--pid", &pid, "-f", "/dev/null"]);
+        command
+    } else {
+        bail!("--prevent-sleep isn't supported on this platform")
+    };
+
+    match command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+    {
+        Ok(child) => Ok(child),
+        Err(_) => bail!(
+            "--prevent-sleep: could not start '{}'",
+            command.get_program().to_string_lossy()
+        ),
+    }
+}
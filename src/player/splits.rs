@@ -0,0 +1,103 @@
+use std::{fs, iter::once};
+
+use super::AudioFile;
+
+// Splits `file` into a track for each line of the `.splits` file with the same
+// name, such as `mix.splits` next to `mix.flac`, if there is one. Each line is
+// the time the track starts at, as `mm:ss` or `h:mm:ss`, and its title:
+//
+//     0:00 Intro
+//     4:12 The Second Song
+//
+// The tracks all play from the one file, which isn't decoded again when
+// moving between them.
+pub fn split(file: AudioFile) -> Vec<AudioFile> {
+    let text = match fs::read_to_string(file.path.with_extension("splits")) {
+        Ok(text) => text,
+        Err(_) => return vec![file],
+    };
+
+    let mut points = parse(&text);
+    // Split points past the end of the file are left out.
+    points.retain(|(start, _)| file.duration == 0 || *start < file.duration);
+
+    match points.first() {
+        None => return vec![file],
+        Some((0, _)) => (),
+        // The part before the first split point keeps the title of the file.
+        Some(_) => points.insert(0, (0, file.title.to_owned())),
+    }
+
+    let ends = points.iter().skip(1).map(|(start, _)| *start).chain(once(file.duration));
+
+    points
+        .iter()
+        .zip(ends)
+        .enumerate()
+        .map(|(i, ((start, title), end))| AudioFile {
+            title: title.to_owned(),
+            track: i as u32 + 1,
+            start: *start,
+            // The length of the last track is unknown if the file's is.
+            duration: end.saturating_sub(*start),
+            ..file.to_owned()
+        })
+        .collect()
+}
+
+// Parses the lines of a `.splits` file into the start time, in seconds, and
+// title of each track, sorted by start time. Blank lines, lines starting with
+// `#` and lines without a valid time are skipped.
+fn parse(text: &str) -> Vec<(usize, String)> {
+    let mut points = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (time, title) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            Some((parse_time(time)?, title.trim().to_string()))
+        })
+        .collect::<Vec<_>>();
+
+    points.sort_by_key(|(start, _)| *start);
+    points.dedup_by_key(|(start, _)| *start);
+    points
+}
+
+// Parses an `mm:ss` or `h:mm:ss` time into seconds. Fractions of a second are
+// ignored.
+fn parse_time(time: &str) -> Option<usize> {
+    let time = time.split('.').next()?;
+    let parts = time
+        .split(':')
+        .map(|part| part.parse::<usize>().ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    match parts[..] {
+        [mins, secs] if secs < 60 => Some(mins * 60 + secs),
+        [hours, mins, secs] if mins < 60 && secs < 60 => Some((hours * 60 + mins) * 60 + secs),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let points = parse("# a mix\n\n4:12 The Second Song\n0:00.50 Intro\n1:02:03 Last\nnope\n");
+        assert_eq!(
+            points,
+            vec![
+                (0, String::from("Intro")),
+                (252, String::from("The Second Song")),
+                (3723, String::from("Last")),
+            ]
+        );
+
+        assert_eq!(parse_time("0:60"), None);
+        assert_eq!(parse_time("12"), None);
+        assert_eq!(parse_time("90:00"), Some(5400));
+    }
+}
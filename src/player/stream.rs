@@ -0,0 +1,336 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Cursor, Read, Seek, SeekFrom},
+    path::PathBuf,
+    sync::Mutex,
+    time::Duration,
+};
+
+use anyhow::bail;
+use reqwest::{
+    blocking::{Client, Response},
+    header::{CONTENT_TYPE, USER_AGENT},
+    redirect::Policy,
+    Url,
+};
+use rodio::Decoder;
+
+use super::AudioFile;
+
+// How long to wait for the server to respond before giving up.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// The number of redirects to follow before giving up.
+const MAX_REDIRECTS: usize = 10;
+
+// The number of bytes at the start of a stream that are kept, so that the
+// decoder can rewind after probing the format.
+const PROBE_LEN: usize = 256 * 1024;
+
+lazy_static::lazy_static! {
+    // The stream that was last connected to, kept so that the connection made
+    // to read its headers and check that it can be decoded is the one played.
+    static ref KEPT: Mutex<Option<(PathBuf, Kept)>> = Mutex::new(None);
+}
+
+// A connection to a stream that hasn't been played yet.
+enum Kept {
+    Reader(HttpReader),
+    Decoder(Decoder<Input>),
+}
+
+/// The data read by the decoder, either from a local file, from a local file
+/// that was read ahead into memory, or from the network.
+pub enum Input {
+    File(BufReader<File>),
+    Memory(Cursor<Vec<u8>>),
+    Http(BufReader<HttpReader>),
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Input::File(r) => r.read(buf),
+            Input::Memory(r) => r.read(buf),
+            Input::Http(r) => r.read(buf),
+        }
+    }
+}
+
+impl Seek for Input {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            Input::File(r) => r.seek(pos),
+            Input::Memory(r) => r.seek(pos),
+            Input::Http(r) => r.seek(pos),
+        }
+    }
+}
+
+/// Reads the body of an http response.
+///
+/// The decoder requires a seekable reader, but a network stream can only be
+/// read forwards. The first `PROBE_LEN` bytes are kept, so that the decoder
+/// can rewind while probing the format, but seeking anywhere else fails.
+pub struct HttpReader<R = Response> {
+    // The response is wrapped so that the reader can be shared between threads.
+    response: Mutex<R>,
+    // The bytes received so far, until there are more than `PROBE_LEN`.
+    head: Vec<u8>,
+    // The number of bytes received from the response.
+    received: u64,
+    // The number of bytes read so far.
+    position: u64,
+}
+
+impl<R> HttpReader<R> {
+    fn new(response: R) -> Self {
+        HttpReader {
+            response: Mutex::new(response),
+            head: Vec::new(),
+            received: 0,
+            position: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for HttpReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Read the bytes that were kept after rewinding.
+        if self.position < self.received {
+            let start = self.position as usize;
+            let n = (&self.head[start..]).read(buf)?;
+            self.position += n as u64;
+            return Ok(n);
+        }
+
+        let response = self.response.get_mut().map_err(|_| io::ErrorKind::Other)?;
+        let n = response.read(buf)?;
+        if self.head.len() as u64 == self.received && self.head.len() + n <= PROBE_LEN {
+            self.head.extend_from_slice(&buf[..n]);
+        }
+        self.received += n as u64;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R> Seek for HttpReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::Current(n) => self.position.checked_add_signed(n),
+            SeekFrom::End(_) => None,
+        };
+
+        // Once bytes past the head have been received it can't be read again.
+        let rewindable = self.head.len() as u64 == self.received;
+
+        match target {
+            Some(n) if n == self.position || (rewindable && n <= self.received) => {
+                self.position = n;
+                Ok(n)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "network streams can't be seeked",
+            )),
+        }
+    }
+}
+
+/// Returns true if `path` is an http or https URL.
+pub fn is_url(path: &PathBuf) -> bool {
+    let s = path.to_string_lossy();
+    s.starts_with("http://") || s.starts_with("https://")
+}
+
+/// Opens a connection to the stream at `url`.
+pub fn open(url: &PathBuf) -> Result<HttpReader, anyhow::Error> {
+    Ok(HttpReader::new(connect(url)?))
+}
+
+/// Decodes the stream at `url`, using the connection that was kept for it if
+/// there is one.
+pub fn decode(url: &PathBuf) -> Result<Decoder<Input>, anyhow::Error> {
+    let reader = match take(url) {
+        Some(Kept::Decoder(source)) => return Ok(source),
+        Some(Kept::Reader(reader)) => reader,
+        None => open(url)?,
+    };
+
+    match Decoder::new(Input::Http(BufReader::new(reader))) {
+        Ok(s) => Ok(s),
+        Err(_) => bail!("could not decode '{}'", url.display()),
+    }
+}
+
+/// Keeps the decoded stream at `url` to be played, so that checking that the
+/// stream can be decoded doesn't need another connection.
+pub fn keep(url: &PathBuf, source: Decoder<Input>) {
+    store(url, Kept::Decoder(source));
+}
+
+// Replaces the kept connection, closing the previous one.
+fn store(url: &PathBuf, kept: Kept) {
+    if let Ok(mut last) = KEPT.lock() {
+        *last = Some((url.to_owned(), kept));
+    }
+}
+
+// Takes the connection kept for `url`. A connection to another stream is
+// closed, since it won't be played.
+fn take(url: &PathBuf) -> Option<Kept> {
+    match KEPT.lock().ok()?.take() {
+        Some((path, kept)) if &path == url => Some(kept),
+        _ => None,
+    }
+}
+
+/// Creates an audio file for the stream at `url`, checking that the server
+/// responds with something that looks like audio.
+///
+/// Streams don't have tags, so the title is taken from the station name sent
+/// by internet radio servers, if any, or else from the end of the URL.
+pub fn audio_file(url: &PathBuf) -> Result<AudioFile, anyhow::Error> {
+    let response = connect(url)?;
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_string())
+    };
+
+    // Servers often send `application/octet-stream` for audio, so only reject
+    // types that are certainly not audio. The decoder probes the format.
+    if let Some(content_type) = header(CONTENT_TYPE.as_str()) {
+        if content_type.starts_with("text/") || content_type.starts_with("image/") {
+            bail!(
+                "'{}' is not an audio stream ({})",
+                url.display(),
+                content_type
+            )
+        }
+    }
+
+    let title = header("icy-name").filter(|name| !name.is_empty());
+    let file = stream_file(url, response.url(), title, 0);
+
+    // The connection is kept to be decoded.
+    store(url, Kept::Reader(HttpReader::new(response)));
+
+    Ok(file)
+}
+
+/// Creates an audio file for the stream at `url` without connecting to it,
+/// for streams listed in playlist files.
+pub fn remote_file(url: &PathBuf, title: Option<String>, duration: usize) -> AudioFile {
+    match Url::parse(&url.to_string_lossy()) {
+        Ok(parsed) => stream_file(url, &parsed, title, duration),
+        Err(_) => AudioFile {
+            path: url.to_owned(),
+            title: title.unwrap_or_else(|| url.display().to_string()),
+            artist: String::from("None"),
+            album: String::from("stream"),
+            year: None,
+            disc: None,
+            track: 1,
+            duration,
+            start: 0,
+        },
+    }
+}
+
+// The title falls back to the end of the URL, after any redirects.
+fn stream_file(
+    url: &PathBuf,
+    final_url: &Url,
+    title: Option<String>,
+    duration: usize,
+) -> AudioFile {
+    let title = match title {
+        Some(title) => title,
+        None => final_url
+            .path_segments()
+            .and_then(|segments| segments.filter(|s| !s.is_empty()).last())
+            .unwrap_or("stream")
+            .to_string(),
+    };
+
+    AudioFile {
+        path: url.to_owned(),
+        title,
+        artist: final_url.host_str().unwrap_or("None").to_string(),
+        album: String::from("stream"),
+        year: None,
+        disc: None,
+        track: 1,
+        duration,
+        start: 0,
+    }
+}
+
+fn connect(url: &PathBuf) -> Result<Response, anyhow::Error> {
+    let client = Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .redirect(Policy::limited(MAX_REDIRECTS))
+        // Live streams never finish, so the body must not time out.
+        .timeout(None)
+        .build()?;
+
+    let response = match client
+        .get(url.to_string_lossy().as_ref())
+        .header(USER_AGENT, concat!("tap/", env!("CARGO_PKG_VERSION")))
+        .send()
+    {
+        Ok(r) => r,
+        Err(e) => bail!("could not connect to '{}'\n- `{}`", url.display(), e),
+    };
+
+    match response.status().is_success() {
+        true => Ok(response),
+        false => bail!(
+            "could not open '{}'\n- `{}`",
+            url.display(),
+            response.status()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_url() {
+        assert!(is_url(&PathBuf::from("https://example.com/stream.mp3")));
+        assert!(is_url(&PathBuf::from("http://example.com:8000/radio")));
+        assert!(!is_url(&PathBuf::from("/music/http://album")));
+        assert!(!is_url(&PathBuf::from("ftp://example.com/track.mp3")));
+    }
+
+    #[test]
+    fn test_http_reader_rewinds_after_probing() {
+        let body: Vec<u8> = (0..=255).cycle().take(PROBE_LEN + 100).collect();
+        let mut reader = HttpReader::new(Cursor::new(body.to_owned()));
+        let mut buf = [0; 27];
+
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(reader.seek(SeekFrom::Start(0)).unwrap(), 0);
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..], &body[..27]);
+        assert_eq!(reader.seek(SeekFrom::Current(-7)).unwrap(), 20);
+        assert!(reader.seek(SeekFrom::Start(28)).is_err());
+
+        // Bytes past the head can only be read once.
+        let mut rest = vec![];
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(&rest[..], &body[20..]);
+        assert!(reader.seek(SeekFrom::Start(0)).is_err());
+        assert_eq!(
+            reader.seek(SeekFrom::Current(0)).unwrap(),
+            body.len() as u64
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use std::{
+    io::{stdout, Write},
+    path::PathBuf,
+    process::Command,
+};
+
+use anyhow::bail;
+use cursive::{reexports::ncurses, Cursive};
+
+use crate::config::args;
+
+// Suspends the TUI to run `--shell-command`, or else `$SHELL`, in the directory
+// `path`, or in the parent of `path` if it is a file. The TUI is restored when
+// the command exits.
+pub fn open_shell(siv: &mut Cursive, path: PathBuf) -> Result<(), anyhow::Error> {
+    let dir = match path.is_dir() {
+        true => path,
+        false => match path.parent() {
+            Some(parent) => parent.to_path_buf(),
+            None => bail!("No parent"),
+        },
+    };
+
+    let mut command = match args::shell_command() {
+        Some(template) => {
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(template);
+            command
+        }
+        None => match std::env::var_os("SHELL") {
+            Some(shell) => Command::new(shell),
+            None => bail!(
+                "could not open a shell: '$SHELL' isn't set\n\
+                - set the command to run with `--shell-command <CMD>`"
+            ),
+        },
+    };
+
+    // Leave the alternate screen and stop mouse reporting, as the backend does
+    // on quit, so that the command has the terminal to itself.
+    ncurses::def_prog_mode();
+    ncurses::endwin();
+    print!("\x1B[?1002l");
+    _ = stdout().flush();
+
+    let status = command.current_dir(&dir).status();
+
+    print!("\x1B[?1002h");
+    _ = stdout().flush();
+    ncurses::reset_prog_mode();
+    ncurses::refresh();
+    siv.clear();
+
+    match status {
+        Ok(_) => Ok(()),
+        Err(e) => bail!(
+            "could not run '{}'\n- `{}`",
+            command.get_program().to_string_lossy(),
+            e
+        ),
+    }
+}
+
+// Switches to and from the alternate screen, saving and restoring the cursor.
+const ENTER_ALTERNATE_SCREEN: &str = "\x1B[?1049h";
+const LEAVE_ALTERNATE_SCREEN: &str = "\x1B[?1049l";
+
+const SHOW_CURSOR: &str = "\x1B[?25h";
+
+// Draws the interface in the alternate screen for as long as it is held, with
+// `args::clean_exit`, so that nothing drawn is left in the scrollback. The
+// backend switches screens itself only if the terminfo entry says to.
+pub struct AlternateScreen(bool);
+
+impl AlternateScreen {
+    pub fn enter() -> Self {
+        let clean_exit = args::clean_exit();
+        if clean_exit {
+            print!("{}", ENTER_ALTERNATE_SCREEN);
+            stdout().flush().unwrap_or_default();
+        }
+        Self(clean_exit)
+    }
+}
+
+impl Drop for AlternateScreen {
+    fn drop(&mut self) {
+        if self.0 {
+            print!("{}{}", LEAVE_ALTERNATE_SCREEN, SHOW_CURSOR);
+            stdout().flush().unwrap_or_default();
+        }
+    }
+}
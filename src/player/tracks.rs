@@ -0,0 +1,206 @@
+use std::{fs::File, io::BufReader, path::PathBuf};
+
+use anyhow::bail;
+use rodio::Decoder;
+
+use crate::error::TapError;
+
+use super::{
+    audio_file::unsupported_format,
+    discs, pls, splits,
+    stream::{self, Input},
+    valid_audio_ext, AudioFile,
+};
+
+/// Options for reading the audio files of an album.
+#[derive(Clone, Debug, Default)]
+pub struct AudioOpts {
+    /// Whether or not the discs of an album, in subdirectories such as `CD1`
+    /// and `CD2`, are read in turn as one album.
+    pub merge_discs: bool,
+    /// Tracks shorter than this many seconds are left out, unless every track
+    /// is.
+    pub min_track_seconds: usize,
+}
+
+/// Reads the audio files at `path`, sorted by album, then track number and title.
+///
+/// `path` can be a single audio file, a directory, a `.pls` playlist or an
+/// http(s) URL. Fails if no audio files are found or if the first audio file
+/// can't be decoded.
+pub fn audio_files(path: &PathBuf, opts: &AudioOpts) -> Result<Vec<AudioFile>, anyhow::Error> {
+    if stream::is_url(path) {
+        let file = stream::audio_file(path)?;
+        check(path)?;
+        return Ok(vec![file]);
+    }
+
+    // Playlist files keep their own order.
+    if pls::is_pls(path) {
+        return pls::audio_files(path);
+    }
+
+    // The discs of an album are played in turn, with `opts.merge_discs`.
+    if opts.merge_discs {
+        if let Some(discs) = discs::dirs(path) {
+            return discs::merged_files(&discs, opts);
+        }
+    }
+
+    // The error we get if we can't create an audio file.
+    let mut error: Option<anyhow::Error> = None;
+
+    // Collect the potential audio file paths.
+    let paths = match path.read_dir() {
+        Ok(path) => path
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|entry| entry.is_file())
+            .collect::<Vec<_>>(),
+        Err(_) => {
+            vec![path.to_owned()]
+        }
+    };
+
+    if paths.is_empty() {
+        bail!(TapError::NoAudio(format!("'{}' is empty", path.display())))
+    }
+
+    let unsupported = paths.iter().find_map(unsupported_format);
+
+    // The audio files comprising our playlist.
+    let list = paths
+        .into_iter()
+        .filter(|path| valid_audio_ext(path))
+        .filter_map(|path| match AudioFile::new(path) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                if error.is_none() {
+                    error = Some(e)
+                }
+                None
+            }
+        })
+        .collect::<Vec<AudioFile>>();
+
+    // Check the first track can be decoded.
+    match list.first() {
+        Some(first) => {
+            check(&first.path)?;
+        }
+        None => match (error, unsupported) {
+            (Some(e), _) => bail!(e),
+            (None, Some(format)) => bail!(TapError::NoAudio(format!(
+                "{} files can't be decoded by tap, in '{}'",
+                format,
+                path.display()
+            ))),
+            (None, None) => bail!(TapError::NoAudio(format!(
+                "no audio files detected in '{}'",
+                path.display()
+            ))),
+        },
+    }
+
+    let mut list = without_short_tracks(list, opts.min_track_seconds);
+    list.sort();
+
+    Ok(list.into_iter().flat_map(splits::split).collect())
+}
+
+// Removes the tracks shorter than `min_secs`, unless that would leave nothing
+// to play. Tracks with an unknown duration are kept.
+fn without_short_tracks(list: Vec<AudioFile>, min_secs: usize) -> Vec<AudioFile> {
+    let is_short = |f: &AudioFile| f.duration > 0 && f.duration < min_secs;
+
+    match list.iter().all(is_short) {
+        true => list,
+        false => list.into_iter().filter(|f| !is_short(f)).collect(),
+    }
+}
+
+// Checks that the track at `path` can be decoded. A stream is kept decoded to
+// be played, so that it's only connected to once.
+pub fn check(path: &PathBuf) -> Result<(), anyhow::Error> {
+    let source = decode(path)?;
+    if stream::is_url(path) {
+        stream::keep(path, source);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+thread_local! {
+    // The number of times `decode` was called on the current thread.
+    static DECODED: std::cell::Cell<usize> = std::cell::Cell::new(0);
+}
+
+pub fn decode(path: &PathBuf) -> Result<Decoder<Input>, anyhow::Error> {
+    #[cfg(test)]
+    DECODED.with(|count| count.set(count.get() + 1));
+
+    if stream::is_url(path) {
+        return stream::decode(path);
+    }
+
+    let source = match File::open(path.as_path()) {
+        Ok(inner) => match Decoder::new(Input::File(BufReader::new(inner))) {
+            Ok(s) => s,
+            Err(_) => bail!("could not decode '{}'", path.display()),
+        },
+        Err(_) => bail!("could not open '{}'", path.display()),
+    };
+    Ok(source)
+}
+
+#[cfg(test)]
+// The number of times `decode` was called on the current thread.
+pub fn decoded() -> usize {
+    DECODED.with(|count| count.get())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::create_working_dir;
+
+    #[test]
+    fn test_short_tracks_are_removed() {
+        let file = |title: &str, duration: usize| AudioFile {
+            path: PathBuf::from(title),
+            title: title.to_string(),
+            artist: String::from("artist"),
+            album: String::from("album"),
+            year: None,
+            disc: None,
+            track: 0,
+            duration,
+            start: 0,
+        };
+
+        let list = vec![file("sting", 3), file("song", 200), file("live", 0)];
+        let titles = |list: Vec<AudioFile>| list.into_iter().map(|f| f.title).collect::<Vec<_>>();
+
+        assert_eq!(
+            titles(without_short_tracks(list.clone(), 0)),
+            ["sting", "song", "live"]
+        );
+        assert_eq!(titles(without_short_tracks(list, 10)), ["song", "live"]);
+
+        // An album of only short tracks is left as it is.
+        let list = vec![file("sting", 3), file("jingle", 5)];
+        assert_eq!(titles(without_short_tracks(list, 10)), ["sting", "jingle"]);
+    }
+
+    #[test]
+    fn test_unsupported_format_error() {
+        let root = create_working_dir(&[], &[], &["a.wv", "cover.jpg"])
+            .expect("create temp dir")
+            .into_path();
+
+        let err = audio_files(&root, &AudioOpts::default()).expect_err("WavPack can't be decoded");
+        assert!(err
+            .to_string()
+            .starts_with("WavPack (.wv) files can't be decoded"));
+    }
+}
@@ -17,9 +17,11 @@ pub trait IntoInner {
 
 pub type InnerType<U> = <U as IntoInner>::T;
 
-// Maps the array to a single value, i.e. `[0, 1, 2]` -> `12`.
+// Maps the array to a single value, i.e. `[0, 1, 2]` -> `12`. Saturates at
+// `usize::MAX` rather than overflowing.
 pub fn concatenate(arr: &Vec<usize>) -> usize {
-    arr.iter().fold(0, |acc, x| acc * 10 + x)
+    arr.iter()
+        .fold(0, |acc, x| acc.saturating_mul(10).saturating_add(*x))
 }
 
 // Generates a random unsigned int in the given range.
@@ -27,6 +29,25 @@ pub fn random(range: Range<usize>) -> usize {
     thread_rng().gen_range(range)
 }
 
+// Generates a random index into `weights`, where the chance of each index is
+// its share of the total weight. Falls back to a uniform choice when every
+// weight is zero.
+pub fn weighted_random(weights: &[u64]) -> usize {
+    let total: u64 = weights.iter().sum();
+    if total == 0 {
+        return random(0..weights.len());
+    }
+
+    let mut target = thread_rng().gen_range(0..total);
+    for (i, &weight) in weights.iter().enumerate() {
+        if target < weight {
+            return i;
+        }
+        target -= weight;
+    }
+    weights.len() - 1
+}
+
 // Bounds a value by a minimum and maximum value.
 pub fn clamp<T: PartialOrd>(input: T, min: T, max: T) -> T {
     if input < min {
@@ -88,6 +109,37 @@ pub fn open_file_manager(path: PathBuf) -> Result<(), anyhow::Error> {
     }
 }
 
+#[cfg(feature = "clipboard")]
+lazy_static::lazy_static! {
+    // The clipboard is kept open, since on some platforms the copied text is
+    // only available while it is.
+    static ref CLIPBOARD: std::sync::Mutex<Option<arboard::Clipboard>> =
+        std::sync::Mutex::new(None);
+}
+
+// Copies `text` to the system clipboard.
+#[cfg(feature = "clipboard")]
+pub fn copy_to_clipboard(text: &str) -> Result<(), anyhow::Error> {
+    let mut clipboard = match CLIPBOARD.lock() {
+        Ok(clipboard) => clipboard,
+        Err(_) => bail!("the clipboard is unavailable"),
+    };
+
+    if clipboard.is_none() {
+        *clipboard = Some(arboard::Clipboard::new()?);
+    }
+
+    match clipboard.as_mut() {
+        Some(clipboard) => Ok(clipboard.set_text(text)?),
+        None => bail!("the clipboard is unavailable"),
+    }
+}
+
+#[cfg(not(feature = "clipboard"))]
+pub fn copy_to_clipboard(_: &str) -> Result<(), anyhow::Error> {
+    bail!("tap was built without the `clipboard` feature")
+}
+
 pub fn display_with_spinner<F, T>(
     action: F,
     path: &PathBuf,
@@ -104,12 +156,15 @@ where
         let ellipses = vec!["   ", ".  ", ".. ", "..."];
         let mut spinner = ellipses.iter().cycle();
         let mut is_showing = false;
+        let width = "[tap]: ...".len() + msg.len() + 1;
 
         loop {
             match rx.try_recv() {
                 Ok(should_exit) => {
                     if should_exit {
-                        print!("\r{: <1$}\r", "", 20);
+                        // Erase the spinner so that it isn't left behind once
+                        // the interface or later output is drawn.
+                        print!("\r{: <1$}\r", "", width);
                         stdout().flush().unwrap_or_default();
                         break;
                     }
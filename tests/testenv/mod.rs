@@ -50,6 +50,81 @@ impl TestEnv {
         );
     }
 
+    // Assert that calling tap with the specified arguments prints the expected output.
+    pub fn assert_output(&self, args: &[&str], expected: &str) {
+        self.assert_output_with_env(args, &[], expected)
+    }
+
+    // Assert that calling tap with the specified arguments and environment variables
+    // prints the expected output.
+    pub fn assert_output_with_env(&self, args: &[&str], envs: &[(&str, &str)], expected: &str) {
+        let mut cmd = self.command(".".as_ref(), args);
+        cmd.envs(envs.iter().copied());
+
+        let output = cmd.output().expect("tap output");
+        let stdout = String::from_utf8(output.stdout).expect("output should be utf8");
+
+        assert!(
+            stdout.contains(expected),
+            "\nThe output:\n`{}`\n\
+            does not contain the expected output:\n`{}`\n",
+            stdout,
+            expected
+        );
+    }
+
+    // Assert that calling tap with the specified arguments doesn't print the output.
+    pub fn assert_no_output(&self, args: &[&str], unexpected: &str) {
+        let output = self.run_command(".".as_ref(), args);
+        let stdout = String::from_utf8(output.stdout).expect("output should be utf8");
+
+        assert!(
+            !stdout.contains(unexpected),
+            "\nThe output:\n`{}`\n\
+            contains the unexpected output:\n`{}`\n",
+            stdout,
+            unexpected
+        );
+    }
+
+    // Assert that calling tap with the specified arguments exits with `code`.
+    pub fn assert_exit_code(&self, args: &[&str], code: i32) {
+        let output = self.run_command(".".as_ref(), args);
+        assert_eq!(output.status.code(), Some(code));
+    }
+
+    // Assert that calling tap with the specified arguments doesn't produce the error.
+    pub fn assert_no_error_msg(&self, args: &[&str], unexpected: &str) {
+        let output = self.run_command(".".as_ref(), args);
+        let stderr = String::from_utf8(output.stderr).expect("error message should be utf8");
+
+        assert!(
+            !stderr.contains(unexpected),
+            "\nThe error message:\n`{}`\n\
+            contains the unexpected message:\n`{}`\n",
+            stderr,
+            unexpected
+        );
+    }
+
+    // Assert that calling tap with the specified arguments and environment variables
+    // produces an error message without any escape sequences.
+    pub fn assert_plain_error_msg(&self, args: &[&str], envs: &[(&str, &str)]) {
+        let mut cmd = self.command(".".as_ref(), args);
+        cmd.envs(envs.iter().copied());
+
+        let output = cmd.output().expect("tap output");
+        let stderr = String::from_utf8(output.stderr).expect("error message should be utf8");
+
+        assert!(stderr.contains("[tap error]"));
+        assert!(
+            !stderr.contains('\x1b'),
+            "\nThe error message:\n`{:?}`\n\
+            contains escape sequences\n",
+            stderr
+        );
+    }
+
     pub fn assert_normalized_paths(&self, expected: &[&str]) {
         let output = self.run_command(".".as_ref(), &[]);
         let stderr = normalize(output);
@@ -68,11 +143,14 @@ impl TestEnv {
     }
 
     fn run_command(&self, path: &Path, args: &[&str]) -> process::Output {
+        self.command(path, args).output().expect("tap output")
+    }
+
+    fn command(&self, path: &Path, args: &[&str]) -> process::Command {
         let mut cmd = process::Command::new(&self.tap_exe);
         cmd.current_dir(self.temp_dir.path().join(path));
         cmd.args(args);
-
-        cmd.output().expect("tap output")
+        cmd
     }
 }
 
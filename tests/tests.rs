@@ -7,8 +7,25 @@ use crate::testenv::TestEnv;
 
 #[test]
 fn test_empty_dir_error() {
-    let te = TestEnv::new(&["one", "one/two"], &[], &[]);
-    te.assert_error_msg(&[], "is empty");
+    let te = TestEnv::new(&[], &[], &["foo.txt"]);
+    te.assert_error_msg(&[], "set a default directory");
+}
+
+#[test]
+fn test_no_audio_opens_finder() {
+    let te = TestEnv::new(&["one/two_a", "one/two_b"], &[], &["one/foo.txt"]);
+    te.assert_no_error_msg(&[], "no audio");
+}
+
+#[test]
+fn test_exit_codes() {
+    let te = TestEnv::new(&["one"], &[("one/a.mp3", "test_mp3_audio.mp3")], &[]);
+    te.assert_exit_code(&["--analyze", "--automate", "one"], 2);
+    te.assert_exit_code(&["not_a_path"], 3);
+
+    let te = TestEnv::new(&[], &[], &["foo.txt"]);
+    te.assert_exit_code(&[], 4);
+    te.assert_exit_code(&["--automate", "."], 4);
 }
 
 #[test]
@@ -21,6 +38,51 @@ fn test_no_audio_error() {
     te.assert_error_msg(&[], "no audio");
 }
 
+#[test]
+fn test_no_color_error_is_plain() {
+    let te = TestEnv::new(&["one"], &[("one/a.mp3", "test_mp3_audio.mp3")], &[]);
+    te.assert_plain_error_msg(&["not_a_path"], &[("NO_COLOR", "1")]);
+}
+
+#[test]
+fn test_dump_config_shows_color_argument() {
+    let te = TestEnv::new(&["one"], &[("one/a.mp3", "test_mp3_audio.mp3")], &[]);
+    let args = ["--dump-config", "--color", "fg=ff9999", "--fps", "10"];
+    te.assert_output(&args, "fg = \"ff9999\"  # --color");
+    te.assert_output(&args, "bg = \"1f211d\"  # default");
+    te.assert_output(&args, "fps = 10  # command line");
+}
+
+#[test]
+fn test_config_file_from_env() {
+    let te = TestEnv::new(&["one"], &[("one/a.mp3", "test_mp3_audio.mp3")], &[]);
+    std::fs::write(te.temp_dir.path().join("tap.toml"), "[colors]\nfg = \"ff9999\"\n")
+        .expect("config file");
+
+    let envs = [("TAP_CONFIG", "tap.toml")];
+    te.assert_output_with_env(&["--dump-config"], &envs, "fg = \"ff9999\"  # config file");
+    te.assert_output_with_env(
+        &["--dump-config", "--color", "fg=268bd2"],
+        &envs,
+        "fg = \"268bd2\"  # --color",
+    );
+}
+
+#[test]
+fn test_doctor_reports_problems() {
+    let te = TestEnv::new(
+        &["one", "two"],
+        &[
+            ("one/a.mp3", "test_mp3_audio.mp3"),
+            ("one/b.mp3", "test_audio_invalid.mp3"),
+            ("two/c.mp3", "test_audio_no_tags.mp3"),
+        ],
+        &[],
+    );
+    te.assert_output(&["--doctor", "."], "undecodable\tone/b.mp3\nuntagged\ttwo/c.mp3\n");
+    te.assert_no_output(&["--doctor", "."], "a.mp3");
+}
+
 #[test]
 fn test_multiple_audio_files_success() {
     let te = TestEnv::new(
@@ -78,6 +140,22 @@ fn test_find_two_audio_dirs() {
     te.assert_normalized_paths(&["one", "two"]);
 }
 
+#[test]
+fn test_autoplay_opens_player() {
+    let te = TestEnv::new(
+        &["one", "two"],
+        &[
+            ("one/a.mp3", "test_mp3_audio.mp3"),
+            ("two/b.mp3", "test_mp3_audio.mp3"),
+        ],
+        &[],
+    );
+    // Playing an album adds it to the front of the queue, which otherwise only
+    // holds the next random track.
+    te.assert_error_msg(&["--autoplay", "first"], "one\", 0), (\"");
+    te.assert_no_error_msg(&[], "0), (\"");
+}
+
 #[test]
 fn test_exclude_empty_dir() {
     let te = TestEnv::new(
@@ -144,3 +222,14 @@ fn test_single_audio_dir() {
     );
     te.assert_success(&[]);
 }
+
+#[test]
+fn test_single_file_opens_player() {
+    let te = TestEnv::new(&["one"], &[("one/a.mp3", "test_mp3_audio.mp3")], &[]);
+    let playlist = te.temp_dir.path().join("one/list.pls");
+    std::fs::write(playlist, "[playlist]\nFile1=a.mp3\nNumberOfEntries=1\n")
+        .expect("write playlist");
+
+    te.assert_success(&["one/a.mp3"]);
+    te.assert_success(&["one/list.pls"]);
+}